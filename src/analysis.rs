@@ -0,0 +1,236 @@
+//! Contain the analysis of the resources a program needs, computed without
+//! simulating the program. The module is **unstable**.
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::RuntimeError;
+use crate::grammar::ast;
+use crate::semantics::check_semantics;
+use crate::transpiler::{expand, register_sizes, transpile, Basis, Qubit};
+
+/// Bytes of an amplitude of the state-vector, a pair of `f64`.
+const AMPLITUDE_SIZE: u64 = 16;
+
+/// The resources a program needs.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProgramStats {
+    /// Number of qubits, summing all the quantum registers.
+    pub qubits: usize,
+    /// Number of classical bits, summing all the classical registers.
+    pub clbits: usize,
+    /// Number of `U` gates once all the gate calls are inlined.
+    pub u_count: usize,
+    /// Number of `CX` gates once all the gate calls are inlined.
+    pub cx_count: usize,
+    /// Number of applications of each gate called from the program, with a
+    /// gate call on registers counting once per qubit of the registers.
+    pub gate_counts: BTreeMap<String, usize>,
+    /// Number of layers of `U` and `CX` gates, where the gates of a layer act
+    /// on different qubits.
+    pub depth: usize,
+    /// Number of layers of `CX` gates, where the gates of a layer act on
+    /// different qubits.
+    pub two_qubit_depth: usize,
+    /// Number of qubits measured, with a measurement of a register counting
+    /// once per qubit.
+    pub measurements: usize,
+    /// Number of conditional statements.
+    pub conditionals: usize,
+    /// Bytes of the state-vector simulating the program, 16 bytes per
+    /// amplitude, or `u64::MAX` if the amount does not fit.
+    pub statevector_bytes: u64,
+}
+
+/// Return the resources `program` needs. The function neither simulates the
+/// program nor allocates its state-vector, so it is safe to use on programs
+/// too large to simulate.
+///
+/// Gates under conditionals count as if they always run.
+///
+/// # Errors
+///
+/// The function fails if the program has semantic errors, or calls opaque
+/// gates, as [`transpile()`] does.
+///
+/// [`transpile()`]: ../transpiler/fn.transpile.html
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::parse_and_link;
+/// use qasmsim::analysis::analyze;
+///
+/// let program = parse_and_link(r#"
+/// OPENQASM 2.0;
+/// include "qelib1.inc";
+/// qreg q[2];
+/// creg c[2];
+/// h q;
+/// cx q[0], q[1];
+/// measure q -> c;
+/// "#)?;
+/// let stats = analyze(&program)?;
+///
+/// assert_eq!(stats.qubits, 2);
+/// assert_eq!(stats.gate_counts["h"], 2);
+/// assert_eq!((stats.u_count, stats.cx_count), (2, 1));
+/// assert_eq!((stats.depth, stats.two_qubit_depth), (2, 1));
+/// assert_eq!(stats.measurements, 2);
+/// assert_eq!(stats.statevector_bytes, 64);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn analyze(program: &ast::OpenQasmProgram) -> Result<ProgramStats, RuntimeError> {
+    let semantics = check_semantics(program).map_err(|mut errors| errors.remove(0))?;
+    let mut stats = ProgramStats {
+        qubits: semantics.quantum_memory_size,
+        clbits: semantics.classical_memory_size,
+        statevector_bytes: statevector_bytes(semantics.quantum_memory_size),
+        ..ProgramStats::default()
+    };
+
+    let sizes = register_sizes(program);
+    for span in &program.program {
+        let operation = match &*span.node {
+            ast::Statement::QuantumOperation(operation) => operation,
+            ast::Statement::Conditional(_, _, operation) => {
+                stats.conditionals += 1;
+                operation
+            }
+            _ => continue,
+        };
+        if let ast::QuantumOperation::Unitary(ast::UnitaryOperation(name, _, args)) = operation {
+            *stats.gate_counts.entry(name.clone()).or_default() += expand(args, &sizes).len();
+        }
+    }
+
+    let flat = transpile(program, Basis::Primitive)?;
+    let mut layers: HashMap<Qubit, (usize, usize)> = HashMap::new();
+    for span in &flat.program {
+        let operation = match &*span.node {
+            ast::Statement::QuantumOperation(operation)
+            | ast::Statement::Conditional(_, _, operation) => operation,
+            _ => continue,
+        };
+        let (args, is_cx) = match operation {
+            ast::QuantumOperation::Unitary(ast::UnitaryOperation(name, _, args)) => {
+                (args, name == "CX")
+            }
+            ast::QuantumOperation::Measure(source, target) => {
+                let args = [source.clone(), target.clone()];
+                stats.measurements += expand(&args, &sizes).len();
+                continue;
+            }
+            ast::QuantumOperation::Reset(_) => continue,
+        };
+        for qubits in expand(args, &sizes) {
+            if is_cx {
+                stats.cx_count += 1;
+            } else {
+                stats.u_count += 1;
+            }
+            let (layer, two_qubit_layer) = qubits
+                .iter()
+                .map(|qubit| layers.get(qubit).copied().unwrap_or_default())
+                .fold((0, 0), |(a, b), (c, d)| (a.max(c), b.max(d)));
+            let layers_after = (layer + 1, two_qubit_layer + is_cx as usize);
+            for qubit in qubits {
+                layers.insert(qubit, layers_after);
+            }
+            stats.depth = stats.depth.max(layers_after.0);
+            stats.two_qubit_depth = stats.two_qubit_depth.max(layers_after.1);
+        }
+    }
+    Ok(stats)
+}
+
+/// Return the bytes of a state-vector of `qubits` qubits, saturating at
+/// `u64::MAX`.
+fn statevector_bytes(qubits: usize) -> u64 {
+    u32::try_from(qubits)
+        .ok()
+        .and_then(|qubits| 1u64.checked_shl(qubits))
+        .and_then(|amplitudes| amplitudes.checked_mul(AMPLITUDE_SIZE))
+        .unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use crate::parse_and_link;
+
+    #[test]
+    fn test_analyze_counts_expanded_gates() {
+        let program = parse_and_link(indoc!(
+            "
+            OPENQASM 2.0;
+            include \"qelib1.inc\";
+            qreg q[3];
+            qreg r[1];
+            creg c[3];
+            h q;
+            ccx q[0], q[1], q[2];
+            cx q[0], r[0];
+            measure q -> c;
+            if (c == 7) x r[0];
+            if (c == 0) U(0, 0, 0) q[1];
+            "
+        ))
+        .unwrap();
+        let stats = analyze(&program).unwrap();
+        let gate_counts = vec![("U", 1), ("ccx", 1), ("cx", 1), ("h", 3), ("x", 1)]
+            .into_iter()
+            .map(|(name, count)| (name.to_string(), count))
+            .collect();
+        assert_eq!(
+            stats,
+            ProgramStats {
+                qubits: 4,
+                clbits: 3,
+                u_count: 3 + 9 + 1 + 1,
+                cx_count: 6 + 1,
+                gate_counts,
+                depth: 14,
+                two_qubit_depth: 7,
+                measurements: 3,
+                conditionals: 2,
+                statevector_bytes: 256,
+            }
+        );
+    }
+
+    #[test]
+    fn test_analyze_does_not_allocate_the_statevector() {
+        let program = parse_and_link(indoc!(
+            "
+            OPENQASM 2.0;
+            qreg a[20];
+            qreg b[20];
+            creg c[2];
+            U(0, 0, 0) a;
+            CX a, b;
+            measure a[0] -> c;
+            "
+        ))
+        .unwrap();
+        let stats = analyze(&program).unwrap();
+        assert_eq!(stats.qubits, 40);
+        assert_eq!((stats.u_count, stats.cx_count, stats.depth), (20, 20, 2));
+        assert_eq!(stats.measurements, 2);
+        assert_eq!(stats.statevector_bytes, 16 << 40);
+    }
+
+    #[test]
+    fn test_statevector_bytes_saturate() {
+        assert_eq!(statevector_bytes(59), 1 << 63);
+        assert_eq!(statevector_bytes(60), u64::MAX);
+        assert_eq!(statevector_bytes(64), u64::MAX);
+        assert_eq!(statevector_bytes(usize::MAX), u64::MAX);
+    }
+}
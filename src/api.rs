@@ -1,19 +1,22 @@
 use std::collections::HashMap;
 use std::iter::FromIterator;
 
-use crate::error::QasmSimError;
-use crate::grammar::{ast, parse_program};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::{OwnedQasmSimError, QasmSimError};
+use crate::grammar::{ast, parse_program, source_map::SourceMap};
 use crate::interpreter;
-use crate::linker::Linker;
+use crate::linker::{ChainedResolver, EmbeddedResolver, IncludeResolver, Linker, LinkerOptions};
 use crate::qe;
 
 pub type Result<'src, T> = std::result::Result<T, QasmSimError<'src>>;
 
-/// Return the default linker which includes the [`qelib1.inc`] library.
+/// Return the default resolver which includes the [`qelib1.inc`] library.
 ///
 /// [`qelib1.inc`]: https://github.com/Qiskit/openqasm/blob/master/examples/generic/qelib1.inc
-fn default_linker() -> Linker {
-    Linker::with_embedded(HashMap::from_iter(vec![(
+fn default_resolver() -> EmbeddedResolver {
+    EmbeddedResolver::new(HashMap::from_iter(vec![(
         "qelib1.inc".to_owned(),
         qe::QELIB1.to_owned(),
     )]))
@@ -46,13 +49,304 @@ fn default_linker() -> Linker {
 /// # Ok::<(), qasmsim::QasmSimError>(())
 /// ```
 pub fn parse_and_link(input: &str) -> Result<'_, ast::OpenQasmProgram> {
-    let linker = default_linker();
-    let program = parse_program(&input)?;
-    linker
-        .link(program)
-        .map_err(|err| QasmSimError::from((input, err)))
+    parse_and_link_with(input, &LinkerOptions::default())
+}
+
+/// Return the AST of `input` and link external sources as
+/// [`parse_and_link()`] does, but with an error which does not borrow
+/// `input`.
+///
+/// [`parse_and_link()`]: ./fn.parse_and_link.html
+///
+/// # Errors
+///
+/// The function fails in the same cases [`parse_and_link()`] does.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::{parse_and_link_owned, OwnedQasmSimError};
+///
+/// fn parse(source: String) -> Result<(), OwnedQasmSimError> {
+///     parse_and_link_owned(&source)?;
+///     Ok(())
+/// }
+///
+/// assert!(parse("OPENQASM 2.0;\ninclude \"missing.inc\";\n".to_string()).is_err());
+/// ```
+pub fn parse_and_link_owned(
+    input: &str,
+) -> std::result::Result<ast::OpenQasmProgram, OwnedQasmSimError> {
+    parse_and_link(input).map_err(QasmSimError::into_owned)
+}
+
+/// Return the AST of `input` and link external sources, looking for them in
+/// the filesystem as indicated by `options`.
+///
+/// # Errors
+///
+/// The function fails in the same cases [`parse_and_link()`] does.
+///
+/// [`parse_and_link()`]: ./fn.parse_and_link.html
+///
+/// # Examples
+///
+/// See [`LinkerOptions`] for an example.
+///
+/// [`LinkerOptions`]: ./struct.LinkerOptions.html
+pub fn parse_and_link_with<'src>(
+    input: &'src str,
+    options: &LinkerOptions,
+) -> Result<'src, ast::OpenQasmProgram> {
+    parse_and_link_with_source_map(input, options).map(|(program, _)| program)
+}
+
+/// Return the AST of `input`, linked as [`parse_and_link_with()`] does, along
+/// with the [`SourceMap`] relating the locations in the AST with the sources
+/// of the program and of the included libraries.
+///
+/// Use the source map for converting the errors of a later simulation into
+/// a [`QasmSimError`] pointing to the right file.
+///
+/// [`parse_and_link_with()`]: ./fn.parse_and_link_with.html
+/// [`SourceMap`]: ./grammar/source_map/struct.SourceMap.html
+/// [`QasmSimError`]: ./error/enum.QasmSimError.html
+///
+/// # Errors
+///
+/// The function fails in the same cases [`parse_and_link()`] does.
+///
+/// [`parse_and_link()`]: ./fn.parse_and_link.html
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::{parse_and_link_with_source_map, simulate, LinkerOptions, QasmSimError};
+///
+/// let source = r#"
+/// OPENQASM 2.0;
+/// include "qelib1.inc";
+/// qreg q[2];
+/// cx q;
+/// "#;
+/// let (program, source_map) = parse_and_link_with_source_map(source, &LinkerOptions::new())?;
+/// let error = simulate(&program).expect_err("wrong number of arguments");
+/// let error = QasmSimError::from((&source_map, error));
+/// # Ok::<(), QasmSimError>(())
+/// ```
+pub fn parse_and_link_with_source_map<'src>(
+    input: &'src str,
+    options: &LinkerOptions,
+) -> Result<'src, (ast::OpenQasmProgram, SourceMap<'src>)> {
+    let linker = Linker::new(default_resolver()).with_options(options.clone());
+    link(input, &linker)
+}
+
+/// Return the AST of `input` and link external sources, asking `resolver`
+/// for the libraries not embedded in the simulator, such as
+/// [`qelib1.inc`].
+///
+/// [`qelib1.inc`]: https://github.com/Qiskit/openqasm/blob/master/examples/generic/qelib1.inc
+///
+/// # Errors
+///
+/// The function fails in the same cases [`parse_and_link()`] does.
+///
+/// [`parse_and_link()`]: ./fn.parse_and_link.html
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::parse_and_link_with_resolver;
+/// use qasmsim::EmbeddedResolver;
+///
+/// let resolver = EmbeddedResolver::default().with_library("bell.inc", r#"
+/// gate bell a, b {
+///     U(pi/2, 0, pi) a;
+///     CX a, b;
+/// }
+/// "#);
+/// let program = parse_and_link_with_resolver(r#"
+/// OPENQASM 2.0;
+/// include "bell.inc";
+/// qreg q[2];
+/// bell q[0], q[1];
+/// "#, resolver)?;
+/// # use qasmsim::QasmSimError;
+/// # Ok::<(), QasmSimError>(())
+/// ```
+pub fn parse_and_link_with_resolver<R: IncludeResolver + 'static>(
+    input: &str,
+    resolver: R,
+) -> Result<'_, ast::OpenQasmProgram> {
+    let linker = Linker::new(
+        ChainedResolver::new()
+            .with_resolver(default_resolver())
+            .with_resolver(resolver),
+    );
+    link(input, &linker).map(|(program, _)| program)
+}
+
+fn link<'src>(
+    input: &'src str,
+    linker: &Linker,
+) -> Result<'src, (ast::OpenQasmProgram, SourceMap<'src>)> {
+    let program = parse_program(input)?;
+    let mut source_map = SourceMap::new(input);
+    let program = linker
+        .link(program, &mut source_map)
+        .map_err(|err| QasmSimError::from((&source_map, err)))?;
+    Ok((program, source_map))
+}
+
+/// The kinds of gates a program can call.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum GateKind {
+    /// One of the gates built into OPENQASM, `U` and `CX`.
+    Primitive,
+    /// A gate declared with `gate`.
+    Gate,
+    /// A gate declared with `opaque gate`, which has no body.
+    Opaque,
+}
+
+/// The signature and documentation of a gate, as returned by
+/// [`list_gates()`].
+///
+/// [`list_gates()`]: ./fn.list_gates.html
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GateInfo {
+    /// Name of the gate.
+    pub name: String,
+    /// Whether the gate is primitive, declared or opaque.
+    pub kind: GateKind,
+    /// Names of the real parameters.
+    pub real_args: Vec<String>,
+    /// Names of the quantum arguments.
+    pub args: Vec<String>,
+    /// Documentation, taken from the comment lines right before the
+    /// declaration.
+    pub doc: Option<String>,
+}
+
+impl GateInfo {
+    /// Return the number of qubits the gate acts on.
+    pub fn arity(&self) -> usize {
+        self.args.len()
+    }
+
+    fn primitive(name: &str, real_args: &[&str], args: &[&str], doc: &str) -> Self {
+        GateInfo {
+            name: name.into(),
+            kind: GateKind::Primitive,
+            real_args: real_args.iter().map(|&arg| arg.into()).collect(),
+            args: args.iter().map(|&arg| arg.into()).collect(),
+            doc: Some(doc.into()),
+        }
+    }
+}
+
+/// Return the gates `program` can call: the primitive gates `U` and `CX`
+/// followed by the gates declared in the program and its libraries, in the
+/// order they are declared. Only the first declaration of a gate is listed.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::{list_gates, parse_and_link, GateKind};
+///
+/// let program = parse_and_link(r#"
+/// OPENQASM 2.0;
+/// include "qelib1.inc";
+/// "#)?;
+/// let gates = list_gates(&program);
+/// let cx = gates.iter().find(|gate| gate.name == "cx").unwrap();
+///
+/// assert_eq!(gates[0].name, "U");
+/// assert_eq!(gates[0].kind, GateKind::Primitive);
+/// assert_eq!(cx.arity(), 2);
+/// assert_eq!(cx.doc.as_deref(), Some("controlled-NOT"));
+/// # use qasmsim::QasmSimError;
+/// # Ok::<(), QasmSimError>(())
+/// ```
+pub fn list_gates(program: &ast::OpenQasmProgram) -> Vec<GateInfo> {
+    let mut gates = vec![
+        GateInfo::primitive(
+            "U",
+            &["theta", "phi", "lambda"],
+            &["q"],
+            "Primitive single-qubit gate: U(theta, phi, lambda) = Rz(phi) Ry(theta) Rz(lambda)",
+        ),
+        GateInfo::primitive("CX", &[], &["c", "t"], "Primitive controlled-NOT gate"),
+    ];
+    for span in &program.program {
+        let gate = match &*span.node {
+            ast::Statement::GateDecl(name, real_args, args, _, doc) => {
+                (name, GateKind::Gate, real_args, args, doc)
+            }
+            ast::Statement::OpaqueGateDecl(name, real_args, args, doc) => {
+                (name, GateKind::Opaque, real_args, args, doc)
+            }
+            _ => continue,
+        };
+        let (name, kind, real_args, args, doc) = gate;
+        if gates.iter().any(|gate| &gate.name == name) {
+            continue;
+        }
+        gates.push(GateInfo {
+            name: name.clone(),
+            kind,
+            real_args: real_args.clone(),
+            args: args.clone(),
+            doc: doc.clone(),
+        });
+    }
+    gates
 }
 
+/// Return the documentation of the gates `program` can call, by name. Gates
+/// without documentation are left out.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::{gate_docs, parse_and_link};
+///
+/// let program = parse_and_link(r#"
+/// OPENQASM 2.0;
+/// include "qelib1.inc";
+///
+/// // Prepare a Bell pair.
+/// gate bell a, b { h a; cx a, b; }
+/// "#)?;
+/// let docs = gate_docs(&program);
+///
+/// assert_eq!(docs["h"], "Clifford gate: Hadamard");
+/// assert_eq!(docs["bell"], "Prepare a Bell pair.");
+/// # use qasmsim::QasmSimError;
+/// # Ok::<(), QasmSimError>(())
+/// ```
+pub fn gate_docs(program: &ast::OpenQasmProgram) -> HashMap<String, String> {
+    list_gates(program)
+        .into_iter()
+        .filter_map(|gate| Some((gate.name, gate.doc?)))
+        .collect()
+}
+
+pub use interpreter::runtime::check;
+
 pub use interpreter::runtime::simulate;
 
+pub use interpreter::runtime::simulate_from;
+
+pub use interpreter::runtime::simulate_with;
+
 pub use interpreter::runtime::simulate_with_shots;
+
+pub use interpreter::runtime::simulate_with_shots_from;
+
+pub use interpreter::runtime::SimulationOptions;
@@ -2,18 +2,36 @@
 
 use std::collections::HashMap;
 use std::convert;
+use std::ops::Range;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::{api, statevector::StateVector};
+use crate::{
+    api,
+    statevector::{DensityMatrix, StateVector},
+};
 
-use crate::error::QasmSimError;
+use crate::error::{OwnedQasmSimError, QasmSimError};
 use crate::interpreter::{Computation, Histogram};
+use crate::linker::LinkerOptions;
 
+pub use api::check;
+pub use api::gate_docs;
+pub use api::list_gates;
 pub use api::parse_and_link;
+pub use api::parse_and_link_owned;
+pub use api::parse_and_link_with;
+pub use api::parse_and_link_with_resolver;
+pub use api::parse_and_link_with_source_map;
 pub use api::simulate;
+pub use api::simulate_from;
+pub use api::simulate_with;
 pub use api::simulate_with_shots;
+pub use api::simulate_with_shots_from;
+pub use api::GateInfo;
+pub use api::GateKind;
+pub use api::SimulationOptions;
 
 macro_rules! measure {
     ($block:expr) => {{
@@ -85,6 +103,8 @@ pub struct Execution {
     memory: HashMap<String, u64>,
     histogram: Option<Histogram>,
     times: ExecutionTimes,
+    quantum_registers: HashMap<String, Range<usize>>,
+    snapshots: HashMap<String, StateVector>,
 }
 
 impl Execution {
@@ -102,6 +122,8 @@ impl Execution {
             memory,
             histogram,
             times,
+            quantum_registers: HashMap::new(),
+            snapshots: HashMap::new(),
         }
     }
 
@@ -129,6 +151,55 @@ impl Execution {
     pub fn times(&self) -> &ExecutionTimes {
         &self.times
     }
+
+    /// Return an associative map with quantum register names and the range
+    /// of qubits they occupy in the state-vector.
+    pub fn quantum_registers(&self) -> &HashMap<String, Range<usize>> {
+        &self.quantum_registers
+    }
+
+    /// Return an associative map with the labels of the `snapshot` statements
+    /// and the state-vector at those points of the program.
+    ///
+    /// See [`Computation::snapshots()`] for an example.
+    ///
+    /// [`Computation::snapshots()`]: ./struct.Computation.html#method.snapshots
+    pub fn snapshots(&self) -> &HashMap<String, StateVector> {
+        &self.snapshots
+    }
+
+    /// Return the reduced density matrix of the quantum register `name`, or
+    /// `None` if there is no such a quantum register.
+    ///
+    /// See [`Computation::register_density_matrix()`] for an example.
+    ///
+    /// [`Computation::register_density_matrix()`]: ./struct.Computation.html#method.register_density_matrix
+    pub fn register_density_matrix(&self, name: &str) -> Option<DensityMatrix> {
+        let range = self.quantum_registers.get(name)?;
+        let qubits: Vec<usize> = range.clone().collect();
+        Some(self.statevector.reduced_density_matrix(&qubits))
+    }
+
+    /// Return the entanglement entropy of the quantum register `name` with
+    /// the rest of the qubits, or `None` if there is no such a quantum
+    /// register.
+    ///
+    /// See [`Computation::register_entropy()`] for an example.
+    ///
+    /// [`Computation::register_entropy()`]: ./struct.Computation.html#method.register_entropy
+    pub fn register_entropy(&self, name: &str) -> Option<f64> {
+        let range = self.quantum_registers.get(name)?;
+        let qubits: Vec<usize> = range.clone().collect();
+        Some(self.statevector.entanglement_entropy(&qubits))
+    }
+
+    /// Return the purity of the reduced state of the quantum register
+    /// `name`, or `None` if there is no such a quantum register.
+    pub fn register_purity(&self, name: &str) -> Option<f64> {
+        let range = self.quantum_registers.get(name)?;
+        let qubits: Vec<usize> = range.clone().collect();
+        Some(self.statevector.reduced_purity(&qubits))
+    }
 }
 
 impl convert::From<(Computation, u128, u128)> for Execution {
@@ -143,6 +214,8 @@ impl convert::From<(Computation, u128, u128)> for Execution {
                 parsing_time,
                 simulation_time,
             },
+            quantum_registers: computation.quantum_registers().clone(),
+            snapshots: computation.snapshots().clone(),
         }
     }
 }
@@ -173,13 +246,127 @@ impl convert::From<(Computation, u128, u128)> for Execution {
 /// # Ok::<(), QasmSimError>(())
 /// ```
 pub fn run(input: &str, shots: Option<usize>) -> api::Result<'_, Execution> {
-    let (linked, parsing_time) = measure!({ parse_and_link(input) });
-    let (out, simulation_time) = measure!({
-        match shots {
-            None => simulate(&linked?),
-            Some(shots) => simulate_with_shots(&linked?, shots),
-        }
-    });
-    let out = out.map_err(|err| QasmSimError::from((input, err)));
+    let options = match shots {
+        None => SimulationOptions::new(),
+        Some(shots) => SimulationOptions::new().with_shots(shots),
+    };
+    run_with(input, options)
+}
+
+/// Parse and simulate the `input` OPENQASM program with optional `shots`, as
+/// [`run()`] does, but with an error which does not borrow `input`.
+///
+/// # Errors
+///
+/// The function fails in the same cases [`run()`] does.
+///
+/// [`run()`]: ./fn.run.html
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// let source = "OPENQASM 2.0;\nqreg q[2];\nU(0, 0, 0) q[2];\n".to_string();
+/// let error = thread::spawn(move || qasmsim::run_owned(&source, None))
+///     .join()
+///     .unwrap()
+///     .expect_err("index out of bounds");
+/// assert_eq!(error.code(), "E0101");
+/// ```
+pub fn run_owned(input: &str, shots: Option<usize>) -> Result<Execution, OwnedQasmSimError> {
+    run(input, shots).map_err(QasmSimError::into_owned)
+}
+
+/// Parse and simulate the `input` OPENQASM program with optional `shots`,
+/// starting from `initial_state` instead of |0⟩.
+///
+/// # Errors
+///
+/// Besides the errors [`run()`] can return, the function fails if the
+/// initial state is not valid for the program. See [`simulate_with()`].
+///
+/// [`run()`]: ./fn.run.html
+/// [`simulate_with()`]: ./fn.simulate_with.html
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::run_from;
+/// use qasmsim::statevector::{Complex, StateVector};
+///
+/// let one = StateVector::from_complex_bases(vec![Complex::from(0.0), Complex::from(1.0)]);
+/// let execution = run_from(r#"
+/// OPENQASM 2.0;
+/// include "qelib1.inc";
+/// qreg q[1];
+/// "#, None, one)?;
+/// # use qasmsim::QasmSimError;
+/// # Ok::<(), QasmSimError>(())
+/// ```
+pub fn run_from(
+    input: &str,
+    shots: Option<usize>,
+    initial_state: StateVector,
+) -> api::Result<'_, Execution> {
+    let options = SimulationOptions::new().with_initial_state(initial_state);
+    let options = match shots {
+        None => options,
+        Some(shots) => options.with_shots(shots),
+    };
+    run_with(input, options)
+}
+
+/// Parse and simulate the `input` OPENQASM program with the given `options`.
+/// The rest of `run*()` functions are particular cases of this one.
+///
+/// # Errors
+///
+/// Besides the errors [`run()`] can return, the function fails if the
+/// initial state is not valid for the program. See [`simulate_with()`].
+///
+/// [`run()`]: ./fn.run.html
+/// [`simulate_with()`]: ./fn.simulate_with.html
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use qasmsim::{run_with, Observer, SimulationOptions};
+///
+/// struct MeasurementLogger(Vec<u64>);
+///
+/// impl Observer for MeasurementLogger {
+///     fn on_measure(&mut self, _qubit: usize, _register: &str, _bit: usize, outcome: u64) {
+///         self.0.push(outcome);
+///     }
+/// }
+///
+/// let mut logger = MeasurementLogger(vec![]);
+/// let options = SimulationOptions::new()
+///     .with_shots(10)
+///     .with_observer(&mut logger);
+/// let execution = run_with(r#"
+/// OPENQASM 2.0;
+/// include "qelib1.inc";
+/// qreg q[2];
+/// creg c[2];
+/// x q[1];
+/// measure q -> c;
+/// "#, options)?;
+/// assert_eq!(logger.0.len(), 20);
+/// # use qasmsim::QasmSimError;
+/// # Ok::<(), QasmSimError>(())
+/// ```
+pub fn run_with<'src>(
+    input: &'src str,
+    options: SimulationOptions,
+) -> api::Result<'src, Execution> {
+    let (linked, parsing_time) =
+        measure!({ parse_and_link_with_source_map(input, &LinkerOptions::default()) });
+    let (program, source_map) = linked?;
+    let (out, simulation_time) = measure!({ simulate_with(&program, options) });
+    let out = out.map_err(|err| QasmSimError::from((&source_map, err)));
     Ok(Execution::from((out?, parsing_time, simulation_time)))
 }
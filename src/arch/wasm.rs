@@ -4,7 +4,9 @@
 #[macro_use]
 mod macros;
 mod computation;
+mod debugger;
 mod error;
+mod resolver;
 
 use console_error_panic_hook;
 use serde_wasm_bindgen;
@@ -13,10 +15,9 @@ use web_sys;
 
 use crate::api;
 use crate::error::QasmSimError;
-use crate::grammar::{
-    self,
-    ast
-};
+use crate::grammar::{self, ast, lexer};
+
+use resolver::JsResolver;
 
 macro_rules! adapt_parse_functions {
     ($($(#[$attr:meta])* $vis:vis fn $funcname:ident ($param:ident) => $parsefunc:path;)*) => {
@@ -26,7 +27,7 @@ macro_rules! adapt_parse_functions {
             $(#[$attr])* $vis fn $funcname(
                 $param: &str
             ) -> Result<JsValue, JsValue> {
-                $parsefunc(source)
+                $parsefunc($param)
                     .map(|v| serde_wasm_bindgen::to_value(&v).unwrap())
                     .map_err(|err| err.into())
             }
@@ -35,8 +36,12 @@ macro_rules! adapt_parse_functions {
 }
 
 #[wasm_bindgen]
-pub fn run(input: &str, shots: Option<usize>) -> Result<JsValue, JsValue> {
-    let (linked, parsing_time) = measure!("parsing", { api::parse_and_link(input) });
+pub fn run(
+    input: &str,
+    shots: Option<usize>,
+    resolver: Option<js_sys::Object>,
+) -> Result<JsValue, JsValue> {
+    let (linked, parsing_time) = measure!("parsing", { parse_and_link(input, resolver) });
     let (computation, simulation_time) = measure!("simulation", {
         match shots {
             None => api::simulate(&linked?),
@@ -63,13 +68,39 @@ pub fn simulate(program: JsValue, shots: Option<usize>) -> Result<JsValue, JsVal
     let openqasm_program: ast::OpenQasmProgram = serde_wasm_bindgen::from_value(program)?;
     let computation = match shots {
         None => api::simulate(&openqasm_program),
-        Some(shots) => api::simulate_with_shots(&openqasm_program, shots)
+        Some(shots) => api::simulate_with_shots(&openqasm_program, shots),
     };
-    computation
-        .map(|v| v.into())
+    computation.map(|v| v.into()).map_err(|err| err.into())
+}
+
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn parseAndLinkWith(source: &str, resolver: js_sys::Object) -> Result<JsValue, JsValue> {
+    parse_and_link(source, Some(resolver))
+        .map(|v| serde_wasm_bindgen::to_value(&v).unwrap())
         .map_err(|err| err.into())
 }
 
+#[wasm_bindgen]
+pub fn tokenize(source: &str, trivia: Option<bool>) -> JsValue {
+    let tokens = if trivia.unwrap_or(false) {
+        lexer::tokenize_with_trivia(source)
+    } else {
+        lexer::tokenize(source)
+    };
+    serde_wasm_bindgen::to_value(&tokens).unwrap()
+}
+
+fn parse_and_link(
+    source: &str,
+    resolver: Option<js_sys::Object>,
+) -> api::Result<'_, ast::OpenQasmProgram> {
+    match resolver {
+        None => api::parse_and_link(source),
+        Some(resolver) => api::parse_and_link_with_resolver(source, JsResolver::from(resolver)),
+    }
+}
+
 adapt_parse_functions! {
     pub fn parseAndLink(source) => api::parse_and_link;
     pub fn parseProgram(source) => grammar::parse_program;
@@ -10,7 +10,7 @@ use wasm_bindgen::prelude::JsValue;
 use crate::interpreter::Computation;
 use crate::statevector::StateVector;
 
-struct JsMemory<'a>(&'a HashMap<String, u64>);
+pub(super) struct JsMemory<'a>(pub(super) &'a HashMap<String, u64>);
 struct JsHistogram<'a>(&'a HashMap<String, Vec<(u64, usize)>>);
 
 impl From<JsMemory<'_>> for JsValue {
@@ -48,6 +48,11 @@ impl From<Computation> for JsValue {
             "probabilities" => as_typed_array(computation.probabilities().to_vec()),
             "memory" => JsMemory(computation.memory())
         );
+        let snapshots = Object::new();
+        for (label, statevector) in computation.snapshots() {
+            set!(&snapshots, label => statevector);
+        }
+        set!(&out, "snapshots" => snapshots);
         if let Some(histogram) = computation.histogram() {
             set!(&out,
                 "histogram" => JsHistogram(histogram)
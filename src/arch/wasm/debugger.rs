@@ -0,0 +1,117 @@
+#![cfg(target_arch = "wasm32")]
+
+use js_sys::{Array, Object};
+use serde_wasm_bindgen;
+use wasm_bindgen::prelude::{wasm_bindgen, JsValue};
+
+use crate::arch::wasm::computation::JsMemory;
+use crate::interpreter::{Debugger, MacroFrame, Stop};
+
+#[wasm_bindgen(js_name = Debugger)]
+pub struct JsDebugger(Debugger);
+
+#[wasm_bindgen(js_class = Debugger)]
+impl JsDebugger {
+    #[wasm_bindgen(constructor)]
+    pub fn new(source: &str) -> Result<JsDebugger, JsValue> {
+        Ok(JsDebugger(Debugger::new(source)?))
+    }
+
+    pub fn step(&mut self) -> Result<JsValue, JsValue> {
+        Ok(JsStop(self.0.step()?).into())
+    }
+
+    #[wasm_bindgen(js_name = stepInto)]
+    pub fn step_into(&mut self) -> Result<JsValue, JsValue> {
+        Ok(JsStop(self.0.step_into()?).into())
+    }
+
+    #[wasm_bindgen(js_name = stepOut)]
+    pub fn step_out(&mut self) -> Result<JsValue, JsValue> {
+        Ok(JsStop(self.0.step_out()?).into())
+    }
+
+    pub fn resume(&mut self) -> Result<JsValue, JsValue> {
+        Ok(JsStop(self.0.resume()?).into())
+    }
+
+    #[wasm_bindgen(js_name = addBreakpoint)]
+    pub fn add_breakpoint(&mut self, lineno: usize) {
+        self.0.add_breakpoint(lineno);
+    }
+
+    #[wasm_bindgen(js_name = removeBreakpoint)]
+    pub fn remove_breakpoint(&mut self, lineno: usize) {
+        self.0.remove_breakpoint(lineno);
+    }
+
+    #[wasm_bindgen(js_name = isFinished)]
+    pub fn is_finished(&self) -> bool {
+        self.0.is_finished()
+    }
+
+    pub fn location(&self) -> Option<usize> {
+        self.0.location().map(|location| location.offset)
+    }
+
+    pub fn lineno(&self) -> Option<usize> {
+        self.0.lineno()
+    }
+
+    #[wasm_bindgen(js_name = macroStack)]
+    pub fn macro_stack(&self) -> JsValue {
+        let stack = Array::new();
+        for frame in self.0.macro_stack() {
+            stack.push(&JsMacroFrame(&frame).into());
+        }
+        stack.into()
+    }
+
+    pub fn memory(&self) -> JsValue {
+        JsMemory(self.0.memory()).into()
+    }
+
+    pub fn statevector(&self) -> JsValue {
+        self.0.statevector().into()
+    }
+}
+
+struct JsStop(Stop);
+struct JsMacroFrame<'a>(&'a MacroFrame);
+
+impl From<JsStop> for JsValue {
+    fn from(value: JsStop) -> Self {
+        let obj = Object::new();
+        match value.0 {
+            Stop::Step => set!(&obj, "reason" => "step"),
+            Stop::Breakpoint(lineno) => set!(&obj,
+                "reason" => "breakpoint",
+                "lineno" => lineno as f64
+            ),
+            Stop::Finished => set!(&obj, "reason" => "finished"),
+        }
+        obj.into()
+    }
+}
+
+impl From<JsMacroFrame<'_>> for JsValue {
+    fn from(value: JsMacroFrame) -> Self {
+        let frame = value.0;
+        let real_args = Object::new();
+        for (name, value) in frame.real_args() {
+            set!(&real_args, name => *value);
+        }
+        let args = Object::new();
+        for (name, argument) in frame.args() {
+            set!(&args, name => serde_wasm_bindgen::to_value(argument).unwrap());
+        }
+        let obj = Object::new();
+        set!(&obj,
+            "name" => frame.name(),
+            "realArgs" => real_args,
+            "args" => args,
+            "nextOperation" => frame.next_operation() as f64
+        );
+        obj.into()
+    }
+}
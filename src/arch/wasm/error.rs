@@ -5,17 +5,21 @@ use std::convert::From;
 use js_sys::{self, Object};
 use wasm_bindgen::prelude::JsValue;
 
-use crate::error::QasmSimError;
-use crate::semantics::SemanticError;
+use crate::error::{Diagnostic, QasmSimError};
 use crate::interpreter::runtime::RuntimeError;
+use crate::semantics::SemanticError;
 
 impl From<QasmSimError<'_>> for JsValue {
     fn from(value: QasmSimError) -> Self {
         let message = format!("{}", &value);
+        let diagnostic = serde_wasm_bindgen::to_value(&Diagnostic::from(&value))
+            .expect("serialize the diagnostic");
         let obj = Object::new();
 
         set!(&obj,
             "message" => &message,
+            "code" => value.code(),
+            "diagnostic" => diagnostic,
             "toString" => js_sys::Function::new_no_args("return this.message")
         );
 
@@ -23,6 +27,23 @@ impl From<QasmSimError<'_>> for JsValue {
             QasmSimError::UnknownError(_) => {
                 set!(&obj, "type" => "Unknown");
             }
+            QasmSimError::InitialStateWidthMismatch { expected, given } => {
+                set!(&obj,
+                    "type" => "InitialStateWidthMismatch",
+                    "expected" => expected as f64,
+                    "given" => given as f64
+                );
+            }
+            QasmSimError::InitialStateNotNormalized => {
+                set!(&obj, "type" => "InitialStateNotNormalized");
+            }
+            QasmSimError::TooManyQubits { qubits, max_qubits } => {
+                set!(&obj,
+                    "type" => "TooManyQubits",
+                    "qubits" => qubits as f64,
+                    "maxQubits" => max_qubits as f64
+                );
+            }
             QasmSimError::InvalidToken {
                 lineno,
                 startpos,
@@ -82,7 +103,9 @@ impl From<QasmSimError<'_>> for JsValue {
             }
             QasmSimError::RedefinitionError {
                 symbol_name,
+                file,
                 lineno,
+                previous_file,
                 previous_lineno,
                 ..
             } => {
@@ -92,6 +115,12 @@ impl From<QasmSimError<'_>> for JsValue {
                     "previousLineNumber" => previous_lineno as f64,
                     "symbolName" => &symbol_name
                 );
+                if let Some(file) = file {
+                    set!(&obj, "file" => &file);
+                }
+                if let Some(previous_file) = previous_file {
+                    set!(&obj, "previousFile" => &previous_file);
+                }
             }
             QasmSimError::LibraryNotFound {
                 libpath, lineno, ..
@@ -102,6 +131,47 @@ impl From<QasmSimError<'_>> for JsValue {
                     "libPath" => &libpath
                 );
             }
+            QasmSimError::LibraryUnreadable {
+                libpath,
+                reason,
+                lineno,
+                ..
+            } => {
+                set!(&obj,
+                    "type" => "LibraryUnreadable",
+                    "lineNumber" => lineno as f64,
+                    "libPath" => &libpath,
+                    "reason" => &reason
+                );
+            }
+            QasmSimError::LibrarySyntaxError {
+                lineno,
+                libpath,
+                error,
+                ..
+            } => {
+                set!(&obj,
+                    "type" => "LibrarySyntaxError",
+                    "lineNumber" => lineno as f64,
+                    "libPath" => &libpath,
+                    "libraryLineNumber" => error.lineno as f64,
+                    "startPosition" => error.startpos as f64
+                );
+                if let Some(endpos) = error.endpos {
+                    set!(&obj, "endPosition" => endpos as f64);
+                }
+                if let Some(token) = error.token {
+                    set!(&obj, "token" => &format!("{}", token));
+                }
+            }
+            QasmSimError::IncludeCycle { chain, lineno, .. } => {
+                let js_chain: js_sys::Array = chain.iter().map(JsValue::from).collect();
+                set!(&obj,
+                    "type" => "IncludeCycle",
+                    "lineNumber" => lineno as f64,
+                    "chain" => js_chain
+                );
+            }
             QasmSimError::IndexOutOfBounds {
                 lineno,
                 symbol_name,
@@ -182,6 +252,125 @@ impl From<QasmSimError<'_>> for JsValue {
                     "symbolName" => &symbol_name
                 );
             }
+            QasmSimError::UseBeforeDefinition {
+                symbol_name,
+                lineno,
+                definition_lineno,
+                definition_file,
+                ..
+            } => {
+                set!(&obj,
+                    "type" => "UseBeforeDefinition",
+                    "lineNumber" => lineno as f64,
+                    "definitionLineNumber" => definition_lineno as f64,
+                    "symbolName" => &symbol_name
+                );
+                if let Some(definition_file) = definition_file {
+                    set!(&obj, "definitionFile" => &definition_file);
+                }
+            }
+            QasmSimError::RecursiveGate {
+                symbol_name,
+                lineno,
+                ..
+            } => {
+                set!(&obj,
+                    "type" => "RecursiveGate",
+                    "lineNumber" => lineno as f64,
+                    "symbolName" => &symbol_name
+                );
+            }
+            QasmSimError::UndeclaredGateArgument {
+                gate_name,
+                symbol_name,
+                lineno,
+                ..
+            } => {
+                set!(&obj,
+                    "type" => "UndeclaredGateArgument",
+                    "lineNumber" => lineno as f64,
+                    "gateName" => &gate_name,
+                    "symbolName" => &symbol_name
+                );
+            }
+            QasmSimError::UndeclaredGateParameter {
+                gate_name,
+                symbol_name,
+                lineno,
+                ..
+            } => {
+                set!(&obj,
+                    "type" => "UndeclaredGateParameter",
+                    "lineNumber" => lineno as f64,
+                    "gateName" => &gate_name,
+                    "symbolName" => &symbol_name
+                );
+            }
+            QasmSimError::IndexedGateArgument {
+                gate_name,
+                symbol_name,
+                index,
+                lineno,
+                ..
+            } => {
+                set!(&obj,
+                    "type" => "IndexedGateArgument",
+                    "lineNumber" => lineno as f64,
+                    "gateName" => &gate_name,
+                    "symbolName" => &symbol_name,
+                    "index" => index as f64
+                );
+            }
+            QasmSimError::EmptyRegister {
+                symbol_name,
+                lineno,
+                ..
+            } => {
+                set!(&obj,
+                    "type" => "EmptyRegister",
+                    "lineNumber" => lineno as f64,
+                    "symbolName" => &symbol_name
+                );
+            }
+            QasmSimError::RegisterTooLarge {
+                symbol_name,
+                lineno,
+                size,
+                max_size,
+                ..
+            } => {
+                set!(&obj,
+                    "type" => "RegisterTooLarge",
+                    "lineNumber" => lineno as f64,
+                    "symbolName" => &symbol_name,
+                    "size" => size as f64,
+                    "maxSize" => max_size as f64
+                );
+            }
+            QasmSimError::QuantumCondition {
+                symbol_name,
+                lineno,
+                ..
+            } => {
+                set!(&obj,
+                    "type" => "QuantumCondition",
+                    "lineNumber" => lineno as f64,
+                    "symbolName" => &symbol_name
+                );
+            }
+            QasmSimError::QubitAliasing {
+                gate_name,
+                symbol_name,
+                lineno,
+                ..
+            } => {
+                set!(&obj,
+                    "type" => "QubitAliasing",
+                    "lineNumber" => lineno as f64,
+                    "gateName" => &gate_name,
+                    "symbolName" => &symbol_name
+                );
+            }
         };
         obj.into()
     }
@@ -194,6 +383,7 @@ impl From<RuntimeError> for JsValue {
 
         set!(&obj,
             "message" => &message,
+            "code" => value.code(),
             "toString" => js_sys::Function::new_no_args("return this.message")
         );
 
@@ -204,6 +394,23 @@ impl From<RuntimeError> for JsValue {
             RuntimeError::Other => {
                 set!(&obj, "type" => "Unknown");
             }
+            RuntimeError::InitialStateWidthMismatch { expected, given } => {
+                set!(&obj,
+                    "type" => "InitialStateWidthMismatch",
+                    "expected" => expected as f64,
+                    "given" => given as f64
+                );
+            }
+            RuntimeError::InitialStateNotNormalized => {
+                set!(&obj, "type" => "InitialStateNotNormalized");
+            }
+            RuntimeError::TooManyQubits { qubits, max_qubits } => {
+                set!(&obj,
+                    "type" => "TooManyQubits",
+                    "qubits" => qubits as f64,
+                    "maxQubits" => max_qubits as f64
+                );
+            }
             RuntimeError::IndexOutOfBounds {
                 location,
                 symbol_name,
@@ -213,7 +420,7 @@ impl From<RuntimeError> for JsValue {
             } => {
                 set!(&obj,
                     "type" => "IndexOutOfBounds",
-                    "location" => location.0 as f64,
+                    "location" => location.offset as f64,
                     "symbolName" => &symbol_name,
                     "index" => index as f64,
                     "size" => size as f64
@@ -227,7 +434,7 @@ impl From<RuntimeError> for JsValue {
             } => {
                 set!(&obj,
                     "type" => "SymbolNotFound",
-                    "location" => location.0 as f64,
+                    "location" => location.offset as f64,
                     "symbolName" => &symbol_name,
                     "expected" => &format!("{}", expected)
                 );
@@ -242,7 +449,7 @@ impl From<RuntimeError> for JsValue {
             } => {
                 set!(&obj,
                     "type" => "WrongNumberOfParameters",
-                    "location" => location.0 as f64,
+                    "location" => location.offset as f64,
                     "symbolName" => &symbol_name,
                     "kind" => if are_registers { "register" } else { "real" },
                     "given" => given as f64,
@@ -256,7 +463,7 @@ impl From<RuntimeError> for JsValue {
             } => {
                 set!(&obj,
                     "type" => "UndefinedGate",
-                    "location" => location.0 as f64,
+                    "location" => location.offset as f64,
                     "symbolName" => &symbol_name
                 );
             }
@@ -268,7 +475,7 @@ impl From<RuntimeError> for JsValue {
             } => {
                 set!(&obj,
                     "type" => "TypeMismatch",
-                    "location" => location.0 as f64,
+                    "location" => location.offset as f64,
                     "symbolName" => &symbol_name,
                     "expected" => &format!("{}", expected)
                 );
@@ -280,7 +487,7 @@ impl From<RuntimeError> for JsValue {
             } => {
                 set!(&obj,
                     "type" => "RegisterSizeMismatch",
-                    "location" => location.0 as f64,
+                    "location" => location.offset as f64,
                     "symbolName" => &symbol_name
                 );
             }
@@ -296,6 +503,7 @@ impl From<SemanticError> for JsValue {
 
         set!(&obj,
             "message" => &message,
+            "code" => value.code(),
             "toString" => js_sys::Function::new_no_args("return this.message")
         );
 
@@ -308,12 +516,118 @@ impl From<SemanticError> for JsValue {
             } => {
                 set!(&obj,
                     "type" => "RedefinitionError",
-                    "location" => location.0 as f64,
-                    "previousLocation" => previous_location.0 as f64,
+                    "location" => location.offset as f64,
+                    "previousLocation" => previous_location.offset as f64,
+                    "symbolName" => &symbol_name
+                );
+            }
+            SemanticError::UseBeforeDefinition {
+                symbol_name,
+                location,
+                definition_location,
+            } => {
+                set!(&obj,
+                    "type" => "UseBeforeDefinition",
+                    "location" => location.offset as f64,
+                    "definitionLocation" => definition_location.offset as f64,
+                    "symbolName" => &symbol_name
+                );
+            }
+            SemanticError::RecursiveGate {
+                symbol_name,
+                location,
+            } => {
+                set!(&obj,
+                    "type" => "RecursiveGate",
+                    "location" => location.offset as f64,
+                    "symbolName" => &symbol_name
+                );
+            }
+            SemanticError::UndeclaredGateArgument {
+                gate_name,
+                symbol_name,
+                location,
+            } => {
+                set!(&obj,
+                    "type" => "UndeclaredGateArgument",
+                    "location" => location.offset as f64,
+                    "gateName" => &gate_name,
+                    "symbolName" => &symbol_name
+                );
+            }
+            SemanticError::UndeclaredGateParameter {
+                gate_name,
+                symbol_name,
+                location,
+            } => {
+                set!(&obj,
+                    "type" => "UndeclaredGateParameter",
+                    "location" => location.offset as f64,
+                    "gateName" => &gate_name,
+                    "symbolName" => &symbol_name
+                );
+            }
+            SemanticError::IndexedGateArgument {
+                gate_name,
+                symbol_name,
+                index,
+                location,
+            } => {
+                set!(&obj,
+                    "type" => "IndexedGateArgument",
+                    "location" => location.offset as f64,
+                    "gateName" => &gate_name,
+                    "symbolName" => &symbol_name,
+                    "index" => index as f64
+                );
+            }
+            SemanticError::EmptyRegister {
+                symbol_name,
+                location,
+            } => {
+                set!(&obj,
+                    "type" => "EmptyRegister",
+                    "location" => location.offset as f64,
+                    "symbolName" => &symbol_name
+                );
+            }
+            SemanticError::RegisterTooLarge {
+                symbol_name,
+                size,
+                max_size,
+                location,
+            } => {
+                set!(&obj,
+                    "type" => "RegisterTooLarge",
+                    "location" => location.offset as f64,
+                    "symbolName" => &symbol_name,
+                    "size" => size as f64,
+                    "maxSize" => max_size as f64
+                );
+            }
+            SemanticError::QuantumCondition {
+                symbol_name,
+                location,
+            } => {
+                set!(&obj,
+                    "type" => "QuantumCondition",
+                    "location" => location.offset as f64,
+                    "symbolName" => &symbol_name
+                );
+            }
+            SemanticError::QubitAliasing {
+                gate_name,
+                symbol_name,
+                location,
+            } => {
+                set!(&obj,
+                    "type" => "QubitAliasing",
+                    "location" => location.offset as f64,
+                    "gateName" => &gate_name,
                     "symbolName" => &symbol_name
                 );
             }
         };
         obj.into()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,42 @@
+use std::io;
+use std::path::Path;
+
+use js_sys::{Function, Object, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::linker::{IncludeResolver, ResolvedLibrary};
+
+/// Resolve libraries by asking a JavaScript object. If the object is a
+/// function, it is called with the library path and must return the source
+/// of the library, or `undefined` if unknown. Otherwise, the object maps
+/// library paths to their sources.
+#[derive(Debug, Clone)]
+pub struct JsResolver(Object);
+
+impl From<Object> for JsResolver {
+    fn from(object: Object) -> Self {
+        JsResolver(object)
+    }
+}
+
+impl IncludeResolver for JsResolver {
+    fn resolve(
+        &self,
+        libpath: &str,
+        _directory: Option<&Path>,
+    ) -> io::Result<Option<ResolvedLibrary>> {
+        let key = JsValue::from_str(libpath);
+        let source = match self.0.dyn_ref::<Function>() {
+            Some(function) => function.call1(&JsValue::NULL, &key),
+            None => Reflect::get(&self.0, &key),
+        };
+        Ok(source
+            .ok()
+            .and_then(|source| source.as_string())
+            .map(|source| ResolvedLibrary {
+                path: libpath.into(),
+                directory: None,
+                source,
+            }))
+    }
+}
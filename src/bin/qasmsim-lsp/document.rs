@@ -0,0 +1,228 @@
+use std::path::PathBuf;
+
+use lsp_types::{
+    DiagnosticRelatedInformation, DiagnosticSeverity, Location as LspLocation, NumberOrString,
+    Position, Range, Url,
+};
+
+use qasmsim::error::{Diagnostic, Severity};
+use qasmsim::grammar::ast::OpenQasmProgram;
+use qasmsim::grammar::lexer::FileId;
+use qasmsim::grammar::parse_program_with_recovery;
+use qasmsim::grammar::source_map::SourceMap;
+use qasmsim::lint::{self, LintConfig, LintDiagnostic};
+use qasmsim::symbols::{identifier_at, symbols, Symbol};
+use qasmsim::{LinkerOptions, QasmSimError};
+
+/// An open document along with the result of analyzing it.
+pub struct Document {
+    pub uri: Url,
+    pub source_map: SourceMap<'static>,
+    pub symbols: Vec<Symbol>,
+    pub diagnostics: Vec<lsp_types::Diagnostic>,
+}
+
+impl Document {
+    /// Parse, link, check and lint `text`. The symbols come from the linked
+    /// program or, if the document does not link, from what the parser could
+    /// recover.
+    pub fn new(uri: Url, text: String) -> Self {
+        let linker_options = linker_options(&uri);
+        let (tree, mut errors) = parse_program_with_recovery(&text);
+        let mut lints = vec![];
+        let mut analyzed: Option<(OpenQasmProgram, SourceMap)> = None;
+        if errors.is_empty() {
+            match qasmsim::parse_and_link_with_source_map(&text, &linker_options) {
+                Ok((program, source_map)) => {
+                    if let Err(semantic_errors) = qasmsim::check(&program) {
+                        errors.extend(
+                            semantic_errors
+                                .into_iter()
+                                .map(|error| QasmSimError::from((&source_map, error))),
+                        );
+                    }
+                    lints = lint::lint(&program, &LintConfig::new())
+                        .into_iter()
+                        .map(|lint| LintDiagnostic::from((&source_map, lint)))
+                        .collect();
+                    analyzed = Some((program, source_map));
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+
+        let diagnostics = errors
+            .iter()
+            .map(Diagnostic::from)
+            .chain(lints.iter().map(Diagnostic::from))
+            .map(|diagnostic| to_lsp_diagnostic(&uri, &text, diagnostic))
+            .collect();
+        let (symbols, source_map) = match analyzed {
+            Some((program, source_map)) => (symbols(&program), source_map.into_owned()),
+            None => (
+                tree.as_ref().map(symbols).unwrap_or_default(),
+                SourceMap::new(&text).into_owned(),
+            ),
+        };
+        Document {
+            uri,
+            source_map,
+            symbols,
+            diagnostics,
+        }
+    }
+
+    /// Return the source code of the document.
+    pub fn text(&self) -> &str {
+        self.source(FileId(0))
+    }
+
+    /// Return the source code of `file`, or an empty string if it is unknown.
+    pub fn source(&self, file: FileId) -> &str {
+        self.source_map.source(file).unwrap_or_default()
+    }
+
+    /// Return the identifier at `position` of the document, if any.
+    pub fn identifier_at(&self, position: Position) -> Option<String> {
+        let text = self.text();
+        identifier_at(text, offset(text, position)).map(|(name, _)| name)
+    }
+
+    /// Return the symbol named `name`, if any.
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+
+    /// Return the range of the name of `symbol` inside its file.
+    pub fn name_range(&self, symbol: &Symbol) -> Range {
+        let source = self.source(symbol.location.file);
+        let range = symbol.name_range(source);
+        Range::new(position(source, range.start), position(source, range.end))
+    }
+
+    /// Return where to find `symbol`, if its file can be opened.
+    pub fn location(&self, symbol: &Symbol) -> Option<LspLocation> {
+        let file = symbol.location.file;
+        let uri = if file == FileId(0) {
+            self.uri.clone()
+        } else {
+            let name = self.source_map.name(file)?;
+            let path = library_path(&linker_options(&self.uri), name)?;
+            Url::from_file_path(path).ok()?
+        };
+        Some(LspLocation::new(uri, self.name_range(symbol)))
+    }
+}
+
+/// Return the position of `offset` in `source`, measuring columns in UTF-16
+/// code units as LSP requires.
+pub fn position(source: &str, offset: usize) -> Position {
+    let offset = offset.min(source.len());
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let line = before.matches('\n').count();
+    let character = before[line_start..].encode_utf16().count();
+    Position::new(line as u32, character as u32)
+}
+
+/// Return the offset of `position` in `source`.
+pub fn offset(source: &str, position: Position) -> usize {
+    let line_start = source
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+    let line = source[line_start..].lines().next().unwrap_or_default();
+    let mut units = 0;
+    for (index, character) in line.char_indices() {
+        if units >= position.character as usize {
+            return line_start + index;
+        }
+        units += character.len_utf16();
+    }
+    line_start + line.len()
+}
+
+fn linker_options(uri: &Url) -> LinkerOptions {
+    let base_dir = uri
+        .to_file_path()
+        .ok()
+        .and_then(|path| path.parent().map(PathBuf::from))
+        .unwrap_or_default();
+    LinkerOptions::new()
+        .with_base_dir(base_dir)
+        .with_env_include_paths()
+}
+
+/// Return the path of the library `name`, looking in the same directories
+/// the linker does.
+fn library_path(linker_options: &LinkerOptions, name: &str) -> Option<PathBuf> {
+    let name = PathBuf::from(name);
+    if name.is_absolute() {
+        return Some(name).filter(|path| path.is_file());
+    }
+    linker_options
+        .base_dir()
+        .into_iter()
+        .chain(linker_options.include_paths().iter().map(PathBuf::as_path))
+        .map(|directory| directory.join(&name))
+        .find(|path| path.is_file())
+}
+
+/// Convert a diagnostic to LSP. Diagnostics inside libraries are reported
+/// where the library is included, or at the start of the document.
+fn to_lsp_diagnostic(uri: &Url, text: &str, diagnostic: Diagnostic) -> lsp_types::Diagnostic {
+    let mut message = diagnostic.message;
+    let range = match (&diagnostic.file, diagnostic.line, diagnostic.columns) {
+        (None, Some(line), Some(columns)) => {
+            let line_start = offset(text, Position::new(line.saturating_sub(1) as u32, 0));
+            Range::new(
+                position(text, line_start + columns.start - 1),
+                position(text, line_start + columns.end - 1),
+            )
+        }
+        (Some(file), line, _) => {
+            message = format!("{}:{}: {}", file, line.unwrap_or_default(), message);
+            diagnostic
+                .related
+                .iter()
+                .find(|related| related.file.is_none())
+                .map(|related| line_range(text, related.line))
+                .unwrap_or_default()
+        }
+        (None, Some(line), None) => line_range(text, line),
+        (None, None, _) => Range::default(),
+    };
+    if let Some(hint) = diagnostic.hint {
+        message = format!("{}\nhelp: {}", message, hint);
+    }
+    let related_information = diagnostic
+        .related
+        .into_iter()
+        .filter(|related| related.file.is_none())
+        .map(|related| DiagnosticRelatedInformation {
+            location: LspLocation::new(uri.clone(), line_range(text, related.line)),
+            message: related.message,
+        })
+        .collect::<Vec<_>>();
+    lsp_types::Diagnostic {
+        range,
+        severity: Some(match diagnostic.severity {
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            _ => DiagnosticSeverity::ERROR,
+        }),
+        code: Some(NumberOrString::String(diagnostic.code)),
+        source: Some("qasmsim".into()),
+        message,
+        related_information: Some(related_information).filter(|related| !related.is_empty()),
+        ..lsp_types::Diagnostic::default()
+    }
+}
+
+/// Return the range of the line `line` (1-based) of `text`.
+fn line_range(text: &str, line: usize) -> Range {
+    let line = line.saturating_sub(1) as u32;
+    let start = offset(text, Position::new(line, 0));
+    let length = text[start..].lines().next().unwrap_or_default().len();
+    Range::new(Position::new(line, 0), position(text, start + length))
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionParams, CompletionResponse, DocumentSymbol,
+    DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse,
+    Hover, HoverContents, HoverParams, MarkupContent, MarkupKind, SymbolKind as LspSymbolKind,
+    TextDocumentPositionParams, Url,
+};
+
+use qasmsim::grammar::lexer::FileId;
+use qasmsim::symbols::{Symbol, SymbolKind};
+
+use crate::document::Document;
+
+/// The gates of `qelib1.inc`, for completing gate names in documents that do
+/// not link yet.
+pub struct Library {
+    gates: Vec<Symbol>,
+}
+
+impl Library {
+    pub fn qelib1() -> Self {
+        let uri = Url::parse("untitled:qelib1").expect("a valid URI");
+        let document = Document::new(uri, "OPENQASM 2.0;\ninclude \"qelib1.inc\";\n".into());
+        Library {
+            gates: document.symbols,
+        }
+    }
+}
+
+pub fn definition(
+    documents: &HashMap<Url, Document>,
+    params: GotoDefinitionParams,
+) -> Option<GotoDefinitionResponse> {
+    let (document, symbol) = symbol_at(documents, &params.text_document_position_params)?;
+    document
+        .location(symbol)
+        .map(GotoDefinitionResponse::Scalar)
+}
+
+pub fn hover(documents: &HashMap<Url, Document>, params: HoverParams) -> Option<Hover> {
+    let (_, symbol) = symbol_at(documents, &params.text_document_position_params)?;
+    let mut value = format!("```qasm\n{}\n```", symbol.signature());
+    if let Some(doc) = &symbol.doc {
+        value = format!("{}\n\n{}", value, doc);
+    }
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: None,
+    })
+}
+
+/// Complete the symbols of the document along with the gates of
+/// `qelib1.inc`.
+pub fn completion(
+    documents: &HashMap<Url, Document>,
+    library: &Library,
+    params: CompletionParams,
+) -> Option<CompletionResponse> {
+    let document = documents.get(&params.text_document_position.text_document.uri)?;
+    let mut items: Vec<CompletionItem> = vec![];
+    for symbol in document.symbols.iter().chain(&library.gates) {
+        if items.iter().any(|item| item.label == symbol.name) {
+            continue;
+        }
+        items.push(CompletionItem {
+            label: symbol.name.clone(),
+            kind: Some(match symbol.kind {
+                SymbolKind::QuantumRegister | SymbolKind::ClassicalRegister => {
+                    CompletionItemKind::VARIABLE
+                }
+                _ => CompletionItemKind::FUNCTION,
+            }),
+            detail: Some(symbol.signature()),
+            documentation: symbol.doc.clone().map(lsp_types::Documentation::String),
+            ..CompletionItem::default()
+        });
+    }
+    Some(CompletionResponse::Array(items))
+}
+
+/// List the registers and gates declared in the document itself.
+pub fn document_symbols(
+    documents: &HashMap<Url, Document>,
+    params: DocumentSymbolParams,
+) -> Option<DocumentSymbolResponse> {
+    let document = documents.get(&params.text_document.uri)?;
+    let symbols = document
+        .symbols
+        .iter()
+        .filter(|symbol| symbol.location.file == FileId(0))
+        .map(|symbol| {
+            let range = document.name_range(symbol);
+            #[allow(deprecated)]
+            DocumentSymbol {
+                name: symbol.name.clone(),
+                detail: Some(symbol.signature()),
+                kind: match symbol.kind {
+                    SymbolKind::QuantumRegister | SymbolKind::ClassicalRegister => {
+                        LspSymbolKind::ARRAY
+                    }
+                    _ => LspSymbolKind::FUNCTION,
+                },
+                tags: None,
+                deprecated: None,
+                range,
+                selection_range: range,
+                children: None,
+            }
+        })
+        .collect();
+    Some(DocumentSymbolResponse::Nested(symbols))
+}
+
+fn symbol_at<'doc>(
+    documents: &'doc HashMap<Url, Document>,
+    params: &TextDocumentPositionParams,
+) -> Option<(&'doc Document, &'doc Symbol)> {
+    let document = documents.get(&params.text_document.uri)?;
+    let name = document.identifier_at(params.position)?;
+    Some((document, document.symbol(&name)?))
+}
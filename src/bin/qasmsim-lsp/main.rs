@@ -0,0 +1,173 @@
+mod document;
+mod features;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as LspNotification, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as LspRequest,
+};
+use lsp_types::{
+    CompletionOptions, HoverProviderCapability, OneOf, PublishDiagnosticsParams,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::document::Document;
+use crate::features::Library;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+fn main() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+    Server::new().run(connection)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// Keep the open documents, analyzing them each time they change.
+struct Server {
+    documents: HashMap<Url, Document>,
+    library: Library,
+}
+
+impl Server {
+    fn new() -> Self {
+        Server {
+            documents: HashMap::new(),
+            library: Library::qelib1(),
+        }
+    }
+
+    fn run(&mut self, connection: Connection) -> Result<()> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(request);
+                    connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => {
+                    match self.handle_notification(notification) {
+                        Ok(Some(published)) => {
+                            let notification =
+                                Notification::new(PublishDiagnostics::METHOD.into(), published);
+                            connection
+                                .sender
+                                .send(Message::Notification(notification))?;
+                        }
+                        Ok(None) => (),
+                        Err(error) => eprintln!("qasmsim-lsp: {}", error),
+                    }
+                }
+                Message::Response(_) => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => params::<GotoDefinition>(request)
+                .map(|params| to_value(features::definition(&self.documents, params))),
+            HoverRequest::METHOD => params::<HoverRequest>(request)
+                .map(|params| to_value(features::hover(&self.documents, params))),
+            Completion::METHOD => params::<Completion>(request).map(|params| {
+                to_value(features::completion(&self.documents, &self.library, params))
+            }),
+            DocumentSymbolRequest::METHOD => params::<DocumentSymbolRequest>(request)
+                .map(|params| to_value(features::document_symbols(&self.documents, params))),
+            method => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("unsupported method `{}`", method),
+                )
+            }
+        };
+        match result {
+            Ok(value) => Response::new_ok(id, value),
+            Err(error) => Response::new_err(id, ErrorCode::InvalidParams as i32, error.to_string()),
+        }
+    }
+
+    /// Update the documents and return the diagnostics to publish, if any.
+    fn handle_notification(
+        &mut self,
+        notification: Notification,
+    ) -> Result<Option<PublishDiagnosticsParams>> {
+        let (uri, text, version) = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = notification_params::<DidOpenTextDocument>(notification)?;
+                let document = params.text_document;
+                (document.uri, document.text, Some(document.version))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = notification_params::<DidChangeTextDocument>(notification)?;
+                let text = match params.content_changes.into_iter().last() {
+                    Some(change) => change.text,
+                    None => return Ok(None),
+                };
+                (
+                    params.text_document.uri,
+                    text,
+                    Some(params.text_document.version),
+                )
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = notification_params::<DidCloseTextDocument>(notification)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                return Ok(Some(PublishDiagnosticsParams::new(uri, vec![], None)));
+            }
+            _ => return Ok(None),
+        };
+        let document = Document::new(uri.clone(), text);
+        let diagnostics = document.diagnostics.clone();
+        self.documents.insert(uri.clone(), document);
+        Ok(Some(PublishDiagnosticsParams::new(
+            uri,
+            diagnostics,
+            version,
+        )))
+    }
+}
+
+fn params<R: LspRequest>(request: Request) -> Result<R::Params>
+where
+    R::Params: DeserializeOwned,
+{
+    Ok(serde_json::from_value(request.params)?)
+}
+
+fn notification_params<N: LspNotification>(notification: Notification) -> Result<N::Params>
+where
+    N::Params: DeserializeOwned,
+{
+    Ok(serde_json::from_value(notification.params)?)
+}
+
+fn to_value<T: serde::Serialize>(result: T) -> Value {
+    serde_json::to_value(result).expect("LSP types serialize to JSON")
+}
@@ -0,0 +1,41 @@
+use std::fs;
+use std::io;
+use std::process;
+
+use qasmsim::grammar::parse_program_with_trivia;
+
+use crate::options::FmtOptions;
+
+/// Format the program in place, or print it if it comes from the stdin. With
+/// `--check`, only report whether the program is already formatted.
+pub fn main(options: &FmtOptions) -> io::Result<()> {
+    let source = crate::source(&options.source)?;
+    let program = match parse_program_with_trivia(&source) {
+        (Some(program), errors) if errors.is_empty() => program,
+        (_, errors) => {
+            crate::print_errors(&errors, "human")?;
+            process::exit(1);
+        }
+    };
+
+    let formatted = program.to_string();
+    if options.check {
+        if formatted != source {
+            let name = options
+                .source
+                .as_ref()
+                .map_or_else(|| "<stdin>".into(), |path| path.display().to_string());
+            eprintln!("{} is not formatted", name);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+    match &options.source {
+        Some(path) if formatted != source => fs::write(path, formatted),
+        Some(_) => Ok(()),
+        None => {
+            print!("{}", formatted);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::io;
+use std::process;
+
+use qasmsim::{GateInfo, GateKind};
+
+use crate::options::GatesOptions;
+
+const QELIB1_PROGRAM: &str = "OPENQASM 2.0;\ninclude \"qelib1.inc\";\n";
+
+/// Print the gates available to the program along with their parameters,
+/// number of qubits and documentation.
+pub fn main(options: &GatesOptions) -> io::Result<()> {
+    let source = match &options.source {
+        None => QELIB1_PROGRAM.into(),
+        Some(_) => crate::source(&options.source)?,
+    };
+    let linker_options = crate::linker_options(&options.source, &options.include_paths);
+    let (program, _) = match crate::parse_and_link(&source, &linker_options) {
+        Ok(linked) => linked,
+        Err(errors) => {
+            crate::print_errors(&errors, "human")?;
+            process::exit(1);
+        }
+    };
+
+    let gates = qasmsim::list_gates(&program);
+    if options.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&gates)?);
+    } else {
+        for gate in &gates {
+            println!("{}", describe(gate));
+            for line in gate.doc.iter().flat_map(|doc| doc.lines()) {
+                println!("    {}", line);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn describe(gate: &GateInfo) -> String {
+    let mut description = gate.name.clone();
+    if !gate.real_args.is_empty() {
+        description = format!("{}({})", description, gate.real_args.join(", "));
+    }
+    let qubits = if gate.arity() == 1 { "qubit" } else { "qubits" };
+    description = format!(
+        "{} {} ({} {}",
+        description,
+        gate.args.join(", "),
+        gate.arity(),
+        qubits
+    );
+    match gate.kind {
+        GateKind::Primitive => description += ", primitive)",
+        GateKind::Opaque => description += ", opaque)",
+        _ => description += ")",
+    }
+    description
+}
@@ -0,0 +1,116 @@
+use std::io;
+use std::path::Path;
+
+use qasmsim::error::RuntimeError;
+use qasmsim::statevector::{Complex, StateVector};
+use qasmsim::MAX_QUBITS;
+
+/// The value of the `--initial-state` option.
+pub enum InitialState {
+    /// A basis state written with the most significant qubit first.
+    Basis(String),
+    /// A state-vector read from a CSV file.
+    State(StateVector),
+}
+
+impl InitialState {
+    /// Return the state-vector of the initial state for a program of `width`
+    /// qubits. The width of a basis state is checked before allocating it.
+    pub fn into_statevector(self, width: usize) -> Result<StateVector, RuntimeError> {
+        match self {
+            InitialState::Basis(bitstring) if bitstring.len() != width => {
+                Err(RuntimeError::InitialStateWidthMismatch {
+                    expected: width,
+                    given: bitstring.len(),
+                })
+            }
+            InitialState::Basis(_) if width > MAX_QUBITS => Err(RuntimeError::TooManyQubits {
+                qubits: width,
+                max_qubits: MAX_QUBITS,
+            }),
+            InitialState::Basis(bitstring) => Ok(from_bitstring(&bitstring)),
+            InitialState::State(statevector) => Ok(statevector),
+        }
+    }
+}
+
+/// Parse the value of the `--initial-state` option. A string made only of
+/// `0` and `1` is a basis state written with the most significant qubit
+/// first. Anything else is the path to a CSV file with the layout of the
+/// `out.state.csv` files.
+pub fn parse(value: &str) -> io::Result<InitialState> {
+    if is_bitstring(value) {
+        Ok(InitialState::Basis(value.to_string()))
+    } else {
+        from_csv(Path::new(value)).map(InitialState::State)
+    }
+}
+
+fn is_bitstring(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c == '0' || c == '1')
+}
+
+fn from_bitstring(bitstring: &str) -> StateVector {
+    let base = bitstring
+        .chars()
+        .fold(0_usize, |acc, c| (acc << 1) | (c == '1') as usize);
+    let mut bases = vec![Complex::from(0.0); 1 << bitstring.len()];
+    bases[base] = Complex::from(1.0);
+    StateVector::from_complex_bases(bases)
+}
+
+fn from_csv(path: &Path) -> io::Result<StateVector> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header == name)
+            .ok_or_else(|| invalid_input(format!("missing column `{}`", name)))
+    };
+    let (base_column, real_column, imaginary_column) =
+        (column("Base")?, column("Real")?, column("Imaginary")?);
+
+    let mut amplitudes = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let base: usize = parse_field(&record, base_column)?;
+        let re: f64 = parse_field(&record, real_column)?;
+        let im: f64 = parse_field(&record, imaginary_column)?;
+        amplitudes.push((base, Complex::new(re, im)));
+    }
+
+    let len = amplitudes.len();
+    if !len.is_power_of_two() {
+        return Err(invalid_input(format!(
+            "expected a power of two amplitudes, found {}",
+            len
+        )));
+    }
+    let mut bases = vec![None; len];
+    for (base, amplitude) in amplitudes {
+        match bases.get_mut(base) {
+            Some(slot @ None) => *slot = Some(amplitude),
+            Some(Some(_)) => {
+                return Err(invalid_input(format!("duplicated base {}", base)));
+            }
+            None => return Err(invalid_input(format!("base {} out of range", base))),
+        }
+    }
+    let bases = bases
+        .into_iter()
+        .map(|amplitude| amplitude.expect("all bases are present"))
+        .collect();
+    Ok(StateVector::from_complex_bases(bases))
+}
+
+fn parse_field<T: std::str::FromStr>(record: &csv::StringRecord, index: usize) -> io::Result<T> {
+    let field = record.get(index).unwrap_or_default().trim();
+    field
+        .parse()
+        .map_err(|_| invalid_input(format!("cannot parse `{}`", field)))
+}
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
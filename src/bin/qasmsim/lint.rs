@@ -0,0 +1,78 @@
+use std::io;
+use std::process;
+
+use qasmsim::error::Diagnostic;
+use qasmsim::lint::{self, Level, LintConfig, LintDiagnostic};
+use qasmsim::QasmSimError;
+
+use crate::options::LintOptions;
+
+/// Lint the program and print the diagnostics. Exit with an error if the
+/// program has errors or any denied lint.
+pub fn main(options: &LintOptions) -> io::Result<()> {
+    let source = crate::source(&options.source)?;
+    let linker_options = crate::linker_options(&options.source, &options.include_paths);
+    let (program, source_map) = match crate::parse_and_link(&source, &linker_options) {
+        Ok(linked) => linked,
+        Err(errors) => {
+            print(options, &errors, &[])?;
+            process::exit(1);
+        }
+    };
+
+    let errors: Vec<QasmSimError> = match qasmsim::check(&program) {
+        Ok(()) => vec![],
+        Err(errors) => errors
+            .into_iter()
+            .map(|error| QasmSimError::from((&source_map, error)))
+            .collect(),
+    };
+    let lints: Vec<LintDiagnostic> = lint::lint(&program, &config(options))
+        .into_iter()
+        .map(|lint| LintDiagnostic::from((&source_map, lint)))
+        .collect();
+    print(options, &errors, &lints)?;
+
+    if !errors.is_empty() || lints.iter().any(|lint| lint.level == Level::Deny) {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+fn print(
+    options: &LintOptions,
+    errors: &[QasmSimError],
+    lints: &[LintDiagnostic],
+) -> io::Result<()> {
+    if options.format == "json" {
+        let diagnostics: Vec<Diagnostic> = errors
+            .iter()
+            .map(Diagnostic::from)
+            .chain(lints.iter().map(Diagnostic::from))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+    } else {
+        for error in errors {
+            eprintln!("{}", error);
+        }
+        for lint in lints {
+            eprintln!("{}", lint);
+        }
+    }
+    Ok(())
+}
+
+fn config(options: &LintOptions) -> LintConfig {
+    let config = options
+        .allow
+        .iter()
+        .fold(LintConfig::new(), |config, code| config.allow(*code));
+    let config = options
+        .warn
+        .iter()
+        .fold(config, |config, code| config.warn(*code));
+    options
+        .deny
+        .iter()
+        .fold(config, |config, code| config.deny(*code))
+}
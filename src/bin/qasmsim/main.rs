@@ -1,20 +1,78 @@
+mod fmt;
+mod gates;
+mod initial_state;
+mod lint;
 mod options;
 mod output;
+mod profiler;
+mod route;
+mod stats;
+mod transpile;
 
 use std::fs;
 use std::io::{self, Read};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use structopt::StructOpt;
 
-use qasmsim::Execution;
+use qasmsim::error::Diagnostic;
+use qasmsim::grammar::ast::{OpenQasmProgram, Statement};
+use qasmsim::grammar::source_map::SourceMap;
+use qasmsim::{Execution, LinkerOptions, Observer, QasmSimError, SimulationOptions};
+
+use crate::initial_state::InitialState;
+use crate::profiler::Profiler;
 
 fn main() -> io::Result<()> {
     let options = options::Options::from_args();
+    match &options.command {
+        Some(options::Command::Lint(lint_options)) => return lint::main(lint_options),
+        Some(options::Command::Gates(gates_options)) => return gates::main(gates_options),
+        Some(options::Command::Fmt(fmt_options)) => return fmt::main(fmt_options),
+        Some(options::Command::Transpile(transpile_options)) => {
+            return transpile::main(transpile_options)
+        }
+        Some(options::Command::Route(route_options)) => return route::main(route_options),
+        Some(options::Command::Stats(stats_options)) => return stats::main(stats_options),
+        None => (),
+    }
     let source = source(&options.source)?;
-    match qasmsim::run(&source, options.shots) {
-        Ok(result) => print_result(&result, &options).expect("print result"),
-        Err(error) => eprintln!("{}", error),
+    let initial_state = match &options.initial_state {
+        None => None,
+        Some(value) => Some(initial_state::parse(value)?),
+    };
+    let mut profiler = Profiler::new();
+    let observer = if options.times {
+        Some(&mut profiler as &mut dyn Observer)
+    } else {
+        None
+    };
+    let linker_options = linker_options(&options.source, &options.include_paths);
+    let execution = run(
+        &source,
+        &linker_options,
+        options.shots,
+        initial_state,
+        options.optimize,
+        observer,
+    );
+    profiler.finish();
+    match execution {
+        Ok(result) => print_result(&result, &profiler, &options).expect("print result"),
+        Err(errors) => print_errors(&errors, &options.error_format)?,
+    }
+    Ok(())
+}
+
+fn print_errors(errors: &[QasmSimError], error_format: &str) -> io::Result<()> {
+    if error_format == "json" {
+        let diagnostics: Vec<Diagnostic> = errors.iter().map(Diagnostic::from).collect();
+        eprintln!("{}", serde_json::to_string_pretty(&diagnostics)?);
+    } else {
+        for error in errors {
+            eprintln!("{}", error);
+        }
     }
     Ok(())
 }
@@ -29,16 +87,105 @@ fn source(source: &Option<PathBuf>) -> io::Result<String> {
     }
 }
 
-fn print_result(result: &Execution, options: &options::Options) -> io::Result<()> {
+fn linker_options(source: &Option<PathBuf>, include_paths: &[PathBuf]) -> LinkerOptions {
+    let base_dir = match source {
+        Some(path) => path.parent().unwrap_or_else(|| Path::new("")).to_path_buf(),
+        None => PathBuf::new(),
+    };
+    include_paths
+        .iter()
+        .fold(
+            LinkerOptions::new().with_base_dir(base_dir),
+            |linker_options, path| linker_options.with_include_path(path),
+        )
+        .with_env_include_paths()
+}
+
+fn run<'src>(
+    source: &'src str,
+    linker_options: &LinkerOptions,
+    shots: Option<usize>,
+    initial_state: Option<InitialState>,
+    optimize: bool,
+    observer: Option<&mut dyn Observer>,
+) -> Result<Execution, Vec<QasmSimError<'src>>> {
+    let parsing_start = Instant::now();
+    let (mut program, source_map) = parse_and_link(source, linker_options)?;
+    qasmsim::check(&program).map_err(|errors| {
+        errors
+            .into_iter()
+            .map(|err| QasmSimError::from((&source_map, err)))
+            .collect::<Vec<_>>()
+    })?;
+    let width = program
+        .program
+        .iter()
+        .filter_map(|span| match &*span.node {
+            Statement::QRegDecl(_, size) => Some(size),
+            _ => None,
+        })
+        .sum();
+    let initial_state = initial_state
+        .map(|initial_state| initial_state.into_statevector(width))
+        .transpose()
+        .map_err(|err| vec![QasmSimError::from((&source_map, err))])?;
+    if optimize {
+        let optimization = qasmsim::transpiler::optimize(&program)
+            .map_err(|err| vec![QasmSimError::from((&source_map, err))])?;
+        transpile::report(&optimization);
+        program = optimization.program;
+    }
+    let parsing_time = parsing_start.elapsed().as_millis();
+
+    let simulation_start = Instant::now();
+    let mut simulation_options = SimulationOptions::new();
+    if let Some(shots) = shots {
+        simulation_options = simulation_options.with_shots(shots);
+    }
+    if let Some(initial_state) = initial_state {
+        simulation_options = simulation_options.with_initial_state(initial_state);
+    }
+    if let Some(observer) = observer {
+        simulation_options = simulation_options.with_observer(observer);
+    }
+    let computation = qasmsim::simulate_with(&program, simulation_options)
+        .map_err(|err| vec![QasmSimError::from((&source_map, err))])?;
+    let simulation_time = simulation_start.elapsed().as_millis();
+
+    Ok(Execution::from((
+        computation,
+        parsing_time,
+        simulation_time,
+    )))
+}
+
+/// Parse and link `source`, reporting all the syntax errors of the program at
+/// once.
+fn parse_and_link<'src>(
+    source: &'src str,
+    linker_options: &LinkerOptions,
+) -> Result<(OpenQasmProgram, SourceMap<'src>), Vec<QasmSimError<'src>>> {
+    let (_, errors) = qasmsim::grammar::parse_program_with_recovery(source);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    qasmsim::parse_and_link_with_source_map(source, linker_options).map_err(|err| vec![err])
+}
+
+fn print_result(
+    result: &Execution,
+    profiler: &Profiler,
+    options: &options::Options,
+) -> io::Result<()> {
     match &options.out {
         None => {
             let stdout = io::stdout();
             let mut handle = io::BufWriter::new(stdout.lock());
-            output::tabular::print(&mut handle, result, options);
+            output::tabular::print(&mut handle, result, profiler, options);
         }
         Some(path) => {
             let mut path = PathBuf::from(path);
-            output::csv::print(&mut path, result, options);
+            output::csv::print(&mut path, result, profiler, options);
         }
     }
     Ok(())
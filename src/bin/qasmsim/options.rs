@@ -2,6 +2,9 @@ use std::path::PathBuf;
 
 use structopt::StructOpt;
 
+use qasmsim::lint::LintCode;
+use qasmsim::transpiler::Basis;
+
 #[derive(Debug, Clone, PartialEq, Eq, StructOpt, Hash)]
 #[structopt(
     name = "qasmsim",
@@ -13,8 +16,10 @@ pub struct Options {
     pub source: Option<PathBuf>,
 
     /// Output files prefix, print in the stdout if not present. The output
-    /// format of each file is CSV. At most, three files are created with the
-    /// names out.memory.csv, out.state.csv and out.times.csv
+    /// format of each file is CSV. At most, six files are created with the
+    /// names out.memory.csv, out.state.csv, out.bloch.csv, out.entropy.csv,
+    /// out.times.csv and out.profile.csv, plus one out.snapshot.<label>.csv
+    /// file per snapshot, with the symbols of the label replaced by `_`
     #[structopt(long)]
     pub out: Option<PathBuf>,
 
@@ -34,19 +39,222 @@ pub struct Options {
     #[structopt(long, short = "i")]
     pub integer: bool,
 
-    /// Prints the state vector of the simulation. Ignored if shots is set.
+    /// Prints the state vector of the simulation and its snapshots. Ignored if shots is set.
     #[structopt(long)]
     pub statevector: bool,
 
-    /// Prints the probabilities vector of the simulation. Ignored if shots is set.
+    /// Prints the probabilities vector of the simulation and its snapshots. Ignored if shots is set.
     #[structopt(long)]
     pub probabilities: bool,
 
-    /// Prints times measured for parsing and simulating.
+    /// Prints the Bloch vector of each qubit, per quantum register. Ignored if shots is set.
+    #[structopt(long)]
+    pub bloch: bool,
+
+    /// Prints the entanglement entropy and purity of each quantum register. Ignored if shots is set.
+    #[structopt(long)]
+    pub entropy: bool,
+
+    /// Prints times measured for parsing and simulating, and the time spent in each gate.
     #[structopt(short, long)]
     pub times: bool,
 
     /// Specify the number of simulations.
     #[structopt(long)]
     pub shots: Option<usize>,
+
+    /// Adds a directory to the list of paths where to look for the libraries
+    /// in include directives, after the directory of the source file. Can be
+    /// repeated. The paths in the QASMSIM_PATH environment variable are
+    /// looked up after these.
+    #[structopt(
+        short = "I",
+        long = "include-path",
+        parse(from_os_str),
+        number_of_values = 1
+    )]
+    pub include_paths: Vec<PathBuf>,
+
+    /// Starts the simulation from the given state instead of |0⟩. Either a
+    /// bitstring, with the most significant qubit first, or a CSV file with
+    /// the layout of out.state.csv.
+    #[structopt(long)]
+    pub initial_state: Option<String>,
+
+    /// Simulates the program after optimizing its circuit, and prints the
+    /// gate count and depth before and after the optimization to stderr.
+    #[structopt(long)]
+    pub optimize: bool,
+
+    /// Format of the errors, either human or json. The json format prints an
+    /// array with all the errors.
+    #[structopt(long, default_value = "human", possible_values = &["human", "json"])]
+    pub error_format: String,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, StructOpt, Hash)]
+pub enum Command {
+    /// Checks the program without simulating it and warns about suspicious
+    /// code.
+    Lint(LintOptions),
+    /// Lists the available gates with their parameters and documentation.
+    Gates(GatesOptions),
+    /// Formats the program, normalizing indentation, spacing and
+    /// expressions, and keeping the comments.
+    Fmt(FmtOptions),
+    /// Inlines all the gate calls of the program down to a basis of gates
+    /// and prints the flat program.
+    Transpile(TranspileOptions),
+    /// Maps the program onto the connectivity of a device, inserting swaps,
+    /// and prints the routed program.
+    Route(RouteOptions),
+    /// Reports the qubits, gates, depth and memory the program needs without
+    /// simulating it.
+    Stats(StatsOptions),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, StructOpt, Hash)]
+pub struct LintOptions {
+    /// QASM program file, read from stdin if not present.
+    #[structopt(parse(from_os_str))]
+    pub source: Option<PathBuf>,
+
+    /// Does not report the lint, given by code (W0001) or name
+    /// (unused-register). Can be repeated.
+    #[structopt(short = "A", long = "allow", number_of_values = 1)]
+    pub allow: Vec<LintCode>,
+
+    /// Reports the lint as a warning. Can be repeated.
+    #[structopt(short = "W", long = "warn", number_of_values = 1)]
+    pub warn: Vec<LintCode>,
+
+    /// Reports the lint as an error. Can be repeated.
+    #[structopt(short = "D", long = "deny", number_of_values = 1)]
+    pub deny: Vec<LintCode>,
+
+    /// Output format, either human or json. The json format prints an array
+    /// with the errors and the warnings.
+    #[structopt(long, default_value = "human", possible_values = &["human", "json"])]
+    pub format: String,
+
+    /// Adds a directory to the list of paths where to look for the libraries
+    /// in include directives. Can be repeated.
+    #[structopt(
+        short = "I",
+        long = "include-path",
+        parse(from_os_str),
+        number_of_values = 1
+    )]
+    pub include_paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, StructOpt, Hash)]
+pub struct GatesOptions {
+    /// QASM program file. Lists the gates of qelib1.inc if not present.
+    #[structopt(parse(from_os_str))]
+    pub source: Option<PathBuf>,
+
+    /// Output format, either human or json.
+    #[structopt(long, default_value = "human", possible_values = &["human", "json"])]
+    pub format: String,
+
+    /// Adds a directory to the list of paths where to look for the libraries
+    /// in include directives. Can be repeated.
+    #[structopt(
+        short = "I",
+        long = "include-path",
+        parse(from_os_str),
+        number_of_values = 1
+    )]
+    pub include_paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, StructOpt, Hash)]
+pub struct FmtOptions {
+    /// QASM program file, rewritten in place. Read from stdin and printed in
+    /// the stdout if not present.
+    #[structopt(parse(from_os_str))]
+    pub source: Option<PathBuf>,
+
+    /// Does not write the formatted program, but exits with an error if the
+    /// program is not formatted.
+    #[structopt(long)]
+    pub check: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, StructOpt, Hash)]
+pub struct TranspileOptions {
+    /// QASM program file, read from stdin if not present.
+    #[structopt(parse(from_os_str))]
+    pub source: Option<PathBuf>,
+
+    /// Gates of the transpiled program, either U,CX, rz,sx,cx or u3,cx.
+    #[structopt(long, default_value = "U,CX")]
+    pub basis: Basis,
+
+    /// Optimizes the circuit before converting it to the basis, and prints
+    /// the gate count and depth before and after the optimization to stderr.
+    #[structopt(long)]
+    pub optimize: bool,
+
+    /// Adds a directory to the list of paths where to look for the libraries
+    /// in include directives. Can be repeated.
+    #[structopt(
+        short = "I",
+        long = "include-path",
+        parse(from_os_str),
+        number_of_values = 1
+    )]
+    pub include_paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, StructOpt, Hash)]
+pub struct RouteOptions {
+    /// QASM program file, read from stdin if not present.
+    #[structopt(parse(from_os_str))]
+    pub source: Option<PathBuf>,
+
+    /// JSON file with the edges between the physical qubits of the device, as
+    /// in [[0, 1], [1, 2]].
+    #[structopt(long, parse(from_os_str))]
+    pub coupling_map: PathBuf,
+
+    /// Physical qubit of each qubit of the program, in order of declaration,
+    /// as in 2,0,1. Defaults to the physical qubits with the same number.
+    #[structopt(long, use_delimiter = true)]
+    pub layout: Vec<usize>,
+
+    /// Adds a directory to the list of paths where to look for the libraries
+    /// in include directives. Can be repeated.
+    #[structopt(
+        short = "I",
+        long = "include-path",
+        parse(from_os_str),
+        number_of_values = 1
+    )]
+    pub include_paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, StructOpt, Hash)]
+pub struct StatsOptions {
+    /// QASM program file, read from stdin if not present.
+    #[structopt(parse(from_os_str))]
+    pub source: Option<PathBuf>,
+
+    /// Output format, either human or json.
+    #[structopt(long, default_value = "human", possible_values = &["human", "json"])]
+    pub format: String,
+
+    /// Adds a directory to the list of paths where to look for the libraries
+    /// in include directives. Can be repeated.
+    #[structopt(
+        short = "I",
+        long = "include-path",
+        parse(from_os_str),
+        number_of_values = 1
+    )]
+    pub include_paths: Vec<PathBuf>,
 }
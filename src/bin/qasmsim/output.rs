@@ -1,2 +1,22 @@
 pub mod csv;
 pub mod tabular;
+
+use std::ops::Range;
+
+use qasmsim::statevector::StateVector;
+use qasmsim::Execution;
+
+/// Return the quantum registers of the execution sorted by their position in
+/// the state-vector.
+fn sorted_quantum_registers(result: &Execution) -> Vec<(&String, &Range<usize>)> {
+    let mut registers: Vec<(&String, &Range<usize>)> = result.quantum_registers().iter().collect();
+    registers.sort_by_key(|(_, range)| range.start);
+    registers
+}
+
+/// Return the snapshots of the execution sorted by label.
+fn sorted_snapshots(result: &Execution) -> Vec<(&String, &StateVector)> {
+    let mut snapshots: Vec<(&String, &StateVector)> = result.snapshots().iter().collect();
+    snapshots.sort_by_key(|(label, _)| *label);
+    snapshots
+}
@@ -9,8 +9,10 @@ use qasmsim::statevector::StateVector;
 use qasmsim::{Execution, ExecutionTimes, Histogram};
 
 use crate::options::Options;
+use crate::output::{sorted_quantum_registers, sorted_snapshots};
+use crate::profiler::Profiler;
 
-pub fn print(path: &mut PathBuf, result: &Execution, options: &Options) {
+pub fn print(path: &mut PathBuf, result: &Execution, profiler: &Profiler, options: &Options) {
     // TODO: Add error handling for path operations.
     let prefix = path
         .file_name()
@@ -41,19 +43,62 @@ pub fn print(path: &mut PathBuf, result: &Execution, options: &Options) {
             writer_ref,
             result.statevector(),
             result.probabilities(),
-            &options,
+            options,
         )
         .expect("writes");
+
+        for (label, statevector) in sorted_snapshots(result) {
+            path.set_file_name(format!("{}.snapshot.{}.csv", &prefix, file_label(label)));
+            let mut writer = csv::Writer::from_path(&path).expect("can open the file");
+            print_state(
+                &mut writer,
+                statevector,
+                &statevector.probabilities(),
+                options,
+            )
+            .expect("writes");
+        }
+    }
+
+    if options.bloch && options.shots.is_none() {
+        path.set_file_name(format!("{}.bloch.csv", &prefix));
+        let mut writer = csv::Writer::from_path(&path).expect("can open the file");
+        print_bloch_vectors(&mut writer, result).expect("writes");
+    }
+
+    if options.entropy && options.shots.is_none() {
+        path.set_file_name(format!("{}.entropy.csv", &prefix));
+        let mut writer = csv::Writer::from_path(&path).expect("can open the file");
+        print_entropies(&mut writer, result).expect("writes");
     }
 
     if options.times {
         path.set_file_name(format!("{}.times.csv", &prefix));
-        let mut writer = csv::Writer::from_path(path).expect("can open the file");
+        let mut writer = csv::Writer::from_path(&path).expect("can open the file");
         let writer_ref = &mut writer;
         print_times(writer_ref, result.times()).expect("writes");
+
+        path.set_file_name(format!("{}.profile.csv", &prefix));
+        let mut writer = csv::Writer::from_path(path).expect("can open the file");
+        print_profile(&mut writer, profiler).expect("writes");
     }
 }
 
+/// Return `label` with all the characters but ASCII alphanumerics, `-` and `_`
+/// replaced by `_`, so it is safe to use in a file name.
+fn file_label(label: &str) -> String {
+    label
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
 fn print_memory<W>(
     writer: &mut csv::Writer<W>,
     memory: &HashMap<String, u64>,
@@ -179,12 +224,70 @@ where
     Ok(())
 }
 
+fn print_bloch_vectors<W>(writer: &mut csv::Writer<W>, result: &Execution) -> io::Result<()>
+where
+    W: Write,
+{
+    writer.write_record(["Name", "Qubit", "X", "Y", "Z"])?;
+
+    let statevector = result.statevector();
+    for (name, range) in sorted_quantum_registers(result) {
+        for (idx, qubit) in range.clone().enumerate() {
+            let [x, y, z] = statevector.bloch_vector(qubit);
+            writer.write_record(&[
+                name.clone(),
+                format!("{}", idx),
+                format!("{:.6}", x),
+                format!("{:.6}", y),
+                format!("{:.6}", z),
+            ])?;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_entropies<W>(writer: &mut csv::Writer<W>, result: &Execution) -> io::Result<()>
+where
+    W: Write,
+{
+    writer.write_record(["Name", "Entropy", "Purity"])?;
+
+    for (name, _) in sorted_quantum_registers(result) {
+        let entropy = result.register_entropy(name).expect("the register exists");
+        let purity = result.register_purity(name).expect("the register exists");
+        writer.write_record(&[
+            name.clone(),
+            format!("{:.6}", entropy),
+            format!("{:.6}", purity),
+        ])?;
+    }
+
+    Ok(())
+}
+
 fn print_times<W>(writer: &mut csv::Writer<W>, times: &ExecutionTimes) -> io::Result<()>
 where
     W: Write,
 {
-    writer.write_record(&["Name", "Duration (ms)"])?;
+    writer.write_record(["Name", "Duration (ms)"])?;
     writer.serialize(("parsing", times.parsing_time()))?;
-    writer.serialize(("simulation", times.parsing_time()))?;
+    writer.serialize(("simulation", times.simulation_time()))?;
+    Ok(())
+}
+
+fn print_profile<W>(writer: &mut csv::Writer<W>, profiler: &Profiler) -> io::Result<()>
+where
+    W: Write,
+{
+    writer.write_record(["Gate", "Calls", "Duration (ms)"])?;
+    for (name, stats) in profiler.stats() {
+        let duration = stats.duration.as_secs_f64() * 1000.0;
+        writer.write_record(&[
+            name.clone(),
+            format!("{}", stats.calls),
+            format!("{:.3}", duration),
+        ])?;
+    }
     Ok(())
 }
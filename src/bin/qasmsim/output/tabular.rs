@@ -8,12 +8,14 @@ use qasmsim::statevector::StateVector;
 use qasmsim::{Execution, ExecutionTimes, Histogram};
 
 use crate::options::Options;
+use crate::output::{sorted_quantum_registers, sorted_snapshots};
+use crate::profiler::Profiler;
 
 /// Writes the `msg` in the `buffer` if `options.verbose` is greater than 0.
 macro_rules! vvprint {
-    ($options:expr, $buffer:expr, $msg:expr) => {{
+    ($options:expr, $buffer:expr, $($msg:tt)+) => {{
         if $options.verbose > 0 {
-            write!($buffer, $msg)
+            write!($buffer, $($msg)+)
         } else {
             Ok(())
         }
@@ -23,22 +25,27 @@ macro_rules! vvprint {
 /// Writes the `msg` in the `buffer` if `options.verbose` is greater than 0 and
 /// ends it with a newline.
 macro_rules! vvprintln {
-    ($options:expr, $buffer:expr, $msg:literal) => {{
-        vvprint!($options, $buffer, concat!($msg, "\n"))
+    ($options:expr, $buffer:expr, $msg:literal $($args:tt)*) => {{
+        vvprint!($options, $buffer, concat!($msg, "\n") $($args)*)
     }};
     ($options:expr, $buffer:expr) => {{
         vvprintln!($options, $buffer, "")
     }};
 }
 
-pub fn print<W>(buffer: &mut W, result: &Execution, options: &Options)
+pub fn print<W>(buffer: &mut W, result: &Execution, profiler: &Profiler, options: &Options)
 where
     W: Write,
 {
-    do_print(buffer, result, options).expect("writes in stdout");
+    do_print(buffer, result, profiler, options).expect("writes in stdout");
 }
 
-fn do_print<W>(buffer: &mut W, result: &Execution, options: &Options) -> io::Result<()>
+fn do_print<W>(
+    buffer: &mut W,
+    result: &Execution,
+    profiler: &Profiler,
+    options: &Options,
+) -> io::Result<()>
 where
     W: Write,
 {
@@ -70,12 +77,34 @@ where
             options,
         )?;
         vvprintln!(options, buffer)?;
+
+        for (label, statevector) in sorted_snapshots(result) {
+            vvprintln!(options, buffer, "Snapshot \"{}\":", label)?;
+            print_state(buffer, statevector, &statevector.probabilities(), options)?;
+            vvprintln!(options, buffer)?;
+        }
+    }
+
+    if options.bloch && options.shots.is_none() {
+        vvprintln!(options, buffer, "Bloch vectors:")?;
+        print_bloch_vectors(buffer, result)?;
+        vvprintln!(options, buffer)?;
+    }
+
+    if options.entropy && options.shots.is_none() {
+        vvprintln!(options, buffer, "Entanglement:")?;
+        print_entropies(buffer, result)?;
+        vvprintln!(options, buffer)?;
     }
 
     if options.times {
         vvprintln!(options, buffer, "Times:")?;
         print_times(buffer, result.times())?;
         vvprintln!(options, buffer)?;
+
+        vvprintln!(options, buffer, "Gate profile:")?;
+        print_profile(buffer, profiler)?;
+        vvprintln!(options, buffer)?;
     }
     Ok(())
 }
@@ -207,6 +236,52 @@ where
     write!(buffer, "{}", table)
 }
 
+fn print_bloch_vectors<W>(buffer: &mut W, result: &Execution) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row![c => "Name", "Qubit", "X", "Y", "Z"]);
+
+    let statevector = result.statevector();
+    for (name, range) in sorted_quantum_registers(result) {
+        for (idx, qubit) in range.clone().enumerate() {
+            let [x, y, z] = statevector.bloch_vector(qubit);
+            table.add_row(row![
+                r -> if idx == 0 { name.as_str() } else { "" },
+                r -> idx,
+                r -> format!("{:.6}", x),
+                r -> format!("{:.6}", y),
+                r -> format!("{:.6}", z)
+            ]);
+        }
+    }
+
+    write!(buffer, "{}", table)
+}
+
+fn print_entropies<W>(buffer: &mut W, result: &Execution) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row![c => "Name", "Entropy", "Purity"]);
+
+    for (name, _) in sorted_quantum_registers(result) {
+        let entropy = result.register_entropy(name).expect("the register exists");
+        let purity = result.register_purity(name).expect("the register exists");
+        table.add_row(row![
+            r -> name,
+            r -> format!("{:.6}", entropy),
+            r -> format!("{:.6}", purity)
+        ]);
+    }
+
+    write!(buffer, "{}", table)
+}
+
 fn print_times<W>(buffer: &mut W, times: &ExecutionTimes) -> io::Result<()>
 where
     W: Write,
@@ -220,3 +295,19 @@ where
 
     write!(buffer, "{}", table)
 }
+
+fn print_profile<W>(buffer: &mut W, profiler: &Profiler) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+
+    table.set_titles(row!["Gate", "Calls", "Duration (ms)"]);
+    for (name, stats) in profiler.stats() {
+        let duration = stats.duration.as_secs_f64() * 1000.0;
+        table.add_row(row![name, r -> stats.calls, r -> format!("{:.3}", duration)]);
+    }
+
+    write!(buffer, "{}", table)
+}
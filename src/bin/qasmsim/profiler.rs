@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use qasmsim::grammar::lexer::Location;
+use qasmsim::Observer;
+
+/// Number of calls and accumulated duration of a gate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GateStats {
+    pub calls: usize,
+    pub duration: Duration,
+}
+
+/// Measure the time spent in each gate. The time of composite gates includes
+/// the time of the gates in their bodies.
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    stats: HashMap<String, GateStats>,
+    running_gate: Option<(String, Instant)>,
+    macro_starts: Vec<Instant>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Stop measuring the last gate. Call it once the simulation is done.
+    pub fn finish(&mut self) {
+        self.stop_running_gate();
+    }
+
+    /// Return the statistics of each gate sorted by descending duration.
+    pub fn stats(&self) -> Vec<(&String, &GateStats)> {
+        let mut stats: Vec<(&String, &GateStats)> = self.stats.iter().collect();
+        stats.sort_by(|(name_a, a), (name_b, b)| {
+            b.duration.cmp(&a.duration).then(name_a.cmp(name_b))
+        });
+        stats
+    }
+
+    fn stop_running_gate(&mut self) {
+        if let Some((name, start)) = self.running_gate.take() {
+            self.stats.entry(name).or_default().duration += start.elapsed();
+        }
+    }
+}
+
+impl Observer for Profiler {
+    fn on_statement(&mut self, _location: Location) {
+        self.stop_running_gate();
+    }
+
+    fn on_gate(&mut self, name: &str, _qubits: &[usize], _params: &[f64]) {
+        self.stop_running_gate();
+        self.stats.entry(name.into()).or_default().calls += 1;
+        self.running_gate = Some((name.into(), Instant::now()));
+    }
+
+    fn on_measure(&mut self, _qubit: usize, _register: &str, _bit: usize, _outcome: u64) {
+        self.stop_running_gate();
+    }
+
+    fn on_macro_enter(&mut self, name: &str, _qubits: &[usize], _params: &[f64]) {
+        self.stop_running_gate();
+        self.stats.entry(name.into()).or_default().calls += 1;
+        self.macro_starts.push(Instant::now());
+    }
+
+    fn on_macro_exit(&mut self, name: &str) {
+        self.stop_running_gate();
+        let start = self.macro_starts.pop().expect("a macro was entered");
+        self.stats.entry(name.into()).or_default().duration += start.elapsed();
+    }
+}
@@ -0,0 +1,57 @@
+use std::fs;
+use std::io;
+use std::process;
+
+use qasmsim::transpiler::{route, CouplingMap, RoutingError};
+use qasmsim::QasmSimError;
+
+use crate::options::RouteOptions;
+
+/// Print the program routed onto the coupling map, and the final layout of
+/// the qubits to stderr.
+pub fn main(options: &RouteOptions) -> io::Result<()> {
+    let coupling_map: CouplingMap =
+        serde_json::from_str(&fs::read_to_string(&options.coupling_map)?)?;
+    let source = crate::source(&options.source)?;
+    let linker_options = crate::linker_options(&options.source, &options.include_paths);
+    let (program, source_map) = match crate::parse_and_link(&source, &linker_options) {
+        Ok(linked) => linked,
+        Err(errors) => {
+            crate::print_errors(&errors, "human")?;
+            process::exit(1);
+        }
+    };
+
+    let layout = if options.layout.is_empty() {
+        None
+    } else {
+        Some(options.layout.as_slice())
+    };
+    match route(&program, &coupling_map, layout) {
+        Ok(routing) => {
+            print!("{}", routing.program);
+            eprintln!(
+                "layout: {} -> {}",
+                list(&routing.initial_layout),
+                list(&routing.final_layout)
+            );
+        }
+        Err(RoutingError::RuntimeError(error)) => {
+            crate::print_errors(&[QasmSimError::from((&source_map, error))], "human")?;
+            process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("error: {}", error);
+            process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+fn list(layout: &[usize]) -> String {
+    layout
+        .iter()
+        .map(|physical| physical.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
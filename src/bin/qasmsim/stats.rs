@@ -0,0 +1,67 @@
+use std::io;
+use std::process;
+
+use qasmsim::analysis::{analyze, ProgramStats};
+use qasmsim::QasmSimError;
+
+use crate::options::StatsOptions;
+
+const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+/// Print the resources the program needs.
+pub fn main(options: &StatsOptions) -> io::Result<()> {
+    let source = crate::source(&options.source)?;
+    let linker_options = crate::linker_options(&options.source, &options.include_paths);
+    let (program, source_map) = match crate::parse_and_link(&source, &linker_options) {
+        Ok(linked) => linked,
+        Err(errors) => {
+            crate::print_errors(&errors, &options.format)?;
+            process::exit(1);
+        }
+    };
+
+    let stats = match analyze(&program) {
+        Ok(stats) => stats,
+        Err(error) => {
+            let error = QasmSimError::from((&source_map, error));
+            crate::print_errors(&[error], &options.format)?;
+            process::exit(1);
+        }
+    };
+    if options.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        print(&stats);
+    }
+    Ok(())
+}
+
+fn print(stats: &ProgramStats) {
+    println!("qubits: {}", stats.qubits);
+    println!("clbits: {}", stats.clbits);
+    println!("U gates: {}", stats.u_count);
+    println!("CX gates: {}", stats.cx_count);
+    println!("depth: {}", stats.depth);
+    println!("two-qubit depth: {}", stats.two_qubit_depth);
+    println!("measurements: {}", stats.measurements);
+    println!("conditionals: {}", stats.conditionals);
+    println!("state-vector memory: {}", memory(stats.statevector_bytes));
+    println!("gate calls:");
+    for (name, count) in &stats.gate_counts {
+        println!("    {}: {}", name, count);
+    }
+}
+
+/// Return `bytes` in the largest binary unit keeping the amount above 1. The
+/// state-vector sizes are powers of two, so the amount is exact unless it
+/// saturates.
+fn memory(bytes: u64) -> String {
+    if bytes == u64::MAX {
+        return "16 EiB or more".into();
+    }
+    let exponent = (0..UNITS.len())
+        .rev()
+        .find(|exponent| bytes >> (10 * exponent) > 0)
+        .unwrap_or_default();
+    format!("{} {}", bytes >> (10 * exponent), UNITS[exponent])
+}
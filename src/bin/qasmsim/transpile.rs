@@ -0,0 +1,47 @@
+use std::io;
+use std::process;
+
+use qasmsim::transpiler::{optimize, transpile, Optimization};
+use qasmsim::QasmSimError;
+
+use crate::options::TranspileOptions;
+
+/// Print the program with its gate calls inlined down to the basis.
+pub fn main(options: &TranspileOptions) -> io::Result<()> {
+    let source = crate::source(&options.source)?;
+    let linker_options = crate::linker_options(&options.source, &options.include_paths);
+    let (program, source_map) = match crate::parse_and_link(&source, &linker_options) {
+        Ok(linked) => linked,
+        Err(errors) => {
+            crate::print_errors(&errors, "human")?;
+            process::exit(1);
+        }
+    };
+
+    let transpiled = if options.optimize {
+        optimize(&program).and_then(|optimization| {
+            report(&optimization);
+            transpile(&optimization.program, options.basis)
+        })
+    } else {
+        transpile(&program, options.basis)
+    };
+    match transpiled {
+        Ok(transpiled) => print!("{}", transpiled),
+        Err(error) => {
+            crate::print_errors(&[QasmSimError::from((&source_map, error))], "human")?;
+            process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+/// Print the gate count and depth of the circuit before and after the
+/// optimization to stderr.
+pub fn report(optimization: &Optimization) {
+    let (before, after) = (optimization.before, optimization.after);
+    eprintln!(
+        "gates: {} -> {}, depth: {} -> {}",
+        before.gate_count, after.gate_count, before.depth, after.depth
+    );
+}
@@ -1,9 +1,11 @@
 //! Contain the error types ragarding the different tasks that QasmSim can
 //! perform.
 
+pub(crate) mod diagnostic;
 #[macro_use]
 pub(crate) mod humanize;
 
+use std::borrow::Cow;
 use std::convert;
 use std::error;
 use std::fmt;
@@ -11,8 +13,10 @@ use std::fmt;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+pub use self::diagnostic::{Diagnostic, RelatedLocation, Severity};
 use self::humanize::humanize_error;
 use crate::grammar::lexer::{self, Location, Tok};
+use crate::grammar::source_map::SourceMap;
 pub use crate::interpreter::runtime::RuntimeError;
 pub use crate::linker::LinkerError;
 use crate::semantics::QasmType;
@@ -21,9 +25,21 @@ pub use crate::semantics::SemanticError;
 /// Represent a parsing error.
 pub type ParseError = lalrpop_util::ParseError<Location, lexer::Tok, lexer::LexicalError<Location>>;
 
+/// A [`QasmSimError`] not borrowing the source code, so it can outlive it,
+/// be sent across threads, or be boxed into a `dyn Error + Send + Sync`.
+/// See [`QasmSimError::into_owned()`].
+///
+/// [`QasmSimError`]: ./enum.QasmSimError.html
+/// [`QasmSimError::into_owned()`]: ./enum.QasmSimError.html#method.into_owned
+pub type OwnedQasmSimError = QasmSimError<'static>;
+
 /// An alias for a pair relating some source code with an error.
 pub type SrcAndErr<'src, E> = (&'src str, E);
 
+/// An alias for a pair relating the source files of a program, and of the
+/// libraries it includes, with an error.
+pub type MapAndErr<'map, 'src, E> = (&'map SourceMap<'src>, E);
+
 /// Types of errors in QasmSim. QasmSim errors contain information about
 /// the error and the location in the source code where the error happens.
 ///
@@ -33,7 +49,9 @@ pub type SrcAndErr<'src, E> = (&'src str, E);
 /// Conversion between [`ParseError`], [`RuntimeError`] and [`LinkerError`] is
 /// possible thanks to the trait `From` is defined for the pair
 /// `(&'source str, T)` (see alias [`SrcAndErr`]) for all the errors listed
-/// above.
+/// above. Pairing the errors with a [`SourceMap`] instead (see alias
+/// [`MapAndErr`]) relates the locations inside the included libraries with
+/// their own sources.
 ///
 /// # Examples
 ///
@@ -63,6 +81,8 @@ pub type SrcAndErr<'src, E> = (&'src str, E);
 /// [`RuntimeError`]: ./enum.RuntimeError.html
 /// [`LinkerError`]: ../linker/enum.LinkerError.html
 /// [`SrcAndErr`]: ./type.SrcAndErr.html
+/// [`SourceMap`]: ../grammar/source_map/struct.SourceMap.html
+/// [`MapAndErr`]: ./type.MapAndErr.html
 /// [`simulate]: ../fn.simulate.html
 /// [`map_err`]: ../../std/result/enum.Result.html#method.map_err
 #[non_exhaustive]
@@ -74,7 +94,9 @@ pub enum QasmSimError<'src> {
     /// Found an invalid token at some position.
     InvalidToken {
         /// Line source.
-        source: &'src str,
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
         /// Line number.
         lineno: usize,
         /// Position inside the line (0-based) where the invalid token starts.
@@ -89,7 +111,9 @@ pub enum QasmSimError<'src> {
     /// Found an unexpected end of file.
     UnexpectedEOF {
         /// Line source.
-        source: &'src str,
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
         /// Line number.
         lineno: usize,
         /// Position inside the line (0-based) where the invalid token starts.
@@ -104,7 +128,9 @@ pub enum QasmSimError<'src> {
     /// Found an unexpected token.
     UnexpectedToken {
         /// Line source.
-        source: &'src str,
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
         /// Line number.
         lineno: usize,
         /// Position inside the line (0-based) where the invalid token starts.
@@ -119,27 +145,73 @@ pub enum QasmSimError<'src> {
     /// Found a redefinition of a register.
     RedefinitionError {
         /// Line source.
-        source: &'src str,
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
         /// Name of the register declared for second time.
         symbol_name: String,
         /// Line number.
         lineno: usize,
         /// Line number where the register was originally declared.
         previous_lineno: usize,
+        /// Name of the file where the register was originally declared, if
+        /// it has one.
+        previous_file: Option<String>,
     },
     /// The unability of linking a library.
     LibraryNotFound {
         /// Line source.
-        source: &'src str,
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
+        /// Path to the library to be included.
+        libpath: String,
+        /// Line number.
+        lineno: usize,
+    },
+    /// The unability of reading an existing library.
+    LibraryUnreadable {
+        /// Line source.
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
         /// Path to the library to be included.
         libpath: String,
+        /// Description of the failure.
+        reason: String,
+        /// Line number.
+        lineno: usize,
+    },
+    /// An `include` directive leading to a library including itself.
+    IncludeCycle {
+        /// Line source.
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
+        /// Library paths in the order they were included.
+        chain: Vec<String>,
         /// Line number.
         lineno: usize,
     },
+    /// A syntax error inside an included library.
+    LibrarySyntaxError {
+        /// Line source of the `include` directive.
+        source: Cow<'src, str>,
+        /// Name of the file containing the `include` directive, if it has one.
+        file: Option<String>,
+        /// Line number of the `include` directive.
+        lineno: usize,
+        /// Path to the library with the syntax error.
+        libpath: String,
+        /// The syntax error, relative to the source of the library.
+        error: Box<LibraryParseError>,
+    },
     /// Use of register index that does not fit the register size.
     IndexOutOfBounds {
         /// Line source.
-        source: &'src str,
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
         /// Line number.
         lineno: usize,
         /// Name of the register being indexed.
@@ -152,7 +224,9 @@ pub enum QasmSimError<'src> {
     /// Use of an unknown/undeclared symbol.
     SymbolNotFound {
         /// Line source.
-        source: &'src str,
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
         /// Line number.
         lineno: usize,
         /// Name of the unknown symbol.
@@ -164,7 +238,9 @@ pub enum QasmSimError<'src> {
     /// parameters.
     WrongNumberOfParameters {
         /// Line source.
-        source: &'src str,
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
         /// Line number.
         lineno: usize,
         /// Name of the operation.
@@ -179,7 +255,9 @@ pub enum QasmSimError<'src> {
     /// Use of a gate not previously defined.
     UndefinedGate {
         /// Line source.
-        source: &'src str,
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
         /// Line number.
         lineno: usize,
         /// Name of the unknown gate.
@@ -188,7 +266,9 @@ pub enum QasmSimError<'src> {
     /// Found an unexpected type of value.
     TypeMismatch {
         /// Line source.
-        source: &'src str,
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
         /// Line number.
         lineno: usize,
         /// Name of the symbol with the incorrect type.
@@ -199,7 +279,9 @@ pub enum QasmSimError<'src> {
     /// Attempt of applying an operation to different sizes registers.
     RegisterSizeMismatch {
         /// Line source.
-        source: &'src str,
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
         /// Line number.
         lineno: usize,
         /// Name of the operation.
@@ -207,6 +289,428 @@ pub enum QasmSimError<'src> {
         /// Sizes of the different registers involved.
         sizes: Vec<usize>,
     },
+    /// Use of a gate before its definition.
+    UseBeforeDefinition {
+        /// Line source.
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
+        /// Line number.
+        lineno: usize,
+        /// Name of the gate.
+        symbol_name: String,
+        /// Line number where the gate is defined.
+        definition_lineno: usize,
+        /// Name of the file where the gate is defined, if it has one.
+        definition_file: Option<String>,
+    },
+    /// A gate calling itself inside its body.
+    RecursiveGate {
+        /// Line source of the gate declaration.
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
+        /// Line number of the gate declaration.
+        lineno: usize,
+        /// Name of the gate.
+        symbol_name: String,
+    },
+    /// Use of a qubit not among the formal arguments inside a gate body.
+    UndeclaredGateArgument {
+        /// Line source of the gate declaration.
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
+        /// Line number of the gate declaration.
+        lineno: usize,
+        /// Name of the gate.
+        gate_name: String,
+        /// Name of the unknown argument.
+        symbol_name: String,
+    },
+    /// Use of a real value not among the formal parameters inside a gate
+    /// body.
+    UndeclaredGateParameter {
+        /// Line source of the gate declaration.
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
+        /// Line number of the gate declaration.
+        lineno: usize,
+        /// Name of the gate.
+        gate_name: String,
+        /// Name of the unknown parameter.
+        symbol_name: String,
+    },
+    /// Indexing of a formal argument inside a gate body.
+    IndexedGateArgument {
+        /// Line source of the gate declaration.
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
+        /// Line number of the gate declaration.
+        lineno: usize,
+        /// Name of the gate.
+        gate_name: String,
+        /// Name of the indexed argument.
+        symbol_name: String,
+        /// Index tried to access.
+        index: usize,
+    },
+    /// Declaration of a register with no elements.
+    EmptyRegister {
+        /// Line source.
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
+        /// Line number.
+        lineno: usize,
+        /// Name of the register.
+        symbol_name: String,
+    },
+    /// Declaration of a classical register with more bits than supported.
+    RegisterTooLarge {
+        /// Line source.
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
+        /// Line number.
+        lineno: usize,
+        /// Name of the register.
+        symbol_name: String,
+        /// Size of the register.
+        size: usize,
+        /// Maximum size the register could have.
+        max_size: usize,
+    },
+    /// A conditional testing the value of a quantum register.
+    QuantumCondition {
+        /// Line source.
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
+        /// Line number.
+        lineno: usize,
+        /// Name of the register.
+        symbol_name: String,
+    },
+    /// A gate application passing the same qubit several times.
+    QubitAliasing {
+        /// Line source.
+        source: Cow<'src, str>,
+        /// Name of the file containing the line source, if it has one.
+        file: Option<String>,
+        /// Line number.
+        lineno: usize,
+        /// Name of the gate.
+        gate_name: String,
+        /// Name of the register, or gate argument, passed several times.
+        symbol_name: String,
+    },
+    /// The initial state does not have as many qubits as the program.
+    InitialStateWidthMismatch {
+        /// Number of qubits declared in the program.
+        expected: usize,
+        /// Number of qubits of the initial state.
+        given: usize,
+    },
+    /// The initial state is not a unit vector.
+    InitialStateNotNormalized,
+    /// The program declares more qubits than the simulator supports.
+    TooManyQubits {
+        /// Number of qubits declared in the program.
+        qubits: usize,
+        /// Maximum number of qubits of a simulation.
+        max_qubits: usize,
+    },
+}
+
+/// The kind of a syntax error, named after the [`QasmSimError`] variant
+/// describing it.
+///
+/// [`QasmSimError`]: ./enum.QasmSimError.html
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SyntaxErrorKind {
+    /// See [`QasmSimError::InvalidToken`].
+    ///
+    /// [`QasmSimError::InvalidToken`]: ./enum.QasmSimError.html#variant.InvalidToken
+    InvalidToken,
+    /// See [`QasmSimError::UnexpectedEOF`].
+    ///
+    /// [`QasmSimError::UnexpectedEOF`]: ./enum.QasmSimError.html#variant.UnexpectedEOF
+    UnexpectedEOF,
+    /// See [`QasmSimError::UnexpectedToken`].
+    ///
+    /// [`QasmSimError::UnexpectedToken`]: ./enum.QasmSimError.html#variant.UnexpectedToken
+    UnexpectedToken,
+}
+
+/// Describe a syntax error relative to the source of an included library.
+/// See [`QasmSimError::LibrarySyntaxError`].
+///
+/// [`QasmSimError::LibrarySyntaxError`]: ./enum.QasmSimError.html#variant.LibrarySyntaxError
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LibraryParseError {
+    /// Kind of the syntax error.
+    pub kind: SyntaxErrorKind,
+    /// Line source of the library.
+    pub source: String,
+    /// Line number inside the library.
+    pub lineno: usize,
+    /// Position inside the line (0-based) where the invalid token starts.
+    pub startpos: usize,
+    /// Position inside the line (0-based) where the invalid token ends.
+    pub endpos: Option<usize>,
+    /// Token found.
+    pub token: Option<Tok>,
+    /// A list of expected tokens.
+    pub expected: Vec<String>,
+}
+
+macro_rules! own_source {
+    ($error:expr, $($variant:ident { $($field:ident),* }),*) => {
+        match $error {
+            $(
+                QasmSimError::$variant { source, $($field),* } => QasmSimError::$variant {
+                    source: Cow::Owned(source.into_owned()),
+                    $($field),*
+                },
+            )*
+            QasmSimError::UnknownError(msg) => QasmSimError::UnknownError(msg),
+            QasmSimError::InitialStateWidthMismatch { expected, given } => {
+                QasmSimError::InitialStateWidthMismatch { expected, given }
+            }
+            QasmSimError::InitialStateNotNormalized => QasmSimError::InitialStateNotNormalized,
+            QasmSimError::TooManyQubits { qubits, max_qubits } => {
+                QasmSimError::TooManyQubits { qubits, max_qubits }
+            }
+        }
+    };
+}
+
+impl QasmSimError<'_> {
+    /// Return an error which does not borrow the source code.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// fn simulate_file_contents(contents: String) -> Result<(), Box<dyn Error + Send + Sync>> {
+    ///     qasmsim::run(&contents, None).map_err(|err| err.into_owned())?;
+    ///     Ok(())
+    /// }
+    ///
+    /// let error = simulate_file_contents("OPENQASM 2.0;\nqreg q[0];\n".to_string())
+    ///     .expect_err("empty register");
+    /// assert!(error.to_string().contains("qreg q[0];"));
+    /// ```
+    pub fn into_owned(self) -> OwnedQasmSimError {
+        own_source!(
+            self,
+            InvalidToken {
+                file,
+                lineno,
+                startpos,
+                endpos,
+                token,
+                expected
+            },
+            UnexpectedEOF {
+                file,
+                lineno,
+                startpos,
+                endpos,
+                token,
+                expected
+            },
+            UnexpectedToken {
+                file,
+                lineno,
+                startpos,
+                endpos,
+                token,
+                expected
+            },
+            RedefinitionError {
+                file,
+                symbol_name,
+                lineno,
+                previous_lineno,
+                previous_file
+            },
+            LibraryNotFound {
+                file,
+                libpath,
+                lineno
+            },
+            LibraryUnreadable {
+                file,
+                libpath,
+                reason,
+                lineno
+            },
+            IncludeCycle {
+                file,
+                chain,
+                lineno
+            },
+            LibrarySyntaxError {
+                file,
+                lineno,
+                libpath,
+                error
+            },
+            IndexOutOfBounds {
+                file,
+                lineno,
+                symbol_name,
+                index,
+                size
+            },
+            SymbolNotFound {
+                file,
+                lineno,
+                symbol_name,
+                expected
+            },
+            WrongNumberOfParameters {
+                file,
+                lineno,
+                symbol_name,
+                are_registers,
+                given,
+                expected
+            },
+            UndefinedGate {
+                file,
+                lineno,
+                symbol_name
+            },
+            TypeMismatch {
+                file,
+                lineno,
+                symbol_name,
+                expected
+            },
+            RegisterSizeMismatch {
+                file,
+                lineno,
+                symbol_name,
+                sizes
+            },
+            UseBeforeDefinition {
+                file,
+                lineno,
+                symbol_name,
+                definition_lineno,
+                definition_file
+            },
+            RecursiveGate {
+                file,
+                lineno,
+                symbol_name
+            },
+            UndeclaredGateArgument {
+                file,
+                lineno,
+                gate_name,
+                symbol_name
+            },
+            UndeclaredGateParameter {
+                file,
+                lineno,
+                gate_name,
+                symbol_name
+            },
+            IndexedGateArgument {
+                file,
+                lineno,
+                gate_name,
+                symbol_name,
+                index
+            },
+            EmptyRegister {
+                file,
+                lineno,
+                symbol_name
+            },
+            RegisterTooLarge {
+                file,
+                lineno,
+                symbol_name,
+                size,
+                max_size
+            },
+            QuantumCondition {
+                file,
+                lineno,
+                symbol_name
+            },
+            QubitAliasing {
+                file,
+                lineno,
+                gate_name,
+                symbol_name
+            }
+        )
+    }
+
+    /// Return the stable code of the error, such as `E0101` for
+    /// [`IndexOutOfBounds`]. Codes never change between releases, nor are
+    /// they reused for other errors.
+    ///
+    /// Codes are grouped by the task failing: `E00xx` for syntax errors,
+    /// `E01xx` for runtime errors, `E02xx` for semantic errors and `E03xx`
+    /// for linking errors. The errors converted into a `QasmSimError` keep
+    /// their codes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qasmsim::QasmSimError;
+    ///
+    /// let error = qasmsim::run("OPENQASM 2.0;\nqreg q[2];\nU(0, 0, 0) q[2];\n", None)
+    ///     .expect_err("index out of bounds");
+    /// assert!(matches!(error, QasmSimError::IndexOutOfBounds { .. }));
+    /// assert_eq!(error.code(), "E0101");
+    /// ```
+    ///
+    /// [`IndexOutOfBounds`]: #variant.IndexOutOfBounds
+    pub fn code(&self) -> &'static str {
+        match self {
+            QasmSimError::UnknownError(_) => "E0001",
+            QasmSimError::InvalidToken { .. } => "E0002",
+            QasmSimError::UnexpectedEOF { .. } => "E0003",
+            QasmSimError::UnexpectedToken { .. } => "E0004",
+            QasmSimError::IndexOutOfBounds { .. } => "E0101",
+            QasmSimError::SymbolNotFound { .. } => "E0102",
+            QasmSimError::WrongNumberOfParameters { .. } => "E0103",
+            QasmSimError::UndefinedGate { .. } => "E0104",
+            QasmSimError::TypeMismatch { .. } => "E0105",
+            QasmSimError::RegisterSizeMismatch { .. } => "E0106",
+            QasmSimError::InitialStateWidthMismatch { .. } => "E0107",
+            QasmSimError::InitialStateNotNormalized => "E0108",
+            QasmSimError::TooManyQubits { .. } => "E0109",
+            QasmSimError::RedefinitionError { .. } => "E0201",
+            QasmSimError::UseBeforeDefinition { .. } => "E0202",
+            QasmSimError::RecursiveGate { .. } => "E0203",
+            QasmSimError::UndeclaredGateArgument { .. } => "E0204",
+            QasmSimError::UndeclaredGateParameter { .. } => "E0205",
+            QasmSimError::IndexedGateArgument { .. } => "E0206",
+            QasmSimError::EmptyRegister { .. } => "E0207",
+            QasmSimError::RegisterTooLarge { .. } => "E0208",
+            QasmSimError::QuantumCondition { .. } => "E0209",
+            QasmSimError::QubitAliasing { .. } => "E0210",
+            QasmSimError::LibraryNotFound { .. } => "E0301",
+            QasmSimError::IncludeCycle { .. } => "E0302",
+            QasmSimError::LibrarySyntaxError { .. } => "E0303",
+            QasmSimError::LibraryUnreadable { .. } => "E0304",
+        }
+    }
 }
 
 impl fmt::Display for QasmSimError<'_> {
@@ -228,69 +732,83 @@ impl convert::From<String> for QasmSimError<'_> {
 impl<'src> From<SrcAndErr<'src, ParseError>> for QasmSimError<'src> {
     fn from(src_and_err: SrcAndErr<'src, ParseError>) -> Self {
         let (input, error) = src_and_err;
-        match error {
-            ParseError::InvalidToken { location } => {
-                let (source, lineno, startpos, endpos) = extract_line(location.0, None, input);
-                QasmSimError::InvalidToken {
-                    source,
-                    lineno,
-                    startpos,
-                    endpos,
-                    token: None,
-                    expected: Vec::new(),
-                }
+        QasmSimError::from((&SourceMap::new(input), error))
+    }
+}
+
+impl<'src> From<MapAndErr<'_, 'src, ParseError>> for QasmSimError<'src> {
+    fn from(map_and_err: MapAndErr<'_, 'src, ParseError>) -> Self {
+        let (source_map, error) = map_and_err;
+        from_parse_error(error, |location, end| {
+            SourceLine::new(source_map, location, end)
+        })
+    }
+}
+
+fn from_parse_error<'src, F>(error: ParseError, line_of: F) -> QasmSimError<'src>
+where
+    F: Fn(Location, Option<Location>) -> SourceLine<'src>,
+{
+    match error {
+        ParseError::InvalidToken { location } => {
+            let line = line_of(location, None);
+            QasmSimError::InvalidToken {
+                source: line.source,
+                file: line.file,
+                lineno: line.lineno,
+                startpos: line.startpos,
+                endpos: line.endpos,
+                token: None,
+                expected: Vec::new(),
             }
-            ParseError::UnrecognizedEOF { location, expected } => {
-                let (source, lineno, startpos, endpos) = extract_line(location.0, None, input);
-                QasmSimError::UnexpectedEOF {
-                    source,
-                    lineno,
-                    startpos,
-                    endpos,
-                    token: None,
-                    expected,
-                }
+        }
+        ParseError::UnrecognizedEOF { location, expected } => {
+            let line = line_of(location, None);
+            QasmSimError::UnexpectedEOF {
+                source: line.source,
+                file: line.file,
+                lineno: line.lineno,
+                startpos: line.startpos,
+                endpos: line.endpos,
+                token: None,
+                expected,
             }
-            ParseError::UnrecognizedToken { token, expected } => {
-                let location = token.0;
-                let endlocation = token.2;
-                let (source, lineno, startpos, endpos) =
-                    extract_line(location.0, Some(endlocation.0), input);
-                QasmSimError::UnexpectedToken {
-                    source,
-                    lineno,
-                    startpos,
-                    endpos,
-                    token: Some(token.1),
-                    expected,
-                }
+        }
+        ParseError::UnrecognizedToken { token, expected } => {
+            let line = line_of(token.0, Some(token.2));
+            QasmSimError::UnexpectedToken {
+                source: line.source,
+                file: line.file,
+                lineno: line.lineno,
+                startpos: line.startpos,
+                endpos: line.endpos,
+                token: Some(token.1),
+                expected,
             }
-            ParseError::ExtraToken { token } => {
-                let location = token.0;
-                let endlocation = token.2;
-                let (source, lineno, startpos, endpos) =
-                    extract_line(location.0, Some(endlocation.0), input);
-                QasmSimError::UnexpectedToken {
-                    source,
-                    lineno,
-                    startpos,
-                    endpos,
-                    token: Some(token.1),
-                    expected: Vec::new(),
-                }
+        }
+        ParseError::ExtraToken { token } => {
+            let line = line_of(token.0, Some(token.2));
+            QasmSimError::UnexpectedToken {
+                source: line.source,
+                file: line.file,
+                lineno: line.lineno,
+                startpos: line.startpos,
+                endpos: line.endpos,
+                token: Some(token.1),
+                expected: Vec::new(),
             }
-            ParseError::User { error: lexer_error } => {
-                let location = lexer_error.location;
-                let (source, lineno, startpos, endpos) = extract_line(location.0, None, input);
-                QasmSimError::InvalidToken {
-                    // XXX: Actually, this should be "InvalidInput"
-                    source,
-                    lineno,
-                    startpos,
-                    endpos,
-                    token: None,
-                    expected: Vec::new(),
-                }
+        }
+        ParseError::User { error: lexer_error } => {
+            let line = line_of(lexer_error.location, None);
+            QasmSimError::InvalidToken {
+                // XXX: Actually, this should be "InvalidInput"
+                source: line.source,
+                file: line.file,
+                lineno: line.lineno,
+                startpos: line.startpos,
+                endpos: line.endpos,
+                token: None,
+                expected: Vec::new(),
             }
         }
     }
@@ -299,17 +817,33 @@ impl<'src> From<SrcAndErr<'src, ParseError>> for QasmSimError<'src> {
 impl<'src> From<SrcAndErr<'src, RuntimeError>> for QasmSimError<'src> {
     fn from(source_and_error: SrcAndErr<'src, RuntimeError>) -> Self {
         let (input, error) = source_and_error;
+        QasmSimError::from((&SourceMap::new(input), error))
+    }
+}
+
+impl<'src> From<MapAndErr<'_, 'src, RuntimeError>> for QasmSimError<'src> {
+    fn from(map_and_err: MapAndErr<'_, 'src, RuntimeError>) -> Self {
+        let (source_map, error) = map_and_err;
+        let line_of = |location| SourceLine::new(source_map, location, None);
         match error {
             RuntimeError::Other => QasmSimError::UnknownError(format!("{:?}", error)),
+            RuntimeError::InitialStateWidthMismatch { expected, given } => {
+                QasmSimError::InitialStateWidthMismatch { expected, given }
+            }
+            RuntimeError::InitialStateNotNormalized => QasmSimError::InitialStateNotNormalized,
+            RuntimeError::TooManyQubits { qubits, max_qubits } => {
+                QasmSimError::TooManyQubits { qubits, max_qubits }
+            }
             RuntimeError::RegisterSizeMismatch {
                 location,
                 symbol_name,
                 sizes,
             } => {
-                let (source, lineno, _, _) = extract_line(location.0, None, input);
+                let line = line_of(location);
                 QasmSimError::RegisterSizeMismatch {
-                    source,
-                    lineno,
+                    source: line.source,
+                    file: line.file,
+                    lineno: line.lineno,
                     symbol_name,
                     sizes,
                 }
@@ -319,10 +853,11 @@ impl<'src> From<SrcAndErr<'src, RuntimeError>> for QasmSimError<'src> {
                 symbol_name,
                 expected,
             } => {
-                let (source, lineno, _, _) = extract_line(location.0, None, input);
+                let line = line_of(location);
                 QasmSimError::TypeMismatch {
-                    source,
-                    lineno,
+                    source: line.source,
+                    file: line.file,
+                    lineno: line.lineno,
                     symbol_name,
                     expected,
                 }
@@ -331,10 +866,11 @@ impl<'src> From<SrcAndErr<'src, RuntimeError>> for QasmSimError<'src> {
                 location,
                 symbol_name,
             } => {
-                let (source, lineno, _, _) = extract_line(location.0, None, input);
+                let line = line_of(location);
                 QasmSimError::UndefinedGate {
-                    source,
-                    lineno,
+                    source: line.source,
+                    file: line.file,
+                    lineno: line.lineno,
                     symbol_name,
                 }
             }
@@ -345,12 +881,13 @@ impl<'src> From<SrcAndErr<'src, RuntimeError>> for QasmSimError<'src> {
                 given,
                 expected,
             } => {
-                let (source, lineno, _, _) = extract_line(location.0, None, input);
+                let line = line_of(location);
                 QasmSimError::WrongNumberOfParameters {
                     are_registers,
-                    source,
+                    source: line.source,
+                    file: line.file,
                     symbol_name,
-                    lineno,
+                    lineno: line.lineno,
                     expected,
                     given,
                 }
@@ -360,11 +897,12 @@ impl<'src> From<SrcAndErr<'src, RuntimeError>> for QasmSimError<'src> {
                 symbol_name,
                 expected,
             } => {
-                let (source, lineno, _, _) = extract_line(location.0, None, input);
+                let line = line_of(location);
                 QasmSimError::SymbolNotFound {
-                    source,
+                    source: line.source,
+                    file: line.file,
                     symbol_name,
-                    lineno,
+                    lineno: line.lineno,
                     expected,
                 }
             }
@@ -374,11 +912,12 @@ impl<'src> From<SrcAndErr<'src, RuntimeError>> for QasmSimError<'src> {
                 index,
                 size,
             } => {
-                let (source, lineno, _, _) = extract_line(location.0, None, input);
+                let line = line_of(location);
                 QasmSimError::IndexOutOfBounds {
-                    source,
+                    source: line.source,
+                    file: line.file,
                     symbol_name,
-                    lineno,
+                    lineno: line.lineno,
                     size,
                     index,
                 }
@@ -389,13 +928,141 @@ impl<'src> From<SrcAndErr<'src, RuntimeError>> for QasmSimError<'src> {
                     location,
                     previous_location,
                 } => {
-                    let (source, lineno, _, _) = extract_line(location.0, None, input);
-                    let (_, previous_lineno, _, _) = extract_line(previous_location.0, None, input);
+                    let line = line_of(location);
+                    let previous_line = line_of(previous_location);
                     QasmSimError::RedefinitionError {
-                        source,
+                        source: line.source,
+                        file: line.file,
+                        symbol_name,
+                        lineno: line.lineno,
+                        previous_lineno: previous_line.lineno,
+                        previous_file: previous_line.file,
+                    }
+                }
+                SemanticError::UseBeforeDefinition {
+                    symbol_name,
+                    location,
+                    definition_location,
+                } => {
+                    let line = line_of(location);
+                    let definition_line = line_of(definition_location);
+                    QasmSimError::UseBeforeDefinition {
+                        source: line.source,
+                        file: line.file,
+                        lineno: line.lineno,
+                        symbol_name,
+                        definition_lineno: definition_line.lineno,
+                        definition_file: definition_line.file,
+                    }
+                }
+                SemanticError::RecursiveGate {
+                    symbol_name,
+                    location,
+                } => {
+                    let line = line_of(location);
+                    QasmSimError::RecursiveGate {
+                        source: line.source,
+                        file: line.file,
+                        lineno: line.lineno,
+                        symbol_name,
+                    }
+                }
+                SemanticError::UndeclaredGateArgument {
+                    gate_name,
+                    symbol_name,
+                    location,
+                } => {
+                    let line = line_of(location);
+                    QasmSimError::UndeclaredGateArgument {
+                        source: line.source,
+                        file: line.file,
+                        lineno: line.lineno,
+                        gate_name,
+                        symbol_name,
+                    }
+                }
+                SemanticError::UndeclaredGateParameter {
+                    gate_name,
+                    symbol_name,
+                    location,
+                } => {
+                    let line = line_of(location);
+                    QasmSimError::UndeclaredGateParameter {
+                        source: line.source,
+                        file: line.file,
+                        lineno: line.lineno,
+                        gate_name,
+                        symbol_name,
+                    }
+                }
+                SemanticError::IndexedGateArgument {
+                    gate_name,
+                    symbol_name,
+                    index,
+                    location,
+                } => {
+                    let line = line_of(location);
+                    QasmSimError::IndexedGateArgument {
+                        source: line.source,
+                        file: line.file,
+                        lineno: line.lineno,
+                        gate_name,
+                        symbol_name,
+                        index,
+                    }
+                }
+                SemanticError::EmptyRegister {
+                    symbol_name,
+                    location,
+                } => {
+                    let line = line_of(location);
+                    QasmSimError::EmptyRegister {
+                        source: line.source,
+                        file: line.file,
+                        lineno: line.lineno,
+                        symbol_name,
+                    }
+                }
+                SemanticError::RegisterTooLarge {
+                    symbol_name,
+                    size,
+                    max_size,
+                    location,
+                } => {
+                    let line = line_of(location);
+                    QasmSimError::RegisterTooLarge {
+                        source: line.source,
+                        file: line.file,
+                        lineno: line.lineno,
+                        symbol_name,
+                        size,
+                        max_size,
+                    }
+                }
+                SemanticError::QuantumCondition {
+                    symbol_name,
+                    location,
+                } => {
+                    let line = line_of(location);
+                    QasmSimError::QuantumCondition {
+                        source: line.source,
+                        file: line.file,
+                        lineno: line.lineno,
+                        symbol_name,
+                    }
+                }
+                SemanticError::QubitAliasing {
+                    gate_name,
+                    symbol_name,
+                    location,
+                } => {
+                    let line = line_of(location);
+                    QasmSimError::QubitAliasing {
+                        source: line.source,
+                        file: line.file,
+                        lineno: line.lineno,
+                        gate_name,
                         symbol_name,
-                        lineno,
-                        previous_lineno,
                     }
                 }
             },
@@ -406,20 +1073,151 @@ impl<'src> From<SrcAndErr<'src, RuntimeError>> for QasmSimError<'src> {
 impl<'src> From<SrcAndErr<'src, LinkerError>> for QasmSimError<'src> {
     fn from(source_and_error: SrcAndErr<'src, LinkerError>) -> Self {
         let (input, error) = source_and_error;
+        QasmSimError::from((&SourceMap::new(input), error))
+    }
+}
+
+impl<'src> From<MapAndErr<'_, 'src, LinkerError>> for QasmSimError<'src> {
+    fn from(map_and_err: MapAndErr<'_, 'src, LinkerError>) -> Self {
+        let (source_map, error) = map_and_err;
+        let line_of = |location| SourceLine::new(source_map, location, None);
         match error {
             LinkerError::LibraryNotFound { location, libpath } => {
-                let (source, lineno, _, _) = extract_line(location.0, None, input);
+                let line = line_of(location);
                 QasmSimError::LibraryNotFound {
-                    source,
+                    source: line.source,
+                    file: line.file,
                     libpath,
-                    lineno,
+                    lineno: line.lineno,
                 }
             }
+            LinkerError::LibraryUnreadable {
+                location,
+                libpath,
+                reason,
+            } => {
+                let line = line_of(location);
+                QasmSimError::LibraryUnreadable {
+                    source: line.source,
+                    file: line.file,
+                    libpath,
+                    reason,
+                    lineno: line.lineno,
+                }
+            }
+            LinkerError::IncludeCycle { location, chain } => {
+                let line = line_of(location);
+                QasmSimError::IncludeCycle {
+                    source: line.source,
+                    file: line.file,
+                    chain,
+                    lineno: line.lineno,
+                }
+            }
+            LinkerError::LibrarySyntaxError {
+                location,
+                libpath,
+                source: library,
+                error,
+            } => {
+                let line = line_of(location);
+                let error = from_parse_error(*error, |location, end| {
+                    SourceLine::in_text(&library, location, end)
+                });
+                let kind = match error {
+                    QasmSimError::InvalidToken { .. } => SyntaxErrorKind::InvalidToken,
+                    QasmSimError::UnexpectedEOF { .. } => SyntaxErrorKind::UnexpectedEOF,
+                    QasmSimError::UnexpectedToken { .. } => SyntaxErrorKind::UnexpectedToken,
+                    _ => unreachable!("parse errors are syntax errors"),
+                };
+                let error = match error {
+                    QasmSimError::InvalidToken {
+                        source,
+                        lineno,
+                        startpos,
+                        endpos,
+                        token,
+                        expected,
+                        ..
+                    }
+                    | QasmSimError::UnexpectedEOF {
+                        source,
+                        lineno,
+                        startpos,
+                        endpos,
+                        token,
+                        expected,
+                        ..
+                    }
+                    | QasmSimError::UnexpectedToken {
+                        source,
+                        lineno,
+                        startpos,
+                        endpos,
+                        token,
+                        expected,
+                        ..
+                    } => LibraryParseError {
+                        kind,
+                        source: source.into(),
+                        lineno,
+                        startpos,
+                        endpos,
+                        token,
+                        expected,
+                    },
+                    _ => unreachable!("parse errors are syntax errors"),
+                };
+                QasmSimError::LibrarySyntaxError {
+                    source: line.source,
+                    file: line.file,
+                    lineno: line.lineno,
+                    libpath,
+                    error: Box::new(error),
+                }
+            }
+        }
+    }
+}
+
+/// The line containing a location, along with the name of its file, its line
+/// number and the positions of the location, and of an optional end, inside
+/// the line.
+struct SourceLine<'src> {
+    source: Cow<'src, str>,
+    file: Option<String>,
+    lineno: usize,
+    startpos: usize,
+    endpos: Option<usize>,
+}
+
+impl<'src> SourceLine<'src> {
+    fn new(source_map: &SourceMap<'src>, location: Location, end: Option<Location>) -> Self {
+        let (source, lineno, startpos, endpos) = source_map.extract_line(location, end);
+        SourceLine {
+            source,
+            file: source_map.name(location.file).map(String::from),
+            lineno,
+            startpos,
+            endpos,
+        }
+    }
+
+    /// Relate `location` with `text` regardless of its file.
+    fn in_text(text: &str, location: Location, end: Option<Location>) -> Self {
+        let (source, lineno, startpos, endpos) =
+            extract_line(location.offset, end.map(|end| end.offset), text);
+        SourceLine {
+            source: Cow::Owned(source.into()),
+            file: None,
+            lineno,
+            startpos,
+            endpos,
         }
     }
 }
 
-fn extract_line(
+pub(crate) fn extract_line(
     offset: usize,
     endoffset: Option<usize>,
     doc: &str,
@@ -452,10 +1250,7 @@ fn extract_line(
     }
 
     let startpos = offset - start;
-    let endpos = match endoffset {
-        None => None,
-        Some(endoffset) => Some(endoffset - start),
-    };
+    let endpos = endoffset.map(|endoffset| endoffset - start);
 
     (&doc[start..end], linecount, startpos, endpos)
 }
@@ -487,3 +1282,35 @@ mod test_into_doc_coords {
     test_last_character: 20, None => ("line 3", 3, 6, None)
   );
 }
+
+#[cfg(test)]
+mod test_into_owned {
+    use std::borrow::Cow;
+    use std::error::Error;
+
+    use super::{OwnedQasmSimError, QasmSimError};
+
+    fn assert_error_send_sync<E: Error + Send + Sync + 'static>() {}
+
+    #[test]
+    fn test_owned_errors_are_send_and_sync() {
+        assert_error_send_sync::<OwnedQasmSimError>();
+    }
+
+    #[test]
+    fn test_into_owned_keeps_the_error() {
+        let source = String::from("OPENQASM 2.0;\nqreg q[1];\nqreg q[1];\n");
+        let error = crate::run(&source, None).expect_err("redefinition");
+        let owned = error.clone().into_owned();
+        drop(source);
+        assert!(matches!(
+            owned,
+            QasmSimError::RedefinitionError {
+                source: Cow::Owned(_),
+                ..
+            }
+        ));
+        assert_eq!(owned.code(), "E0201");
+        assert!(format!("{}", owned).contains("qreg q[1];"));
+    }
+}
@@ -0,0 +1,187 @@
+use std::ops::Range;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::humanize::{human_description, sourceless_message};
+use crate::error::QasmSimError;
+
+/// How serious a diagnostic is.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    /// The program cannot run.
+    Error,
+    /// The program can run but it is suspicious.
+    Warning,
+}
+
+/// Another place in the sources worth looking at for understanding a
+/// diagnostic, such as the first declaration of a symbol declared twice.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RelatedLocation {
+    /// What the location is.
+    pub message: String,
+    /// Name of the file, if it is not the program.
+    pub file: Option<String>,
+    /// Line number (1-based).
+    pub line: usize,
+}
+
+/// A machine-readable description of an error or a warning, for editors and
+/// other tools. With the `serde` feature, it serializes to the JSON objects
+/// printed by the CLI with `--error-format json`.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::error::{Diagnostic, Severity};
+///
+/// let source = "OPENQASM 2.0;\nqreg q[2];\nqreg q[2];\n";
+/// let error = qasmsim::run(source, None).expect_err("redefinition");
+/// let diagnostic = Diagnostic::from(&error);
+///
+/// assert_eq!(diagnostic.code, "E0201");
+/// assert_eq!(diagnostic.severity, Severity::Error);
+/// assert_eq!(diagnostic.message, "cannot declare symbol `q` twice");
+/// assert_eq!(diagnostic.line, Some(3));
+/// assert_eq!(diagnostic.columns, Some(1..2));
+/// assert_eq!(diagnostic.related[0].line, 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Diagnostic {
+    /// Stable code of the error or the warning, such as `E0101`.
+    pub code: String,
+    /// Whether this is an error or a warning.
+    pub severity: Severity,
+    /// Description of the problem.
+    pub message: String,
+    /// Suggestion for fixing the problem.
+    pub hint: Option<String>,
+    /// Name of the file, if it is not the program.
+    pub file: Option<String>,
+    /// Line number (1-based), if the problem relates to the source code.
+    pub line: Option<usize>,
+    /// Columns (1-based, end excluded) of the problem inside the line.
+    pub columns: Option<Range<usize>>,
+    /// Other locations related to the problem.
+    pub related: Vec<RelatedLocation>,
+}
+
+impl From<&QasmSimError<'_>> for Diagnostic {
+    fn from(error: &QasmSimError<'_>) -> Self {
+        let mut diagnostic = match human_description(error) {
+            Some(description) => Diagnostic {
+                code: error.code().into(),
+                severity: Severity::Error,
+                message: description.msg,
+                hint: description.help,
+                file: description.file,
+                line: Some(description.lineno),
+                columns: Some(columns(description.startpos, description.endpos)),
+                related: vec![],
+            },
+            None => Diagnostic {
+                code: error.code().into(),
+                severity: Severity::Error,
+                message: sourceless_message(error).unwrap_or_default(),
+                hint: None,
+                file: None,
+                line: None,
+                columns: None,
+                related: vec![],
+            },
+        };
+        diagnostic.related = related_locations(error);
+        diagnostic
+    }
+}
+
+/// Return the columns (1-based, end excluded) from the positions (0-based)
+/// inside a line.
+pub(crate) fn columns(startpos: usize, endpos: Option<usize>) -> Range<usize> {
+    let end = endpos.unwrap_or(startpos + 1).max(startpos + 1);
+    startpos + 1..end + 1
+}
+
+fn related_locations(error: &QasmSimError<'_>) -> Vec<RelatedLocation> {
+    match error {
+        QasmSimError::RedefinitionError {
+            previous_lineno,
+            previous_file,
+            ..
+        } => vec![RelatedLocation {
+            message: "first declaration".into(),
+            file: previous_file.clone(),
+            line: *previous_lineno,
+        }],
+        QasmSimError::UseBeforeDefinition {
+            definition_lineno,
+            definition_file,
+            ..
+        } => vec![RelatedLocation {
+            message: "definition".into(),
+            file: definition_file.clone(),
+            line: *definition_lineno,
+        }],
+        QasmSimError::LibrarySyntaxError { file, lineno, .. } => vec![RelatedLocation {
+            message: "included here".into(),
+            file: file.clone(),
+            line: *lineno,
+        }],
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostic_of_sourceless_errors() {
+        let error = QasmSimError::InitialStateWidthMismatch {
+            expected: 2,
+            given: 3,
+        };
+        assert_eq!(
+            Diagnostic::from(&error),
+            Diagnostic {
+                code: "E0107".into(),
+                severity: Severity::Error,
+                message: "the initial state has 3 qubits but the program declares 2".into(),
+                hint: None,
+                file: None,
+                line: None,
+                columns: None,
+                related: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_of_library_syntax_errors() {
+        let source = "OPENQASM 2.0;\ninclude \"bad.inc\";\n";
+        let error = crate::parse_and_link_with_resolver(
+            source,
+            crate::EmbeddedResolver::default().with_library("bad.inc", "gate g q {\n  U q;\n}\n"),
+        )
+        .expect_err("syntax error in the library");
+        let diagnostic = Diagnostic::from(&error);
+        assert_eq!(diagnostic.code, "E0303");
+        assert_eq!(diagnostic.file, Some("bad.inc".into()));
+        assert_eq!(diagnostic.line, Some(2));
+        assert_eq!(diagnostic.columns, Some(5..6));
+        assert_eq!(
+            diagnostic.related,
+            vec![RelatedLocation {
+                message: "included here".into(),
+                file: None,
+                line: 2,
+            }]
+        );
+    }
+}
@@ -1,6 +1,7 @@
+use std::borrow::Cow;
 use std::fmt::{self, Write};
 
-use crate::error::QasmSimError;
+use crate::error::{QasmSimError, SyntaxErrorKind};
 
 macro_rules! lazy_humanize {
     ($err:expr, $($variant:path),*) => {{
@@ -10,7 +11,7 @@ macro_rules! lazy_humanize {
                     location,
                     ..
                 } => {
-                    Some(format!("{} at character {}", stringify!($variant), location.0))
+                    Some(format!("{} at character {}", stringify!($variant), location.offset))
                 }
             )*
             #[allow(unreachable_patterns)]
@@ -21,18 +22,20 @@ macro_rules! lazy_humanize {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HumanDescription {
-    msg: String,
-    lineno: usize,
-    startpos: usize,
-    endpos: Option<usize>,
-    linesrc: String,
-    help: Option<String>,
+    pub(crate) msg: String,
+    pub(crate) file: Option<String>,
+    pub(crate) lineno: usize,
+    pub(crate) startpos: usize,
+    pub(crate) endpos: Option<usize>,
+    pub(crate) linesrc: String,
+    pub(crate) help: Option<String>,
 }
 
-fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
+pub(crate) fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
     match error {
         QasmSimError::InvalidToken {
             source,
+            file,
             lineno,
             startpos,
             endpos,
@@ -42,29 +45,33 @@ fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
             lineno: *lineno,
             startpos: *startpos,
             endpos: *endpos,
-            linesrc: (*source).into(),
+            linesrc: source.to_string(),
+            file: file.clone(),
             help: None,
         }),
         QasmSimError::UnexpectedEOF {
             source,
+            file,
             lineno,
             startpos,
             endpos,
             expected,
             ..
         } => {
-            let expectation = expectation(&expected);
+            let expectation = expectation(expected);
             Some(HumanDescription {
                 msg: format!("{}, found EOF", &expectation),
                 lineno: *lineno,
                 startpos: *startpos,
                 endpos: *endpos,
-                linesrc: (*source).into(),
-                help: Some(format!("{} here", hint(&expected))),
+                linesrc: source.to_string(),
+                file: file.clone(),
+                help: Some(format!("{} here", hint(expected))),
             })
         }
         QasmSimError::UnexpectedToken {
             source,
+            file,
             lineno,
             startpos,
             endpos,
@@ -76,9 +83,9 @@ fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
 
             let mut msg = format!("unexpected \"{}\" found", &token);
             let help = if !expected.is_empty() {
-                let expectation = expectation(&expected);
+                let expectation = expectation(expected);
                 msg = format!("{}, found \"{}\"", &expectation, &token);
-                Some(format!("{} before this", hint(&expected)))
+                Some(format!("{} before this", hint(expected)))
             } else {
                 None
             };
@@ -88,29 +95,39 @@ fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
                 lineno: *lineno,
                 startpos: *startpos,
                 endpos: Some(endpos),
-                linesrc: (*source).into(),
+                linesrc: source.to_string(),
+                file: file.clone(),
                 help,
             })
         }
         QasmSimError::RedefinitionError {
             source,
+            file,
             lineno,
             previous_lineno,
+            previous_file,
             symbol_name,
         } => Some(HumanDescription {
             msg: format!("cannot declare symbol `{}` twice", symbol_name),
             lineno: *lineno,
             startpos: 0,
             endpos: None,
-            linesrc: (*source).into(),
-            help: Some(format!(
-                "first declaration happens in line {}",
-                *previous_lineno
-            )),
+            linesrc: source.to_string(),
+            file: file.clone(),
+            help: Some(if previous_file == file {
+                format!("first declaration happens in line {}", *previous_lineno)
+            } else {
+                format!(
+                    "first declaration happens in line {} of {}",
+                    *previous_lineno,
+                    file_description(previous_file)
+                )
+            }),
         }),
         QasmSimError::IndexOutOfBounds {
             symbol_name,
             source,
+            file,
             lineno,
             index,
             size,
@@ -119,7 +136,8 @@ fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
             lineno: *lineno,
             startpos: 0,
             endpos: None,
-            linesrc: (*source).into(),
+            linesrc: source.to_string(),
+            file: file.clone(),
             help: Some(format!(
                 "indices of register `{}` range from 0 to {} but the index is {}",
                 symbol_name,
@@ -129,6 +147,7 @@ fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
         }),
         QasmSimError::SymbolNotFound {
             source,
+            file,
             symbol_name,
             lineno,
             expected,
@@ -140,11 +159,13 @@ fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
             lineno: *lineno,
             startpos: 0,
             endpos: None,
-            linesrc: (*source).into(),
+            linesrc: source.to_string(),
+            file: file.clone(),
             help: None,
         }),
         QasmSimError::TypeMismatch {
             source,
+            file,
             symbol_name,
             lineno,
             expected,
@@ -153,7 +174,8 @@ fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
                 "mismatched types for symbol `{}`: expected \"{}\"",
                 symbol_name, expected
             ),
-            linesrc: (*source).into(),
+            linesrc: source.to_string(),
+            file: file.clone(),
             lineno: *lineno,
             startpos: 0,
             endpos: None,
@@ -161,6 +183,7 @@ fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
         }),
         QasmSimError::RegisterSizeMismatch {
             source,
+            file,
             symbol_name,
             lineno,
             sizes,
@@ -176,7 +199,8 @@ fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
             };
             Some(HumanDescription {
                 msg,
-                linesrc: (*source).into(),
+                linesrc: source.to_string(),
+                file: file.clone(),
                 lineno: *lineno,
                 startpos: 0,
                 endpos: None,
@@ -188,6 +212,7 @@ fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
         }
         QasmSimError::WrongNumberOfParameters {
             source,
+            file,
             symbol_name,
             are_registers,
             lineno,
@@ -204,7 +229,8 @@ fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
                     "wrong number of {} passed to gate `{}`",
                     qualifier, symbol_name
                 ),
-                linesrc: (*source).into(),
+                linesrc: source.to_string(),
+                file: file.clone(),
                 lineno: *lineno,
                 startpos: 0,
                 endpos: None,
@@ -216,28 +242,276 @@ fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
         }
         QasmSimError::UndefinedGate {
             source,
+            file,
             symbol_name,
             lineno,
         } => Some(HumanDescription {
             msg: format!("cannot find gate `{}` in this scope", symbol_name),
-            linesrc: (*source).into(),
+            linesrc: source.to_string(),
+            file: file.clone(),
             lineno: *lineno,
             startpos: 0,
             endpos: None,
             help: None,
         }),
+        QasmSimError::UseBeforeDefinition {
+            source,
+            file,
+            lineno,
+            symbol_name,
+            definition_lineno,
+            definition_file,
+        } => Some(HumanDescription {
+            msg: format!("cannot use gate `{}` before its definition", symbol_name),
+            linesrc: source.to_string(),
+            file: file.clone(),
+            lineno: *lineno,
+            startpos: 0,
+            endpos: None,
+            help: Some(if definition_file == file {
+                format!(
+                    "gate `{}` is defined in line {}",
+                    symbol_name, definition_lineno
+                )
+            } else {
+                format!(
+                    "gate `{}` is defined in line {} of {}",
+                    symbol_name,
+                    definition_lineno,
+                    file_description(definition_file)
+                )
+            }),
+        }),
+        QasmSimError::RecursiveGate {
+            source,
+            file,
+            lineno,
+            symbol_name,
+        } => Some(HumanDescription {
+            msg: format!("gate `{}` calls itself", symbol_name),
+            linesrc: source.to_string(),
+            file: file.clone(),
+            lineno: *lineno,
+            startpos: 0,
+            endpos: None,
+            help: Some("gates cannot be recursive".into()),
+        }),
+        QasmSimError::UndeclaredGateArgument {
+            source,
+            file,
+            lineno,
+            gate_name,
+            symbol_name,
+        } => Some(HumanDescription {
+            msg: format!(
+                "cannot find the argument `{}` in the body of gate `{}`",
+                symbol_name, gate_name
+            ),
+            linesrc: source.to_string(),
+            file: file.clone(),
+            lineno: *lineno,
+            startpos: 0,
+            endpos: None,
+            help: Some("gate bodies can only use the arguments of the gate".into()),
+        }),
+        QasmSimError::UndeclaredGateParameter {
+            source,
+            file,
+            lineno,
+            gate_name,
+            symbol_name,
+        } => Some(HumanDescription {
+            msg: format!(
+                "cannot find the real parameter `{}` in the body of gate `{}`",
+                symbol_name, gate_name
+            ),
+            linesrc: source.to_string(),
+            file: file.clone(),
+            lineno: *lineno,
+            startpos: 0,
+            endpos: None,
+            help: Some("gate bodies can only use the real parameters of the gate".into()),
+        }),
+        QasmSimError::IndexedGateArgument {
+            source,
+            file,
+            lineno,
+            gate_name,
+            symbol_name,
+            index,
+        } => Some(HumanDescription {
+            msg: format!(
+                "cannot index the argument `{}` in the body of gate `{}`",
+                symbol_name, gate_name
+            ),
+            linesrc: source.to_string(),
+            file: file.clone(),
+            lineno: *lineno,
+            startpos: 0,
+            endpos: None,
+            help: Some(format!(
+                "gate arguments are single qubits, use `{}` instead of `{}[{}]`",
+                symbol_name, symbol_name, index
+            )),
+        }),
+        QasmSimError::EmptyRegister {
+            source,
+            file,
+            lineno,
+            symbol_name,
+        } => Some(HumanDescription {
+            msg: format!("cannot declare register `{}` with no elements", symbol_name),
+            linesrc: source.to_string(),
+            file: file.clone(),
+            lineno: *lineno,
+            startpos: 0,
+            endpos: None,
+            help: Some("registers must have at least one element".into()),
+        }),
+        QasmSimError::RegisterTooLarge {
+            source,
+            file,
+            lineno,
+            symbol_name,
+            size,
+            max_size,
+        } => Some(HumanDescription {
+            msg: format!("register `{}` is too large", symbol_name),
+            linesrc: source.to_string(),
+            file: file.clone(),
+            lineno: *lineno,
+            startpos: 0,
+            endpos: None,
+            help: Some(format!(
+                "classical registers can have {} bits at most, found {}",
+                max_size, size
+            )),
+        }),
+        QasmSimError::QuantumCondition {
+            source,
+            file,
+            lineno,
+            symbol_name,
+        } => Some(HumanDescription {
+            msg: format!(
+                "cannot test the value of the quantum register `{}`",
+                symbol_name
+            ),
+            linesrc: source.to_string(),
+            file: file.clone(),
+            lineno: *lineno,
+            startpos: 0,
+            endpos: None,
+            help: Some("conditionals can only test classical registers".into()),
+        }),
+        QasmSimError::QubitAliasing {
+            source,
+            file,
+            lineno,
+            gate_name,
+            symbol_name,
+        } => Some(HumanDescription {
+            msg: format!(
+                "cannot pass the same qubit of `{}` twice to gate `{}`",
+                symbol_name, gate_name
+            ),
+            linesrc: source.to_string(),
+            file: file.clone(),
+            lineno: *lineno,
+            startpos: 0,
+            endpos: None,
+            help: Some("the qubits of a gate application must be different".into()),
+        }),
         QasmSimError::LibraryNotFound {
             source,
+            file,
             lineno,
             libpath,
         } => Some(HumanDescription {
             msg: format!("cannot find library `{}`", libpath),
-            linesrc: (*source).into(),
+            linesrc: source.to_string(),
+            file: file.clone(),
+            lineno: *lineno,
+            startpos: 0,
+            endpos: None,
+            help: None,
+        }),
+        QasmSimError::LibraryUnreadable {
+            source,
+            file,
+            lineno,
+            libpath,
+            reason,
+        } => Some(HumanDescription {
+            msg: format!("cannot read library `{}`: {}", libpath, reason),
+            linesrc: source.to_string(),
+            file: file.clone(),
             lineno: *lineno,
             startpos: 0,
             endpos: None,
             help: None,
         }),
+        QasmSimError::IncludeCycle {
+            source,
+            file,
+            lineno,
+            chain,
+        } => Some(HumanDescription {
+            msg: format!(
+                "library `{}` includes itself",
+                chain.last().map(String::as_str).unwrap_or_default()
+            ),
+            linesrc: source.to_string(),
+            file: file.clone(),
+            lineno: *lineno,
+            startpos: 0,
+            endpos: None,
+            help: Some(format!("include chain: {}", chain.join(" -> "))),
+        }),
+        QasmSimError::LibrarySyntaxError { libpath, error, .. } => {
+            let (source, file, lineno, startpos, endpos, token, expected) = (
+                Cow::Borrowed(error.source.as_str()),
+                Some(libpath.clone()),
+                error.lineno,
+                error.startpos,
+                error.endpos,
+                error.token.clone(),
+                error.expected.clone(),
+            );
+            let syntax_error = match error.kind {
+                SyntaxErrorKind::UnexpectedToken => QasmSimError::UnexpectedToken {
+                    source,
+                    file,
+                    lineno,
+                    startpos,
+                    endpos,
+                    token,
+                    expected,
+                },
+                SyntaxErrorKind::UnexpectedEOF => QasmSimError::UnexpectedEOF {
+                    source,
+                    file,
+                    lineno,
+                    startpos,
+                    endpos,
+                    token,
+                    expected,
+                },
+                SyntaxErrorKind::InvalidToken => QasmSimError::InvalidToken {
+                    source,
+                    file,
+                    lineno,
+                    startpos,
+                    endpos,
+                    token,
+                    expected,
+                },
+            };
+            human_description(&syntax_error).map(|description| HumanDescription {
+                msg: format!("{} in library `{}`", description.msg, libpath),
+                ..description
+            })
+        }
         _ => None,
     }
 }
@@ -245,6 +519,37 @@ fn human_description(error: &QasmSimError) -> Option<HumanDescription> {
 pub fn humanize_error<W: Write>(buffer: &mut W, error: &QasmSimError) -> fmt::Result {
     match error {
         QasmSimError::UnknownError(msg) => write!(buffer, "{}", msg),
+        QasmSimError::InitialStateWidthMismatch { .. }
+        | QasmSimError::InitialStateNotNormalized
+        | QasmSimError::TooManyQubits { .. } => {
+            let msg = sourceless_message(error).expect("some message");
+            writeln!(buffer, "error: {}", msg)
+        }
+        QasmSimError::LibrarySyntaxError {
+            source,
+            file,
+            lineno,
+            ..
+        } => {
+            let description: HumanDescription =
+                human_description(error).expect("some human description");
+            humanize(buffer, &description)?;
+            match file {
+                None => writeln!(
+                    buffer,
+                    "note: included at line {}: {}",
+                    lineno,
+                    source.trim()
+                ),
+                Some(_) => writeln!(
+                    buffer,
+                    "note: included at line {} of {}: {}",
+                    lineno,
+                    file_description(file),
+                    source.trim()
+                ),
+            }
+        }
         _ => {
             let description: HumanDescription =
                 human_description(error).expect("some human description");
@@ -253,45 +558,85 @@ pub fn humanize_error<W: Write>(buffer: &mut W, error: &QasmSimError) -> fmt::Re
     }
 }
 
+/// Return the message of the errors that do not relate to the source code.
+pub(crate) fn sourceless_message(error: &QasmSimError) -> Option<String> {
+    match error {
+        QasmSimError::UnknownError(msg) => Some(msg.clone()),
+        QasmSimError::InitialStateWidthMismatch { expected, given } => Some(format!(
+            "the initial state has {} qubits but the program declares {}",
+            given, expected
+        )),
+        QasmSimError::InitialStateNotNormalized => {
+            Some("the initial state is not normalized".into())
+        }
+        QasmSimError::TooManyQubits { qubits, max_qubits } => Some(format!(
+            "the program declares {} qubits but the simulator supports {} at most",
+            qubits, max_qubits
+        )),
+        _ => None,
+    }
+}
+
 fn humanize<W: Write>(buffer: &mut W, descripition: &HumanDescription) -> fmt::Result {
-    match descripition {
-        HumanDescription {
-            msg,
-            lineno,
-            startpos,
-            endpos,
-            linesrc,
-            help,
-        } => {
-            let lineno_str = format!("{} ", lineno);
-            let lineno_len = lineno_str.len();
-            let linesrc_str: String = linesrc.into();
-            let linesrc_str_trimmed = linesrc_str.trim_end();
-            let help_str = help.clone().unwrap_or_else(|| msg.clone());
-            let indicator_width = if let Some(pos) = endpos {
-                pos - startpos
-            } else {
-                1
-            };
+    humanize_with_severity(buffer, "error", descripition)
+}
 
-            writeln!(buffer, "error: {}", msg)?;
-            writeln!(buffer, "{:>alignment$}|", "", alignment = lineno_len)?;
-            writeln!(buffer, "{}| {}", lineno_str, linesrc_str_trimmed)?;
-            writeln!(
-                buffer,
-                "{:>alignment$}| {:>padding$}{:^>indicator_width$} help: {}",
-                "",
-                "",
-                "",
-                help_str,
-                alignment = lineno_str.len(),
-                padding = startpos,
-                indicator_width = indicator_width
-            )?;
+/// Render `description` as [`humanize()`] does, but starting with `severity`
+/// instead of "error".
+///
+/// [`humanize()`]: ./fn.humanize.html
+pub(crate) fn humanize_with_severity<W: Write>(
+    buffer: &mut W,
+    severity: &str,
+    descripition: &HumanDescription,
+) -> fmt::Result {
+    let HumanDescription {
+        msg,
+        file,
+        lineno,
+        startpos,
+        endpos,
+        linesrc,
+        help,
+    } = descripition;
+    let lineno_str = format!("{} ", lineno);
+    let lineno_len = lineno_str.len();
+    let linesrc_str: String = linesrc.into();
+    let linesrc_str_trimmed = linesrc_str.trim_end();
+    let help_str = help.clone().unwrap_or_else(|| msg.clone());
+    let indicator_width = if let Some(pos) = endpos {
+        pos - startpos
+    } else {
+        1
+    };
 
-            fmt::Result::Ok(())
-        }
+    writeln!(buffer, "{}: {}", severity, msg)?;
+    if let Some(file) = file {
+        writeln!(
+            buffer,
+            "{:>alignment$}--> {}:{}:{}",
+            "",
+            file,
+            lineno,
+            startpos + 1,
+            alignment = lineno_len - 1
+        )?;
     }
+    writeln!(buffer, "{:>alignment$}|", "", alignment = lineno_len)?;
+    writeln!(buffer, "{}| {}", lineno_str, linesrc_str_trimmed)?;
+    writeln!(
+        buffer,
+        "{:>alignment$}| {:>padding$}{:^>indicator_width$} help: {}",
+        "",
+        "",
+        "",
+        help_str,
+        alignment = lineno_str.len(),
+        padding = startpos,
+        indicator_width = indicator_width
+    )?;
+
+    fmt::Result::Ok(())
 }
 
 fn expectation(expected: &[String]) -> String {
@@ -308,6 +653,13 @@ fn hint(expected: &[String]) -> String {
     )
 }
 
+fn file_description(file: &Option<String>) -> String {
+    match file {
+        Some(file) => format!("`{}`", file),
+        None => "the program".into(),
+    }
+}
+
 fn list_of_choices(choices: &[String]) -> Option<String> {
     let len = choices.len();
     match len {
@@ -330,10 +682,12 @@ mod test_humanize_error {
     use indoc::indoc;
 
     use super::*;
+    use crate::error::LibraryParseError;
 
     #[test]
     fn test_eof_error() {
         let error = HumanDescription {
+            file: None,
             msg: r#"expected ";", found EOF"#.into(),
             lineno: 777,
             startpos: 10,
@@ -359,6 +713,7 @@ mod test_humanize_error {
     #[test]
     fn test_complete_error() {
         let error = HumanDescription {
+            file: None,
             msg: r#"expected ";", found "qreg""#.into(),
             lineno: 778,
             startpos: 0,
@@ -384,6 +739,7 @@ mod test_humanize_error {
     #[test]
     fn test_no_hint_error() {
         let error = HumanDescription {
+            file: None,
             msg: r#"unexpected keyword `qreg` found"#.into(),
             lineno: 778,
             startpos: 0,
@@ -409,6 +765,7 @@ mod test_humanize_error {
     #[test]
     fn test_trim_line_source_end() {
         let error = HumanDescription {
+            file: None,
             msg: r#"unexpected keyword `qreg` found"#.into(),
             lineno: 778,
             startpos: 0,
@@ -434,6 +791,7 @@ mod test_humanize_error {
     #[test]
     fn test_preserve_line_source_start() {
         let error = HumanDescription {
+            file: None,
             msg: r#"unexpected keyword `qreg` found"#.into(),
             lineno: 778,
             startpos: 2,
@@ -455,4 +813,25 @@ mod test_humanize_error {
             )
         );
     }
+
+    #[test]
+    fn test_library_syntax_errors_keep_their_kind() {
+        let error = QasmSimError::LibrarySyntaxError {
+            source: "include \"lib.inc\";\n".into(),
+            file: None,
+            lineno: 2,
+            libpath: "lib.inc".into(),
+            error: Box::new(LibraryParseError {
+                kind: SyntaxErrorKind::InvalidToken,
+                source: "gate g q { $ }\n".into(),
+                lineno: 1,
+                startpos: 11,
+                endpos: None,
+                token: None,
+                expected: vec!["\"}\"".into()],
+            }),
+        };
+        let description = human_description(&error).expect("some description");
+        assert_eq!(description.msg, "invalid token in library `lib.inc`");
+    }
 }
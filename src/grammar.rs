@@ -10,29 +10,39 @@
 //! for enabling the users to experiment with performing code manipulations at
 //! the abstract level.
 
-use lalrpop_util::{self, lalrpop_mod};
+use lalrpop_util::{self, lalrpop_mod, ErrorRecovery};
 
 pub mod ast;
 pub mod lexer;
+mod printer;
+pub mod source_map;
 lalrpop_mod!(
     #[allow(clippy::all)]
     open_qasm2,
     "/grammar/open_qasm2.rs"
 );
 
-use self::ast::{Expression, OpenQasmLibrary, OpenQasmProgram, Span, Statement};
-use self::lexer::Lexer;
-use crate::error::QasmSimError;
+use self::ast::{
+    Expression, OpenQasmLibrary, OpenQasmProgram, Span, Statement, Trivia, TriviaAnchor, TriviaKind,
+};
+use self::lexer::{
+    tokenize_with_trivia, FileId, Lexer, LexicalError, Location, Tok, Token, TokenKind,
+};
+use crate::error::{ParseError, QasmSimError};
 
 macro_rules! parse_functions {
     ($($(#[$attr:meta])* $vis:vis fn $funcname:ident ($param:ident) -> $rettype:ty => $parser:ty;)*) => {
         $(
             $(#[$attr])* $vis fn $funcname(
                 $param: &str
-            ) -> Result<$rettype, QasmSimError> {
-                let lexer = Lexer::new($param);
-                let parser = <$parser>::new();
-                parser.parse(lexer).map_err(|err| ($param, err).into())
+            ) -> Result<$rettype, QasmSimError<'_>> {
+                let (tree, mut errors) = recovering(|recovered| {
+                    <$parser>::new().parse($param, recovered, Lexer::new($param))
+                });
+                match tree {
+                    Some(tree) if errors.is_empty() => Ok(tree),
+                    _ => Err(($param, errors.remove(0)).into()),
+                }
             }
         )*
     };
@@ -109,7 +119,8 @@ parse_functions! {
     ///                         vec![Argument::Id("q".to_string())]
     ///                     )
     ///                 )
-    ///             ]
+    ///             ],
+    ///             None
     ///         )
     ///     ]
     /// });
@@ -155,7 +166,7 @@ parse_functions! {
     /// assert_eq!(program_ast, OpenQasmProgram{
     ///     version: "2.0".to_string(),
     ///     program: vec![Span{
-    ///         boundaries: (Location(15), Location(46)),
+    ///         boundaries: (Location::new_at(15), Location::new_at(46)),
     ///         node: Box::new(Statement::GateDecl(
     ///             "idle".to_string(),
     ///             vec![],
@@ -172,9 +183,11 @@ parse_functions! {
     ///                         vec![Argument::Id("q".to_string())]
     ///                     )
     ///                 )
-    ///             ]
+    ///             ],
+    ///             None
     ///         ))
-    ///     }]
+    ///     }],
+    ///     trivia: vec![]
     /// });
     /// # Ok::<(), QasmSimError>(())
     /// ```
@@ -210,7 +223,7 @@ parse_functions! {
     /// let statement_list = parse_program_body("gate idle q { U(0, 0, 0) q; }")?;
     ///
     /// assert_eq!(statement_list, vec![Span{
-    ///     boundaries: (Location(0), Location(29)),
+    ///     boundaries: (Location::new_at(0), Location::new_at(29)),
     ///     node: Box::new(Statement::GateDecl(
     ///         "idle".to_string(),
     ///         vec![],
@@ -227,7 +240,8 @@ parse_functions! {
     ///                     vec![Argument::Id("q".to_string())]
     ///                 )
     ///             )
-    ///         ]
+    ///         ],
+    ///         None
     ///     ))
     /// }]);
     /// # Ok::<(), QasmSimError>(())
@@ -276,7 +290,8 @@ parse_functions! {
     ///                 vec![Argument::Id("q".to_string())]
     ///             )
     ///         )
-    ///     ]
+    ///     ],
+    ///     None
     /// ));
     /// # Ok::<(), QasmSimError>(())
     /// ```
@@ -291,6 +306,226 @@ parse_functions! {
     pub fn parse_statement(source) -> Statement => open_qasm2::StatementParser;
 }
 
+/// Parse `source` into a [`OpenQasmProgram`] AST, going on after the syntax
+/// errors to report all of them.
+///
+/// After a syntax error, the parser skips to the next `;` or `}` and goes on
+/// parsing. Return the AST, without the statements containing errors, and
+/// the list of errors, in the order they appear in the source. The AST is
+/// `None` if the parser cannot recover from an error, for instance, if the
+/// program is missing the `OPENQASM` header.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::grammar::parse_program_with_recovery;
+///
+/// let (program, errors) = parse_program_with_recovery(r#"
+/// OPENQASM 2.0;
+/// qreg q[2]
+/// creg c[2];
+/// measure q[0] -> ;
+/// reset q;
+/// "#);
+///
+/// assert_eq!(errors.len(), 2);
+/// assert_eq!(program.expect("a partial AST").program.len(), 1);
+/// ```
+///
+/// [`OpenQasmProgram`]: ./ast/struct.OpenQasmProgram.html
+pub fn parse_program_with_recovery(
+    source: &str,
+) -> (Option<OpenQasmProgram>, Vec<QasmSimError<'_>>) {
+    let (tree, errors) = recovering(|recovered| {
+        open_qasm2::OpenQasmProgramParser::new().parse(source, recovered, Lexer::new(source))
+    });
+    let errors = errors
+        .into_iter()
+        .map(|error| QasmSimError::from((source, error)))
+        .collect();
+    (tree, errors)
+}
+
+/// Parse `source` into a [`OpenQasmProgram`] AST keeping the comments and
+/// blank lines of the source as [`Trivia`], so printing the program
+/// preserves them.
+///
+/// The other parsing functions leave [`OpenQasmProgram::trivia`] empty since
+/// collecting trivia takes a second pass over the source. Like
+/// [`parse_program_with_recovery()`], go on after the syntax errors to report
+/// all of them.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::grammar::parse_program_with_trivia;
+///
+/// let source = "OPENQASM 2.0;\n// Two qubits\nqreg q[2];\n";
+/// let (program, errors) = parse_program_with_trivia(source);
+///
+/// assert!(errors.is_empty());
+/// assert_eq!(program.expect("an AST").to_string(), source);
+/// ```
+///
+/// [`OpenQasmProgram`]: ./ast/struct.OpenQasmProgram.html
+/// [`Trivia`]: ./ast/struct.Trivia.html
+/// [`OpenQasmProgram::trivia`]: ./ast/struct.OpenQasmProgram.html#structfield.trivia
+/// [`parse_program_with_recovery()`]: ./fn.parse_program_with_recovery.html
+pub fn parse_program_with_trivia(
+    source: &str,
+) -> (Option<OpenQasmProgram>, Vec<QasmSimError<'_>>) {
+    let (mut tree, errors) = parse_program_with_recovery(source);
+    if let Some(tree) = &mut tree {
+        tree.trivia = program_trivia(source, &tree.program);
+    }
+    (tree, errors)
+}
+
+/// Parse `source` as the library `file`, keeping the location of each
+/// definition. The error is kept apart from the source code so the linker
+/// can report it against the library.
+pub(crate) fn parse_library_source(
+    source: &str,
+    file: FileId,
+) -> Result<Vec<Span<Statement>>, ParseError> {
+    let (tree, mut errors) = recovering(|recovered| {
+        open_qasm2::LibraryBodyParser::new().parse(
+            source,
+            recovered,
+            Lexer::with_file(source, file),
+        )
+    });
+    match tree {
+        Some(tree) if errors.is_empty() => Ok(tree),
+        _ => Err(errors.remove(0)),
+    }
+}
+
+/// Return the text of the comment lines right before the declaration at
+/// `offset` of `source`, without the leading `//`. Only declarations starting
+/// their line are documented.
+pub(crate) fn doc_comment(source: &str, offset: usize) -> Option<String> {
+    let offset = offset.min(source.len());
+    let line_start = source[..offset].rfind('\n').map_or(0, |index| index + 1);
+    if !source[line_start..offset].trim().is_empty() {
+        return None;
+    }
+    let mut lines: Vec<&str> = source[..line_start]
+        .lines()
+        .rev()
+        .map(str::trim)
+        .take_while(|line| line.starts_with("//"))
+        .map(|line| line.trim_start_matches('/').trim())
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    lines.reverse();
+    Some(lines.join("\n"))
+}
+
+/// Return the comments and blank lines of `source`, the source code of a
+/// program whose body is `program`. Blank lines at the start and the end of
+/// the program, or of the body of a gate, are not kept.
+fn program_trivia(source: &str, program: &[Span<Statement>]) -> Vec<Trivia> {
+    let tokens = tokenize_with_trivia(source);
+    let mut trivia = vec![];
+    let mut code_before = false;
+    let mut code_in_line = false;
+    for (index, token) in tokens.iter().enumerate() {
+        let kind = match token.kind {
+            TokenKind::Comment => {
+                let text = token.text.trim_end().to_string();
+                if code_in_line {
+                    TriviaKind::TrailingComment(text)
+                } else {
+                    TriviaKind::Comment(text)
+                }
+            }
+            TokenKind::Whitespace => {
+                if token.text.contains('\n') {
+                    code_in_line = false;
+                }
+                let previous = tokens[..index]
+                    .iter()
+                    .rev()
+                    .find(|token| token.kind != TokenKind::Whitespace);
+                let next = tokens[index + 1..]
+                    .iter()
+                    .find(|token| token.kind != TokenKind::Whitespace);
+                match (previous, next) {
+                    (Some(previous), Some(next))
+                        if token.text.matches('\n').count() > 1
+                            && previous.text != "{"
+                            && next.text != "}" =>
+                    {
+                        TriviaKind::BlankLine
+                    }
+                    _ => continue,
+                }
+            }
+            _ => {
+                code_before = true;
+                code_in_line = true;
+                continue;
+            }
+        };
+        let anchor = if code_before {
+            trivia_anchor(&tokens[..index], token.range.start, program)
+        } else {
+            TriviaAnchor::Header
+        };
+        trivia.push(Trivia { kind, anchor });
+    }
+    trivia
+}
+
+/// Return the anchor of the trivia at `offset`, given the tokens before it.
+fn trivia_anchor(before: &[Token], offset: usize, program: &[Span<Statement>]) -> TriviaAnchor {
+    let index = program
+        .iter()
+        .take_while(|span| span.boundaries.0.offset < offset)
+        .count();
+    if index > 0 {
+        let span = &program[index - 1];
+        if let Statement::GateDecl(_, _, _, body, _) = &*span.node {
+            if offset < span.boundaries.1.offset {
+                let operations = before
+                    .iter()
+                    .filter(|token| token.range.start >= span.boundaries.0.offset)
+                    .skip_while(|token| token.text != "{")
+                    .filter(|token| token.text == ";")
+                    .count();
+                return TriviaAnchor::GateOperation(index - 1, operations.min(body.len()));
+            }
+        }
+    }
+    TriviaAnchor::Statement(index)
+}
+
+type Recovered = Vec<ErrorRecovery<Location, Tok, LexicalError<Location>>>;
+
+/// Run `parse` and return its AST, if any, along with the errors the parser
+/// recovered from and the error that stopped it, in order.
+fn recovering<T, F>(parse: F) -> (Option<T>, Vec<ParseError>)
+where
+    F: FnOnce(&mut Recovered) -> Result<T, ParseError>,
+{
+    let mut recovered = vec![];
+    let result = parse(&mut recovered);
+    let mut errors: Vec<ParseError> = recovered
+        .into_iter()
+        .map(|recovery| recovery.error)
+        .collect();
+    match result {
+        Ok(tree) => (Some(tree), errors),
+        Err(error) => {
+            errors.push(error);
+            (None, errors)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use indoc::indoc;
@@ -298,11 +533,12 @@ mod tests {
     use crate::grammar::lexer::Lexer;
     use crate::grammar::open_qasm2;
     use crate::grammar::{ast::*, lexer::Location};
+    use crate::grammar::{parse_program, parse_program_with_trivia};
 
     macro_rules! span {
         ($left:expr, $node:expr, $right:expr) => {
             Span {
-                boundaries: (Location($left), Location($right)),
+                boundaries: (Location::new_at($left), Location::new_at($right)),
                 node: Box::new($node),
             }
         };
@@ -319,7 +555,7 @@ mod tests {
         );
         let lexer = Lexer::new(source);
         let parser = open_qasm2::OpenQasmProgramParser::new();
-        let tree = parser.parse(lexer).unwrap();
+        let tree = parser.parse(source, &mut vec![], lexer).unwrap();
         assert_eq!(
             tree,
             OpenQasmProgram {
@@ -327,11 +563,41 @@ mod tests {
                 program: vec![
                     span!(14, Statement::QRegDecl("q".to_string(), 2), 24),
                     span!(25, Statement::CRegDecl("c".to_string(), 2), 35)
-                ]
+                ],
+                trivia: vec![]
             }
         );
     }
 
+    #[test]
+    fn test_parse_snapshot() {
+        let source = "
+    snapshot \"after oracle\";
+    ";
+        let lexer = Lexer::new(source);
+        let parser = open_qasm2::StatementParser::new();
+        let tree = parser.parse(source, &mut vec![], lexer).unwrap();
+        assert_eq!(tree, Statement::Snapshot("after oracle".to_string()));
+    }
+
+    #[test]
+    fn test_parse_snapshot_as_an_identifier() {
+        let source = "
+    snapshot q;
+    ";
+        let lexer = Lexer::new(source);
+        let parser = open_qasm2::StatementParser::new();
+        let tree = parser.parse(source, &mut vec![], lexer).unwrap();
+        assert_eq!(
+            tree,
+            Statement::QuantumOperation(QuantumOperation::Unitary(UnitaryOperation(
+                "snapshot".to_string(),
+                vec![],
+                vec![Argument::Id("q".to_string())]
+            )))
+        );
+    }
+
     #[test]
     fn test_parse_id_gate_macro() {
         let source = "
@@ -339,10 +605,16 @@ mod tests {
     ";
         let lexer = Lexer::new(source);
         let parser = open_qasm2::StatementParser::new();
-        let tree = parser.parse(lexer).unwrap();
+        let tree = parser.parse(source, &mut vec![], lexer).unwrap();
         assert_eq!(
             tree,
-            Statement::GateDecl("id".to_string(), vec![], vec!["q".to_string()], vec![])
+            Statement::GateDecl(
+                "id".to_string(),
+                vec![],
+                vec!["q".to_string()],
+                vec![],
+                None
+            )
         );
     }
 
@@ -353,10 +625,16 @@ mod tests {
     ";
         let lexer = Lexer::new(source);
         let parser = open_qasm2::StatementParser::new();
-        let tree = parser.parse(lexer).unwrap();
+        let tree = parser.parse(source, &mut vec![], lexer).unwrap();
         assert_eq!(
             tree,
-            Statement::GateDecl("id".to_string(), vec![], vec!["q".to_string()], vec![])
+            Statement::GateDecl(
+                "id".to_string(),
+                vec![],
+                vec!["q".to_string()],
+                vec![],
+                None
+            )
         );
     }
 
@@ -369,7 +647,7 @@ mod tests {
     ";
         let lexer = Lexer::new(source);
         let parser = open_qasm2::StatementParser::new();
-        let tree = parser.parse(lexer).unwrap();
+        let tree = parser.parse(source, &mut vec![], lexer).unwrap();
         assert_eq!(
             tree,
             Statement::GateDecl(
@@ -380,7 +658,8 @@ mod tests {
                     "CX".to_owned(),
                     vec![],
                     vec![Argument::Id("c".to_owned()), Argument::Id("t".to_owned())]
-                ))]
+                ))],
+                None
             )
         );
     }
@@ -394,7 +673,7 @@ mod tests {
     ";
         let lexer = Lexer::new(source);
         let parser = open_qasm2::StatementParser::new();
-        let tree = parser.parse(lexer).unwrap();
+        let tree = parser.parse(source, &mut vec![], lexer).unwrap();
         assert_eq!(
             tree,
             Statement::GateDecl(
@@ -409,7 +688,8 @@ mod tests {
                         Expression::Id("lambda".to_owned()),
                     ],
                     vec![Argument::Id("q".to_owned())]
-                ))]
+                ))],
+                None
             )
         );
     }
@@ -423,7 +703,7 @@ mod tests {
     ";
         let lexer = Lexer::new(source);
         let parser = open_qasm2::StatementParser::new();
-        let tree = parser.parse(lexer).unwrap();
+        let tree = parser.parse(source, &mut vec![], lexer).unwrap();
         assert_eq!(
             tree,
             Statement::GateDecl(
@@ -434,7 +714,8 @@ mod tests {
                     "u1".to_string(),
                     vec![Expression::Id("phi".to_string())],
                     vec![Argument::Id("a".to_string())]
-                ))]
+                ))],
+                None
             )
         );
     }
@@ -446,7 +727,7 @@ mod tests {
     ";
         let lexer = Lexer::new(source);
         let parser = open_qasm2::StatementParser::new();
-        let tree = parser.parse(lexer).unwrap();
+        let tree = parser.parse(source, &mut vec![], lexer).unwrap();
         assert_eq!(
             tree,
             Statement::QuantumOperation(QuantumOperation::Unitary(UnitaryOperation(
@@ -472,7 +753,7 @@ mod tests {
     ";
         let lexer = Lexer::new(source);
         let parser = open_qasm2::ExprParser::new();
-        let tree = parser.parse(lexer).unwrap();
+        let tree = parser.parse(source, &mut vec![], lexer).unwrap();
         assert_eq!(
             tree,
             Expression::Op(
@@ -506,7 +787,7 @@ mod tests {
         );
         let lexer = Lexer::new(source);
         let parser = open_qasm2::ProgramBodyParser::new();
-        let tree = parser.parse(lexer).unwrap();
+        let tree = parser.parse(source, &mut vec![], lexer).unwrap();
         assert_eq!(
             tree,
             vec![
@@ -538,7 +819,7 @@ mod tests {
         );
         let lexer = Lexer::new(source);
         let parser = open_qasm2::ProgramBodyParser::new();
-        let tree = parser.parse(lexer).unwrap();
+        let tree = parser.parse(source, &mut vec![], lexer).unwrap();
         assert_eq!(
             tree,
             vec![
@@ -572,6 +853,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_doc_comments() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    // Prepare a Bell pair
+    // on two qubits.
+    gate bell a, b { }
+    qreg q[1]; gate undocumented a { }
+    //Measure in the
+    //   magic basis.
+    opaque gate magic a;
+    "
+        );
+        let tree = parse_program(source).unwrap();
+        let docs: Vec<Option<String>> =
+            tree.program
+                .iter()
+                .filter_map(|span| match &*span.node {
+                    Statement::GateDecl(_, _, _, _, doc)
+                    | Statement::OpaqueGateDecl(_, _, _, doc) => Some(doc.clone()),
+                    _ => None,
+                })
+                .collect();
+        assert_eq!(
+            docs,
+            vec![
+                Some("Prepare a Bell pair\non two qubits.".to_string()),
+                None,
+                Some("Measure in the\nmagic basis.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_program_skips_trivia() {
+        let source = "OPENQASM 2.0;\n// Two qubits\n\nqreg q[2];\n";
+        let tree = parse_program(source).unwrap();
+        assert_eq!(tree.trivia, vec![]);
+    }
+
     #[test]
     fn test_comments() {
         let source = indoc!(
@@ -585,11 +907,10 @@ mod tests {
     // Comment 5
     "
         );
-        let lexer = Lexer::new(source);
-        let parser = open_qasm2::OpenQasmProgramParser::new();
-        let tree = parser.parse(lexer).unwrap();
+        let (tree, errors) = parse_program_with_trivia(source);
+        assert!(errors.is_empty());
         assert_eq!(
-            tree,
+            tree.unwrap(),
             OpenQasmProgram {
                 version: "2.0".to_string(),
                 program: vec![span!(
@@ -598,10 +919,37 @@ mod tests {
                         String::from("id"),
                         vec![],
                         vec![String::from("q")],
-                        vec![]
+                        vec![],
+                        Some(String::from("Comment 3"))
                     ),
                     66
-                ),]
+                ),],
+                trivia: vec![
+                    Trivia {
+                        kind: TriviaKind::Comment(String::from("// Comment 1")),
+                        anchor: TriviaAnchor::Header
+                    },
+                    Trivia {
+                        kind: TriviaKind::Comment(String::from("// Comment 2")),
+                        anchor: TriviaAnchor::Statement(0)
+                    },
+                    Trivia {
+                        kind: TriviaKind::BlankLine,
+                        anchor: TriviaAnchor::Statement(0)
+                    },
+                    Trivia {
+                        kind: TriviaKind::Comment(String::from("// Comment 3")),
+                        anchor: TriviaAnchor::Statement(0)
+                    },
+                    Trivia {
+                        kind: TriviaKind::TrailingComment(String::from("// Comment 4")),
+                        anchor: TriviaAnchor::Statement(1)
+                    },
+                    Trivia {
+                        kind: TriviaKind::Comment(String::from("// Comment 5")),
+                        anchor: TriviaAnchor::Statement(1)
+                    },
+                ]
             }
         );
     }
@@ -613,7 +961,7 @@ mod tests {
     ";
         let lexer = Lexer::new(source);
         let parser = open_qasm2::StatementParser::new();
-        let tree = parser.parse(lexer).unwrap();
+        let tree = parser.parse(source, &mut vec![], lexer).unwrap();
         assert_eq!(
             tree,
             Statement::Conditional(
@@ -630,4 +978,50 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_recovery_inside_gate_bodies() {
+        let source = "gate g a { U(0, 0) a; barrier a; }";
+        let mut recovered = vec![];
+        let parser = open_qasm2::StatementParser::new();
+        let tree = parser
+            .parse(source, &mut recovered, Lexer::new(source))
+            .unwrap();
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(
+            tree,
+            Statement::GateDecl(
+                String::from("g"),
+                vec![],
+                vec![String::from("a")],
+                vec![GateOperation::Barrier(BarrierPragma(vec![Argument::Id(
+                    String::from("a")
+                )]))],
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn test_recovery_at_the_end_of_gate_bodies() {
+        let source = "gate g a { barrier a; CX a }";
+        let mut recovered = vec![];
+        let parser = open_qasm2::StatementParser::new();
+        let tree = parser
+            .parse(source, &mut recovered, Lexer::new(source))
+            .unwrap();
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(
+            tree,
+            Statement::GateDecl(
+                String::from("g"),
+                vec![],
+                vec![String::from("a")],
+                vec![GateOperation::Barrier(BarrierPragma(vec![Argument::Id(
+                    String::from("a")
+                )]))],
+                None
+            )
+        );
+    }
 }
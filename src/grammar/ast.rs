@@ -45,7 +45,7 @@ use crate::grammar::lexer::Location;
 ///     version: "2.0".to_string(),
 ///     program: vec![
 ///         Span {
-///             boundaries: (Location(14), Location(24)),
+///             boundaries: (Location::new_at(14), Location::new_at(24)),
 ///             node: Box::new(
 ///                 Statement::QRegDecl(
 ///                     "q".to_string(),
@@ -54,7 +54,7 @@ use crate::grammar::lexer::Location;
 ///             )
 ///         },
 ///         Span {
-///             boundaries: (Location(25), Location(45)),
+///             boundaries: (Location::new_at(25), Location::new_at(45)),
 ///             node: Box::new(
 ///                 Statement::QuantumOperation(
 ///                     QuantumOperation::Unitary(
@@ -77,7 +77,8 @@ use crate::grammar::lexer::Location;
 ///                 )
 ///             )
 ///         }
-///     ]
+///     ],
+///     trivia: vec![]
 /// };
 /// ```
 #[derive(Debug, Clone, PartialEq)]
@@ -88,6 +89,76 @@ pub struct OpenQasmProgram {
     pub version: String,
     /// List of statements conforming the program body.
     pub program: Vec<Span<Statement>>,
+    /// Comments and blank lines of the program, in the order they appear.
+    /// Only [`parse_program_with_trivia()`] collects them.
+    ///
+    /// [`parse_program_with_trivia()`]: ../fn.parse_program_with_trivia.html
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub trivia: Vec<Trivia>,
+}
+
+/// A comment or a blank line. Trivia does not change the meaning of the
+/// program but the AST keeps it so printing the program preserves it.
+///
+/// # Examples
+///
+/// The comment in the following program:
+///
+/// ```qasm
+/// OPENQASM 2.0;
+/// gate id q {
+///     // Nothing to do.
+/// }
+/// ```
+///
+/// Is represented, as trivia, like:
+///
+/// ```
+/// use qasmsim::grammar::ast::{Trivia, TriviaAnchor, TriviaKind};
+///
+/// let comment = Trivia {
+///     kind: TriviaKind::Comment("// Nothing to do.".to_string()),
+///     anchor: TriviaAnchor::GateOperation(0, 0),
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Trivia {
+    /// The comment or blank line.
+    pub kind: TriviaKind,
+    /// Where the trivia appears.
+    pub anchor: TriviaAnchor,
+}
+
+/// Any of the kinds of trivia.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TriviaKind {
+    /// A comment, including the leading `//`, in a line of its own.
+    Comment(String),
+    /// A comment, including the leading `//`, following some code in the
+    /// same line.
+    TrailingComment(String),
+    /// One or more blank lines.
+    BlankLine,
+}
+
+/// The place of some trivia relative to the nodes of the program. Trailing
+/// comments follow the code right before the place.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TriviaAnchor {
+    /// Before the `OPENQASM` header.
+    Header,
+    /// Before the statement at the index, or at the end of the program if
+    /// the index is the number of statements.
+    Statement(usize),
+    /// Inside the body of the gate declared by the statement at the first
+    /// index, before the operation at the second index, or at the end of
+    /// the body if the index is the number of operations.
+    GateOperation(usize, usize),
 }
 
 /// Represent a OPENQASM library. OPENQASM libraries can contain gate
@@ -141,16 +212,18 @@ pub struct OpenQasmProgram {
 ///                         ]
 ///                     )
 ///                 )
-///             ]
+///             ],
+///             None
 ///         )
 ///     ]
 /// };
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct OpenQasmLibrary {
-    /// List of gate declarations. Although the type allows for the contruction
-    /// of a library with arbitrary statements, this would not constitute a
-    /// valid OPENQASM library and the linker would panic at runtime.
+    /// List of gate declarations and `include` directives. Although the type
+    /// allows for the contruction of a library with arbitrary statements, this
+    /// would not constitute a valid OPENQASM library and the linker would
+    /// panic at runtime.
     pub definitions: Vec<Statement>,
 }
 
@@ -159,7 +232,9 @@ pub struct OpenQasmLibrary {
 
 /// A pragma for a potential gate optimizer to prevent the combination of the
 /// gates at both sides of the barrier. The barrier takes a list of registers
-/// or qubits arguments.
+/// or qubits arguments. [`transpiler::optimize()`] honors barriers.
+///
+/// [`transpiler::optimize()`]: ../../transpiler/fn.optimize.html
 ///
 /// # Examples
 ///
@@ -224,22 +299,33 @@ pub enum Statement {
     /// Classical register declaration with name and size.
     CRegDecl(String, usize),
     /// Quantum gate declaration with name, list of formal real parameters,
-    /// list of formal quantum registers, and a list of [`GateOperation`]
-    /// representing the body of the gate.
-    GateDecl(String, Vec<String>, Vec<String>, Vec<GateOperation>),
+    /// list of formal quantum registers, a list of [`GateOperation`]
+    /// representing the body of the gate, and the documentation of the gate
+    /// taken from the comment lines right before the declaration, if any.
+    GateDecl(
+        String,
+        Vec<String>,
+        Vec<String>,
+        Vec<GateOperation>,
+        Option<String>,
+    ),
     /// Include statement for linking with gate libraries.
     Include(String),
     /// A wrapper for the barrier pragma.
     Barrier(BarrierPragma),
-    /// Opaque gate declaration with name and formal lists of real parameters
-    /// and quantum registers. Opaque declarations have no body.
-    OpaqueGateDecl(String, Vec<String>, Vec<String>),
+    /// Opaque gate declaration with name, formal lists of real parameters
+    /// and quantum registers, and the documentation of the gate. Opaque
+    /// declarations have no body.
+    OpaqueGateDecl(String, Vec<String>, Vec<String>, Option<String>),
     /// A wrapper for a quantum operation.
     QuantumOperation(QuantumOperation),
     /// A wrapper for making a quantum operation to simulate just if certain
     /// equality condition holds. The wrapper takes the left-side of the
     /// comparison, the right side, and the operation to perform.
     Conditional(Argument, u64, QuantumOperation),
+    /// Snapshot statement with the label under which the simulator saves a
+    /// copy of the state-vector. This is an extension to OPENQASM.
+    Snapshot(String),
 }
 
 /// Relates a node with the fragment of source code where the node appears.
@@ -260,7 +346,7 @@ pub enum Statement {
 /// use qasmsim::grammar::lexer::Location;
 ///
 /// let barrier_span = Span {
-///     boundaries: (Location(14), Location(25)),
+///     boundaries: (Location::new_at(14), Location::new_at(25)),
 ///     node: Box::new(
 ///         Statement::QRegDecl(
 ///             "q".to_string(),
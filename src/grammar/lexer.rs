@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::fmt;
+use std::ops::Range;
 use std::str::CharIndices;
 
 #[cfg(feature = "serde")]
@@ -11,9 +12,22 @@ use serde::{Deserialize, Serialize};
 use lazy_static::lazy_static;
 use regex::Regex;
 
-/// Represent a position inside the source code.
+/// Identify a source file inside a [`SourceMap`]. The file of the program
+/// being parsed has the default id, `FileId(0)`.
 ///
-/// This position is a character index (0-based).
+/// [`SourceMap`]: ../source_map/struct.SourceMap.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FileId(pub usize);
+
+impl fmt::Display for FileId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Represent a position inside the source code, as the file and the
+/// character index (0-based) inside the file.
 ///
 /// # Examples
 ///
@@ -33,23 +47,40 @@ use regex::Regex;
 /// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct Location(pub usize);
+pub struct Location {
+    /// File containing the position.
+    pub file: FileId,
+    /// Character index inside the file.
+    pub offset: usize,
+}
 
 impl Location {
-    /// Creates a new location at char index 0.
+    /// Creates a new location at char index 0 of the program.
     pub fn new() -> Self {
         Default::default()
     }
 
-    /// Creates a new location at `position`.
+    /// Creates a new location at `position` of the program.
     pub fn new_at(position: usize) -> Self {
-        Location(position)
+        Location::new_in(FileId::default(), position)
+    }
+
+    /// Creates a new location at `position` of `file`.
+    pub fn new_in(file: FileId, position: usize) -> Self {
+        Location {
+            file,
+            offset: position,
+        }
     }
 }
 
 impl fmt::Display for Location {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "character {}", self.0)
+        if self.file == FileId::default() {
+            write!(f, "character {}", self.offset)
+        } else {
+            write!(f, "character {} of file {}", self.offset, self.file)
+        }
     }
 }
 
@@ -75,6 +106,7 @@ pub type Spanned<Tok, Loc, Error> = Result<(Loc, Tok, Loc), Error>;
 /// LexicalError::new_at(14);
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct LexicalError<Loc> {
     /// Location at which the unknown sequence starts.
     pub location: Loc,
@@ -167,6 +199,10 @@ pub enum Tok {
     Barrier,
     /// The key-word `if`.
     If,
+    /// The key-word `snapshot`. This is an extension to OPENQASM and only a
+    /// key-word when followed by a string, so `snapshot` remains a valid
+    /// identifier.
+    Snapshot,
     /// The QASM header `OPENQASM`.
     QASMHeader,
     /// The version of OPENQASM as `X.Y`.
@@ -236,6 +272,7 @@ impl fmt::Display for Tok {
             Tok::Reset => "keyword `reset`".into(),
             Tok::Barrier => "keyword `barrier`".into(),
             Tok::If => "keyword `if`".into(),
+            Tok::Snapshot => "keyword `snapshot`".into(),
             Tok::QASMHeader => "qasm header `OPENQASM`".into(),
             Tok::Version { repr } => format!("open qasm version `{}`", &repr),
             Tok::Id { repr } => format!("identifier `{}`", &repr),
@@ -278,6 +315,7 @@ enum Mode {
 
 #[derive(Debug, Clone)]
 pub(crate) struct Lexer<'input> {
+    file: FileId,
     mode: VecDeque<Mode>,
     lineno: usize,
     lineoffset: usize,
@@ -290,7 +328,12 @@ pub(crate) struct Lexer<'input> {
 
 impl<'input> Lexer<'input> {
     pub fn new(input: &'input str) -> Self {
+        Lexer::with_file(input, FileId::default())
+    }
+
+    pub fn with_file(input: &'input str, file: FileId) -> Self {
         Lexer {
+            file,
             mode: VecDeque::from(vec![Mode::Base]),
             lineno: 1,
             lineoffset: 0,
@@ -315,14 +358,17 @@ impl<'input> Lexer<'input> {
     }
 
     fn advance_offset(&mut self, count: usize) {
-        for _ in 0..count {
+        self.offset += count;
+        while let Some((index, _)) = self.chars.peek() {
+            if *index >= self.offset {
+                break;
+            }
             self.chars.next();
         }
-        self.offset += count;
     }
 
     fn location(&self, offset: usize) -> Location {
-        Location(offset)
+        Location::new_in(self.file, offset)
     }
 }
 
@@ -335,6 +381,7 @@ impl<'input> Iterator for Lexer<'input> {
     //
     // Proposed syntax (if possible): #[modes(mode1, mode2,...)]
     #[allow(clippy::single_match)]
+    #[allow(clippy::collapsible_match)]
     #[allow(clippy::trivial_regex)]
     fn next(&mut self) -> Option<Self::Item> {
         lazy_static! {
@@ -374,7 +421,7 @@ impl<'input> Iterator for Lexer<'input> {
             let start = self.offset;
 
             // #[modes(Base)]
-            match self.mode.get(0) {
+            match self.mode.front() {
                 Some(Mode::Base) => {
                     if let Some((_, c)) = self.chars.peek() {
                         if *c == '"' {
@@ -388,7 +435,7 @@ impl<'input> Iterator for Lexer<'input> {
             }
 
             // #[modes(Str)]
-            match self.mode.get(0) {
+            match self.mode.front() {
                 Some(Mode::Str) => {
                     loop {
                         match self.chars.next() {
@@ -418,7 +465,7 @@ impl<'input> Iterator for Lexer<'input> {
             }
 
             // #[modes(Comment)]
-            match self.mode.get(0) {
+            match self.mode.front() {
                 Some(Mode::Comment) => {
                     if self.try_pattern(&ALL_THE_LINE).is_some() {
                         self.mode.pop_front();
@@ -452,6 +499,13 @@ impl<'input> Iterator for Lexer<'input> {
             // #[modes(all)]
             if let Some(repr) = self.try_pattern(&ID) {
                 let end = start + repr.len();
+                if repr == "snapshot" && self.input[end..].trim_start().starts_with('"') {
+                    return Some(Ok((
+                        self.location(start),
+                        Tok::Snapshot,
+                        self.location(end),
+                    )));
+                }
                 return Some(match self.keywords.get(&repr) {
                     None => Ok((self.location(start), Tok::Id { repr }, self.location(end))),
                     Some(token) => Ok((self.location(start), (*token).clone(), self.location(end))),
@@ -459,7 +513,7 @@ impl<'input> Iterator for Lexer<'input> {
             }
 
             // #[modes(Base)]
-            match self.mode.get(0) {
+            match self.mode.front() {
                 Some(Mode::Base) => {
                     if let Some(repr) = self.try_pattern(&REAL) {
                         let end = start + repr.len();
@@ -474,7 +528,7 @@ impl<'input> Iterator for Lexer<'input> {
             }
 
             // #[modes(Version)]
-            match self.mode.get(0) {
+            match self.mode.front() {
                 Some(Mode::Version) => {
                     if let Some(repr) = self.try_pattern(&VERSION) {
                         let end = start + repr.len();
@@ -535,6 +589,238 @@ impl<'input> Iterator for Lexer<'input> {
     }
 }
 
+/// The kinds of tokens returned by [`tokenize()`], meant for classifying the
+/// source code, as syntax highlighters do.
+///
+/// [`tokenize()`]: ./fn.tokenize.html
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum TokenKind {
+    /// A key-word, such as `qreg`, or the `OPENQASM` header.
+    Keyword,
+    /// One of the primitive gates `U` and `CX`.
+    Gate,
+    /// One of the functions `sin`, `cos`, `tan`, `exp`, `ln` and `sqrt`.
+    Function,
+    /// The constant `pi`.
+    Constant,
+    /// An identifier.
+    Identifier,
+    /// An integer literal.
+    Integer,
+    /// A real literal.
+    Real,
+    /// The version of OPENQASM after the header.
+    Version,
+    /// A string literal, including the quotes.
+    String,
+    /// An operator, such as `+` or `->`.
+    Operator,
+    /// A bracket, a parenthesis, a comma or a semicolon.
+    Punctuation,
+    /// A comment, from `//` to the end of the line.
+    Comment,
+    /// A sequence of blank characters, including new lines.
+    Whitespace,
+    /// A character the lexer does not recognize, or an unterminated string.
+    Error,
+}
+
+impl TokenKind {
+    /// Return `true` for comments and whitespace.
+    pub fn is_trivia(self) -> bool {
+        matches!(self, TokenKind::Comment | TokenKind::Whitespace)
+    }
+
+    fn of(token: &Tok) -> Self {
+        match token {
+            Tok::Add | Tok::Minus | Tok::Mult | Tok::Div | Tok::Pow | Tok::Arrow | Tok::Equal => {
+                TokenKind::Operator
+            }
+            Tok::LBracket
+            | Tok::RBracket
+            | Tok::LBrace
+            | Tok::RBrace
+            | Tok::LParent
+            | Tok::RParent
+            | Tok::Semi
+            | Tok::Comma => TokenKind::Punctuation,
+            Tok::Sin | Tok::Cos | Tok::Tan | Tok::Exp | Tok::Ln | Tok::Sqrt => TokenKind::Function,
+            Tok::ConstPi => TokenKind::Constant,
+            Tok::U | Tok::CX => TokenKind::Gate,
+            Tok::Opaque
+            | Tok::Gate
+            | Tok::Include
+            | Tok::QReg
+            | Tok::CReg
+            | Tok::Measure
+            | Tok::Reset
+            | Tok::Barrier
+            | Tok::If
+            | Tok::Snapshot
+            | Tok::QASMHeader => TokenKind::Keyword,
+            Tok::Version { .. } => TokenKind::Version,
+            Tok::Id { .. } => TokenKind::Identifier,
+            Tok::Int { .. } => TokenKind::Integer,
+            Tok::Real { .. } => TokenKind::Real,
+            Tok::Str { .. } => TokenKind::String,
+        }
+    }
+}
+
+/// A classified piece of the source code.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Token {
+    /// What the token is.
+    pub kind: TokenKind,
+    /// The token as it appears in the source code.
+    pub text: String,
+    /// Byte range of the token in the source code.
+    pub range: Range<usize>,
+    /// Character range of the token in the source code.
+    pub char_range: Range<usize>,
+}
+
+/// Split `source` into tokens with the same lexer the parser uses, skipping
+/// comments and whitespace.
+///
+/// Tokenizing never fails: the characters the lexer does not recognize come
+/// back as [`TokenKind::Error`] tokens and tokenizing continues after them.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::grammar::lexer::{tokenize, TokenKind};
+///
+/// let tokens = tokenize("qreg q[2]; // two qubits");
+/// let kinds: Vec<TokenKind> = tokens.iter().map(|token| token.kind).collect();
+///
+/// assert_eq!(kinds, vec![
+///     TokenKind::Keyword,
+///     TokenKind::Identifier,
+///     TokenKind::Punctuation,
+///     TokenKind::Integer,
+///     TokenKind::Punctuation,
+///     TokenKind::Punctuation,
+/// ]);
+/// assert_eq!(tokens[1].text, "q");
+/// assert_eq!(tokens[1].range, 5..6);
+/// ```
+///
+/// [`TokenKind::Error`]: ./enum.TokenKind.html#variant.Error
+pub fn tokenize(source: &str) -> Vec<Token> {
+    tokenize_with_trivia(source)
+        .into_iter()
+        .filter(|token| !token.kind.is_trivia())
+        .collect()
+}
+
+/// Split `source` into tokens like [`tokenize()`] does, but keeping comments
+/// and whitespace so the tokens cover the whole source code.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::grammar::lexer::{tokenize_with_trivia, TokenKind};
+///
+/// let source = "h q; // é\n$";
+/// let tokens = tokenize_with_trivia(source);
+/// let text: String = tokens.iter().map(|token| token.text.as_str()).collect();
+///
+/// assert_eq!(text, source);
+/// assert_eq!(tokens[5].kind, TokenKind::Comment);
+/// assert_eq!(tokens[7].kind, TokenKind::Error);
+/// assert_eq!(tokens[7].range, 11..12);
+/// assert_eq!(tokens[7].char_range, 10..11);
+/// ```
+///
+/// [`tokenize()`]: ./fn.tokenize.html
+pub fn tokenize_with_trivia(source: &str) -> Vec<Token> {
+    let mut spans = vec![];
+    let mut offset = 0;
+    while offset < source.len() {
+        let rest = &source[offset..];
+        let mut cursor = 0;
+        let mut failed_at = None;
+        for spanned in Lexer::new(rest) {
+            match spanned {
+                Ok((start, token, end)) => {
+                    push_trivia(&mut spans, source, offset + cursor, offset + start.offset);
+                    spans.push((
+                        TokenKind::of(&token),
+                        offset + start.offset..offset + end.offset,
+                    ));
+                    cursor = end.offset;
+                }
+                Err(error) => {
+                    failed_at = Some(error.location.offset);
+                    break;
+                }
+            }
+        }
+
+        let trivia_end = offset + failed_at.unwrap_or(rest.len());
+        let stop = push_trivia(&mut spans, source, offset + cursor, trivia_end);
+        if stop == source.len() {
+            break;
+        }
+        // The lexer stops at unknown characters and at unterminated strings.
+        let end = if failed_at.is_none() && source[stop..].starts_with('"') {
+            source.len()
+        } else {
+            stop + source[stop..].chars().next().map_or(1, char::len_utf8)
+        };
+        spans.push((TokenKind::Error, stop..end));
+        offset = end;
+    }
+
+    let mut chars = 0;
+    let mut last_end = 0;
+    spans
+        .into_iter()
+        .map(|(kind, range)| {
+            chars += source[last_end..range.start].chars().count();
+            let char_start = chars;
+            chars += source[range.clone()].chars().count();
+            last_end = range.end;
+            Token {
+                kind,
+                text: source[range.clone()].into(),
+                range,
+                char_range: char_start..chars,
+            }
+        })
+        .collect()
+}
+
+/// Push the comments and whitespace found between `from` and `to`, and
+/// return where they stop.
+fn push_trivia(
+    spans: &mut Vec<(TokenKind, Range<usize>)>,
+    source: &str,
+    from: usize,
+    to: usize,
+) -> usize {
+    let mut position = from;
+    while position < to {
+        let rest = &source[position..to];
+        let (kind, length) = if rest.starts_with("//") {
+            (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else {
+            (TokenKind::Whitespace, rest.len() - rest.trim_start().len())
+        };
+        if length == 0 {
+            break;
+        }
+        spans.push((kind, position..position + length));
+        position += length;
+    }
+    position
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -554,53 +840,53 @@ mod tests {
             lexer.collect::<Vec<_>>(),
             vec![
                 Ok((
-                    Location(0),
+                    Location::new_at(0),
                     Tok::Int {
                         repr: String::from("0")
                     },
-                    Location(1)
+                    Location::new_at(1)
                 )),
                 Ok((
-                    Location(2),
+                    Location::new_at(2),
                     Tok::Int {
                         repr: String::from("1")
                     },
-                    Location(3),
+                    Location::new_at(3),
                 )),
                 Ok((
-                    Location(4),
+                    Location::new_at(4),
                     Tok::Int {
                         repr: String::from("20")
                     },
-                    Location(6),
+                    Location::new_at(6),
                 )),
                 Ok((
-                    Location(7),
+                    Location::new_at(7),
                     Tok::Real {
                         repr: String::from(".3")
                     },
-                    Location(9),
+                    Location::new_at(9),
                 )),
                 Ok((
-                    Location(10),
+                    Location::new_at(10),
                     Tok::Real {
                         repr: String::from(".4e5")
                     },
-                    Location(14)
+                    Location::new_at(14)
                 )),
                 Ok((
-                    Location(15),
+                    Location::new_at(15),
                     Tok::Real {
                         repr: String::from("0.6E-7")
                     },
-                    Location(21)
+                    Location::new_at(21)
                 )),
                 Ok((
-                    Location(22),
+                    Location::new_at(22),
                     Tok::Str {
                         repr: String::from("8910")
                     },
-                    Location(28)
+                    Location::new_at(28)
                 )),
             ]
         );
@@ -616,7 +902,11 @@ mod tests {
         let lexer = Lexer::new(source);
         assert_eq!(
             lexer.collect::<Vec<_>>(),
-            vec![Ok((Location(4 + 2), Tok::QASMHeader, Location(12 + 2))),]
+            vec![Ok((
+                Location::new_at(4 + 2),
+                Tok::QASMHeader,
+                Location::new_at(12 + 2)
+            )),]
         );
     }
 
@@ -629,15 +919,23 @@ mod tests {
         assert_eq!(
             lexer.collect::<Vec<_>>(),
             vec![
-                Ok((Location(4 + 1), Tok::QASMHeader, Location(12 + 1))),
                 Ok((
-                    Location(13 + 1),
+                    Location::new_at(4 + 1),
+                    Tok::QASMHeader,
+                    Location::new_at(12 + 1)
+                )),
+                Ok((
+                    Location::new_at(13 + 1),
                     Tok::Version {
                         repr: String::from("2.0")
                     },
-                    Location(16 + 1)
+                    Location::new_at(16 + 1)
                 )),
-                Ok((Location(16 + 1), Tok::Semi, Location(17 + 1)))
+                Ok((
+                    Location::new_at(16 + 1),
+                    Tok::Semi,
+                    Location::new_at(17 + 1)
+                ))
             ]
         );
     }
@@ -649,19 +947,19 @@ mod tests {
         assert_eq!(
             lexer.collect::<Vec<_>>(),
             vec![
-                Ok((Location(0), Tok::Add, Location(1))),
-                Ok((Location(1), Tok::Minus, Location(2))),
-                Ok((Location(2), Tok::Mult, Location(3))),
-                Ok((Location(3), Tok::Div, Location(4))),
-                Ok((Location(4), Tok::LBracket, Location(5))),
-                Ok((Location(5), Tok::RBracket, Location(6))),
-                Ok((Location(6), Tok::LBrace, Location(7))),
-                Ok((Location(7), Tok::RBrace, Location(8))),
-                Ok((Location(8), Tok::LParent, Location(9))),
-                Ok((Location(9), Tok::RParent, Location(10))),
-                Ok((Location(10), Tok::Semi, Location(11))),
-                Ok((Location(11), Tok::Comma, Location(12))),
-                Ok((Location(12), Tok::Pow, Location(13)))
+                Ok((Location::new_at(0), Tok::Add, Location::new_at(1))),
+                Ok((Location::new_at(1), Tok::Minus, Location::new_at(2))),
+                Ok((Location::new_at(2), Tok::Mult, Location::new_at(3))),
+                Ok((Location::new_at(3), Tok::Div, Location::new_at(4))),
+                Ok((Location::new_at(4), Tok::LBracket, Location::new_at(5))),
+                Ok((Location::new_at(5), Tok::RBracket, Location::new_at(6))),
+                Ok((Location::new_at(6), Tok::LBrace, Location::new_at(7))),
+                Ok((Location::new_at(7), Tok::RBrace, Location::new_at(8))),
+                Ok((Location::new_at(8), Tok::LParent, Location::new_at(9))),
+                Ok((Location::new_at(9), Tok::RParent, Location::new_at(10))),
+                Ok((Location::new_at(10), Tok::Semi, Location::new_at(11))),
+                Ok((Location::new_at(11), Tok::Comma, Location::new_at(12))),
+                Ok((Location::new_at(12), Tok::Pow, Location::new_at(13)))
             ]
         );
     }
@@ -673,8 +971,8 @@ mod tests {
         assert_eq!(
             lexer.collect::<Vec<_>>(),
             vec![
-                Ok((Location(0), Tok::Arrow, Location(2))),
-                Ok((Location(2), Tok::Equal, Location(4)))
+                Ok((Location::new_at(0), Tok::Arrow, Location::new_at(2))),
+                Ok((Location::new_at(2), Tok::Equal, Location::new_at(4)))
             ]
         );
     }
@@ -685,11 +983,38 @@ mod tests {
             let lexer = Lexer::new(&keyword);
             assert_eq!(
                 lexer.collect::<Vec<_>>(),
-                vec![Ok((Location(0), token, Location(keyword.len())))]
+                vec![Ok((
+                    Location::new_at(0),
+                    token,
+                    Location::new_at(keyword.len())
+                ))]
             );
         }
     }
 
+    #[test]
+    fn test_snapshot_is_a_key_word_only_before_a_string() {
+        let source = "snapshot \"end\"; snapshot q;";
+        let tokens: Vec<_> = Lexer::new(source).map(|token| token.unwrap().1).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Tok::Snapshot,
+                Tok::Str {
+                    repr: String::from("end")
+                },
+                Tok::Semi,
+                Tok::Id {
+                    repr: String::from("snapshot")
+                },
+                Tok::Id {
+                    repr: String::from("q")
+                },
+                Tok::Semi,
+            ]
+        );
+    }
+
     #[test]
     fn test_gates() {
         let source = "CX U";
@@ -697,8 +1022,8 @@ mod tests {
         assert_eq!(
             lexer.collect::<Vec<_>>(),
             vec![
-                Ok((Location(0), Tok::CX, Location(2))),
-                Ok((Location(3), Tok::U, Location(4)))
+                Ok((Location::new_at(0), Tok::CX, Location::new_at(2))),
+                Ok((Location::new_at(3), Tok::U, Location::new_at(4)))
             ]
         );
     }
@@ -710,9 +1035,21 @@ mod tests {
         assert_eq!(
             lexer.collect::<Vec<_>>(),
             vec![
-                Ok((Location(0), Tok::Id { repr: "a".into() }, Location(1))),
-                Ok((Location(2), Tok::Id { repr: "b".into() }, Location(3))),
-                Ok((Location(4), Tok::Id { repr: "c".into() }, Location(5)))
+                Ok((
+                    Location::new_at(0),
+                    Tok::Id { repr: "a".into() },
+                    Location::new_at(1)
+                )),
+                Ok((
+                    Location::new_at(2),
+                    Tok::Id { repr: "b".into() },
+                    Location::new_at(3)
+                )),
+                Ok((
+                    Location::new_at(4),
+                    Tok::Id { repr: "c".into() },
+                    Location::new_at(5)
+                ))
             ]
         );
     }
@@ -724,9 +1061,13 @@ mod tests {
         assert_eq!(
             lexer.collect::<Vec<_>>(),
             vec![
-                Ok((Location(0), Tok::Id { repr: "a".into() }, Location(1))),
+                Ok((
+                    Location::new_at(0),
+                    Tok::Id { repr: "a".into() },
+                    Location::new_at(1)
+                )),
                 Err(LexicalError {
-                    location: Location(2)
+                    location: Location::new_at(2)
                 })
             ]
         );
@@ -742,36 +1083,36 @@ mod tests {
             assert_eq!(
                 lexer.collect::<Vec<_>>(),
                 vec![
-                    Ok((Location(0), Tok::U, Location(1))),
-                    Ok((Location(1), Tok::LParent, Location(2))),
-                    Ok((Location(2), Tok::ConstPi, Location(4))),
-                    Ok((Location(4), Tok::Div, Location(5))),
+                    Ok((Location::new_at(0), Tok::U, Location::new_at(1))),
+                    Ok((Location::new_at(1), Tok::LParent, Location::new_at(2))),
+                    Ok((Location::new_at(2), Tok::ConstPi, Location::new_at(4))),
+                    Ok((Location::new_at(4), Tok::Div, Location::new_at(5))),
                     Ok((
-                        Location(5),
+                        Location::new_at(5),
                         Tok::Int {
                             repr: String::from("2")
                         },
-                        Location(6)
+                        Location::new_at(6)
                     )),
-                    Ok((Location(6), Tok::Comma, Location(7))),
+                    Ok((Location::new_at(6), Tok::Comma, Location::new_at(7))),
                     Ok((
-                        Location(8),
+                        Location::new_at(8),
                         Tok::Int {
                             repr: String::from("0")
                         },
-                        Location(9)
+                        Location::new_at(9)
                     )),
-                    Ok((Location(9), Tok::Comma, Location(10))),
-                    Ok((Location(11), Tok::ConstPi, Location(13))),
-                    Ok((Location(13), Tok::RParent, Location(14))),
+                    Ok((Location::new_at(9), Tok::Comma, Location::new_at(10))),
+                    Ok((Location::new_at(11), Tok::ConstPi, Location::new_at(13))),
+                    Ok((Location::new_at(13), Tok::RParent, Location::new_at(14))),
                     Ok((
-                        Location(15),
+                        Location::new_at(15),
                         Tok::Id {
                             repr: String::from("q")
                         },
-                        Location(16)
+                        Location::new_at(16)
                     )),
-                    Ok((Location(16), Tok::Semi, Location(17))),
+                    Ok((Location::new_at(16), Tok::Semi, Location::new_at(17))),
                 ]
             );
         }
@@ -783,9 +1124,112 @@ mod tests {
             assert_eq!(
                 lexer.collect::<Vec<_>>(),
                 vec![Err(LexicalError {
-                    location: Location(0)
+                    location: Location::new_at(0)
                 })]
             );
         }
+
+        #[test]
+        fn test_non_ascii_comments_do_not_stop_the_lexer() {
+            let source = "// ééé\nq;";
+            let lexer = Lexer::new(source);
+            assert_eq!(
+                lexer.collect::<Vec<_>>(),
+                vec![
+                    Ok((
+                        Location::new_at(10),
+                        Tok::Id {
+                            repr: String::from("q")
+                        },
+                        Location::new_at(11)
+                    )),
+                    Ok((Location::new_at(11), Tok::Semi, Location::new_at(12))),
+                ]
+            );
+        }
+    }
+
+    mod tokenize {
+        use super::*;
+
+        fn summary(tokens: Vec<Token>) -> Vec<(TokenKind, String)> {
+            tokens
+                .into_iter()
+                .map(|token| (token.kind, token.text))
+                .collect()
+        }
+
+        #[test]
+        fn test_tokenize_continues_after_errors() {
+            let source = "OPENQASM 2.0;\nqreg Q[2];";
+            assert_eq!(
+                summary(tokenize(source)),
+                vec![
+                    (TokenKind::Keyword, "OPENQASM".into()),
+                    (TokenKind::Version, "2.0".into()),
+                    (TokenKind::Punctuation, ";".into()),
+                    (TokenKind::Keyword, "qreg".into()),
+                    (TokenKind::Error, "Q".into()),
+                    (TokenKind::Punctuation, "[".into()),
+                    (TokenKind::Integer, "2".into()),
+                    (TokenKind::Punctuation, "]".into()),
+                    (TokenKind::Punctuation, ";".into()),
+                ]
+            );
+        }
+
+        #[test]
+        fn test_tokenize_with_trivia() {
+            let source = "  // bell\ncx a, b;";
+            assert_eq!(
+                summary(tokenize_with_trivia(source)),
+                vec![
+                    (TokenKind::Whitespace, "  ".into()),
+                    (TokenKind::Comment, "// bell".into()),
+                    (TokenKind::Whitespace, "\n".into()),
+                    (TokenKind::Identifier, "cx".into()),
+                    (TokenKind::Whitespace, " ".into()),
+                    (TokenKind::Identifier, "a".into()),
+                    (TokenKind::Punctuation, ",".into()),
+                    (TokenKind::Whitespace, " ".into()),
+                    (TokenKind::Identifier, "b".into()),
+                    (TokenKind::Punctuation, ";".into()),
+                ]
+            );
+        }
+
+        #[test]
+        fn test_tokenize_unterminated_strings() {
+            let source = "include \"qelib1.inc;\nqreg q[1];";
+            assert_eq!(
+                summary(tokenize(source)),
+                vec![
+                    (TokenKind::Keyword, "include".into()),
+                    (TokenKind::Error, "\"qelib1.inc;\nqreg q[1];".into()),
+                ]
+            );
+        }
+
+        #[test]
+        fn test_tokenize_strings_and_expressions() {
+            let source = "include \"qelib1.inc\"; U(-pi/2, 0, 1.5e3) q;";
+            let tokens = tokenize(source);
+            assert_eq!(tokens[1].kind, TokenKind::String);
+            assert_eq!(tokens[1].text, "\"qelib1.inc\"");
+            assert_eq!(tokens[1].range, 8..20);
+            assert_eq!(
+                tokens[3..8]
+                    .iter()
+                    .map(|token| token.kind)
+                    .collect::<Vec<_>>(),
+                vec![
+                    TokenKind::Gate,
+                    TokenKind::Punctuation,
+                    TokenKind::Operator,
+                    TokenKind::Constant,
+                    TokenKind::Operator,
+                ]
+            );
+        }
     }
 }
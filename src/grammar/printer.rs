@@ -0,0 +1,408 @@
+//! Print ASTs back as OPENQASM source code, one statement per line and with
+//! the minimal parentheses in expressions.
+
+use std::fmt;
+
+use super::ast::{
+    Argument, BarrierPragma, Expression, FuncCode, GateOperation, OpCode, OpenQasmLibrary,
+    OpenQasmProgram, QuantumOperation, Statement, Trivia, TriviaAnchor, TriviaKind,
+    UnitaryOperation,
+};
+
+const INDENTATION: &str = "  ";
+
+/// Collect the lines of a program, along with its trivia.
+#[derive(Default)]
+struct Printer {
+    lines: Vec<String>,
+}
+
+impl Printer {
+    fn line(&mut self, indentation: usize, text: impl fmt::Display) {
+        self.lines
+            .push(format!("{}{}", INDENTATION.repeat(indentation), text));
+    }
+
+    fn trivia(&mut self, trivia: &[Trivia], anchor: TriviaAnchor, indentation: usize) {
+        for trivia in trivia.iter().filter(|trivia| trivia.anchor == anchor) {
+            match &trivia.kind {
+                TriviaKind::Comment(text) => self.line(indentation, text),
+                TriviaKind::TrailingComment(text) => match self.lines.last_mut() {
+                    Some(last) => {
+                        last.push(' ');
+                        last.push_str(text);
+                    }
+                    None => self.line(indentation, text),
+                },
+                TriviaKind::BlankLine => self.lines.push(String::new()),
+            }
+        }
+    }
+
+    /// Print `statement`, the statement at `index` of a program whose trivia
+    /// is `trivia`.
+    fn statement(&mut self, statement: &Statement, index: usize, trivia: &[Trivia]) {
+        let (name, real_args, args, body) = match statement {
+            Statement::GateDecl(name, real_args, args, body, _) => (name, real_args, args, body),
+            statement => return self.line(0, statement),
+        };
+        let declaration = Declaration(name, real_args, args);
+        let inner_trivia = trivia.iter().any(
+            |trivia| matches!(trivia.anchor, TriviaAnchor::GateOperation(gate, _) if gate == index),
+        );
+        if body.is_empty() && !inner_trivia {
+            return self.line(0, format!("gate {} {{ }}", declaration));
+        }
+        self.line(0, format!("gate {} {{", declaration));
+        for (position, operation) in body.iter().enumerate() {
+            self.trivia(trivia, TriviaAnchor::GateOperation(index, position), 1);
+            self.line(1, format!("{};", operation));
+        }
+        self.trivia(trivia, TriviaAnchor::GateOperation(index, body.len()), 1);
+        self.line(0, "}");
+    }
+}
+
+impl fmt::Display for Printer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.lines.join("\n"))
+    }
+}
+
+impl fmt::Display for OpenQasmProgram {
+    /// Print the program, including its comments, ending with a new line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut printer = Printer::default();
+        printer.trivia(&self.trivia, TriviaAnchor::Header, 0);
+        printer.line(0, format!("OPENQASM {};", self.version));
+        for (index, span) in self.program.iter().enumerate() {
+            printer.trivia(&self.trivia, TriviaAnchor::Statement(index), 0);
+            printer.statement(&span.node, index, &self.trivia);
+        }
+        let end = TriviaAnchor::Statement(self.program.len());
+        printer.trivia(&self.trivia, end, 0);
+        writeln!(f, "{}", printer)
+    }
+}
+
+impl fmt::Display for OpenQasmLibrary {
+    /// Print the definitions of the library, ending with a new line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.definitions
+            .iter()
+            .try_for_each(|definition| writeln!(f, "{}", definition))
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::QRegDecl(name, size) => write!(f, "qreg {}[{}];", name, size),
+            Statement::CRegDecl(name, size) => write!(f, "creg {}[{}];", name, size),
+            Statement::GateDecl(..) => {
+                let mut printer = Printer::default();
+                printer.statement(self, 0, &[]);
+                write!(f, "{}", printer)
+            }
+            Statement::Include(path) => write!(f, "include \"{}\";", path),
+            Statement::Barrier(barrier) => write!(f, "{};", barrier),
+            Statement::OpaqueGateDecl(name, real_args, args, _) => {
+                write!(f, "opaque gate {};", Declaration(name, real_args, args))
+            }
+            Statement::QuantumOperation(operation) => write!(f, "{};", operation),
+            Statement::Conditional(register, value, operation) => {
+                write!(f, "if ({} == {}) {};", register, value, operation)
+            }
+            Statement::Snapshot(label) => write!(f, "snapshot \"{}\";", label),
+        }
+    }
+}
+
+/// The name, real parameters and quantum arguments of a gate declaration.
+struct Declaration<'a>(&'a str, &'a [String], &'a [String]);
+
+impl fmt::Display for Declaration<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Declaration(name, real_args, args) = self;
+        if real_args.is_empty() {
+            write!(f, "{} {}", name, args.join(", "))
+        } else {
+            write!(f, "{}({}) {}", name, real_args.join(", "), args.join(", "))
+        }
+    }
+}
+
+impl fmt::Display for BarrierPragma {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "barrier {}", List(&self.0))
+    }
+}
+
+impl fmt::Display for GateOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GateOperation::Unitary(operation) => write!(f, "{}", operation),
+            GateOperation::Barrier(barrier) => write!(f, "{}", barrier),
+        }
+    }
+}
+
+impl fmt::Display for QuantumOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuantumOperation::Unitary(operation) => write!(f, "{}", operation),
+            QuantumOperation::Measure(source, target) => {
+                write!(f, "measure {} -> {}", source, target)
+            }
+            QuantumOperation::Reset(target) => write!(f, "reset {}", target),
+        }
+    }
+}
+
+impl fmt::Display for UnitaryOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let UnitaryOperation(name, real_args, args) = self;
+        if real_args.is_empty() {
+            write!(f, "{} {}", name, List(args))
+        } else {
+            write!(f, "{}({}) {}", name, List(real_args), List(args))
+        }
+    }
+}
+
+impl fmt::Display for Argument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Argument::Id(name) => write!(f, "{}", name),
+            Argument::Item(name, index) => write!(f, "{}[{}]", name, index),
+        }
+    }
+}
+
+impl fmt::Display for OpCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            OpCode::Add => "+",
+            OpCode::Sub => "-",
+            OpCode::Mul => "*",
+            OpCode::Div => "/",
+            OpCode::Pow => "^",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl fmt::Display for FuncCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FuncCode::Sin => "sin",
+            FuncCode::Cos => "cos",
+            FuncCode::Tan => "tan",
+            FuncCode::Exp => "exp",
+            FuncCode::Ln => "ln",
+            FuncCode::Sqrt => "sqrt",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for Expression {
+    /// Print the expression with the parentheses the grammar needs only:
+    /// `^` binds tighter than `*` and `/`, which bind tighter than `+` and
+    /// `-`, and all of them associate to the left.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Pi => write!(f, "pi"),
+            Expression::Id(name) => write!(f, "{}", name),
+            Expression::Real(value) => write!(f, "{}", value),
+            Expression::Int(value) => write!(f, "{}", value),
+            Expression::Op(op, left, right) => {
+                let level = precedence(self);
+                write_operand(f, left, precedence(left) < level)?;
+                match op {
+                    OpCode::Add | OpCode::Sub => write!(f, " {} ", op)?,
+                    _ => write!(f, "{}", op)?,
+                }
+                write_operand(f, right, precedence(right) <= level)
+            }
+            Expression::Function(function, argument) => write!(f, "{}({})", function, argument),
+            Expression::Minus(operand) => {
+                write!(f, "-")?;
+                write_operand(f, operand, precedence(operand) < precedence(self))
+            }
+        }
+    }
+}
+
+fn precedence(expression: &Expression) -> u8 {
+    match expression {
+        Expression::Op(OpCode::Add, ..) | Expression::Op(OpCode::Sub, ..) => 1,
+        Expression::Op(OpCode::Mul, ..) | Expression::Op(OpCode::Div, ..) => 2,
+        Expression::Op(OpCode::Pow, ..) => 3,
+        _ => 4,
+    }
+}
+
+fn write_operand(
+    f: &mut fmt::Formatter<'_>,
+    operand: &Expression,
+    parenthesize: bool,
+) -> fmt::Result {
+    if parenthesize {
+        write!(f, "({})", operand)
+    } else {
+        write!(f, "{}", operand)
+    }
+}
+
+/// A comma-separated list.
+struct List<'a, T>(&'a [T]);
+
+impl<T: fmt::Display> fmt::Display for List<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, item) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::grammar::{parse_expression, parse_program_with_trivia, parse_statement};
+    use crate::qe::QELIB1;
+
+    use super::*;
+
+    /// Compare programs without the locations of the statements.
+    fn assert_same_program(left: &OpenQasmProgram, right: &OpenQasmProgram) {
+        let statements = |program: &OpenQasmProgram| -> Vec<Statement> {
+            program
+                .program
+                .iter()
+                .map(|span| (*span.node).clone())
+                .collect()
+        };
+        assert_eq!(left.version, right.version);
+        assert_eq!(statements(left), statements(right));
+        assert_eq!(left.trivia, right.trivia);
+    }
+
+    #[test]
+    fn test_expressions_have_minimal_parentheses() {
+        let cases = vec![
+            ("pi / 2", "pi/2"),
+            ("(a+b)*c", "(a + b)*c"),
+            ("(a-b)-c", "a - b - c"),
+            ("a-(b-c)", "a - (b - c)"),
+            ("a/(b*c)", "a/(b*c)"),
+            ("(a^b)^c", "a^b^c"),
+            ("a^(b^c)", "a^(b^c)"),
+            ("a^(-b)", "a^-b"),
+            ("-(a+b)", "-(a + b)"),
+            ("-a^2", "-a^2"),
+            ("-(a^2)", "-(a^2)"),
+            ("a - -b", "a - -b"),
+            ("sin((pi)) / 2.50", "sin(pi)/2.5"),
+            ("1.0e-3 + .5", "0.001 + 0.5"),
+        ];
+        for (source, expected) in cases {
+            let expression = parse_expression(source).unwrap();
+            let printed = expression.to_string();
+            assert_eq!(printed, expected);
+            assert_eq!(parse_expression(&printed).unwrap(), expression);
+        }
+    }
+
+    #[test]
+    fn test_statements() {
+        let cases = vec![
+            ("qreg   q [2] ;", "qreg q[2];"),
+            ("include  \"qelib1.inc\";", "include \"qelib1.inc\";"),
+            ("barrier q,r[0];", "barrier q, r[0];"),
+            ("opaque gate magic ( a,b ) q;", "opaque gate magic(a, b) q;"),
+            ("measure q[0]->c[0];", "measure q[0] -> c[0];"),
+            ("reset q;", "reset q;"),
+            ("if(c==1) x q;", "if (c == 1) x q;"),
+            ("U(0,0,pi) q[0];", "U(0, 0, pi) q[0];"),
+            ("CX q[0],q[1];", "CX q[0], q[1];"),
+            ("h() q;", "h q;"),
+            ("snapshot \"end\";", "snapshot \"end\";"),
+            ("gate id q {}", "gate id q { }"),
+            (
+                "gate r(theta) a,b { U(theta,0,0) a; barrier a,b; }",
+                "gate r(theta) a, b {\n  U(theta, 0, 0) a;\n  barrier a, b;\n}",
+            ),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse_statement(source).unwrap().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_programs_keep_their_comments() {
+        let source = indoc!(
+            "
+    // Bell state.
+
+    OPENQASM 2.0;
+    include \"qelib1.inc\"; // Standard gates.
+
+
+    // Entangle a pair of qubits.
+    gate bell a,b { // Apply
+    h a;    // superposition
+      // then
+    cx a,b;
+
+    // done
+    }
+    qreg q[2]; creg c[2];
+    bell q[0],q[1];
+    measure q -> c; // Last
+    "
+        );
+        let expected = indoc!(
+            "
+    // Bell state.
+
+    OPENQASM 2.0;
+    include \"qelib1.inc\"; // Standard gates.
+
+    // Entangle a pair of qubits.
+    gate bell a, b { // Apply
+      h a; // superposition
+      // then
+      cx a, b;
+
+      // done
+    }
+    qreg q[2];
+    creg c[2];
+    bell q[0], q[1];
+    measure q -> c; // Last
+    "
+        );
+        let program = parse_program_with_trivia(source).0.unwrap();
+        let printed = program.to_string();
+        assert_eq!(printed, expected);
+        let reprinted = parse_program_with_trivia(&printed).0.unwrap().to_string();
+        assert_eq!(reprinted, printed);
+    }
+
+    #[test]
+    fn test_round_trip_gives_the_same_program() {
+        let source = format!(
+            "OPENQASM 2.0;\n{}\nqreg q[2];\nu3(-pi/2,(1+2)^3,-(1/2)) q[0];",
+            QELIB1
+        );
+        let program = parse_program_with_trivia(&source).0.unwrap();
+        let reparsed = parse_program_with_trivia(&program.to_string()).0.unwrap();
+        assert_same_program(&reparsed, &program);
+    }
+}
@@ -0,0 +1,180 @@
+//! Relate the locations in a linked program with the source files they come
+//! from.
+
+use std::borrow::Cow;
+
+use crate::error::extract_line;
+use crate::grammar::lexer::{FileId, Location};
+
+/// Keep the source code of a program and of the libraries it includes.
+///
+/// The program is always the file with id `FileId(0)`. The linker adds each
+/// library it includes, named after the path in the `include` directive, so
+/// the locations of the statements coming from a library can be related to
+/// the library source.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::grammar::lexer::{FileId, Location};
+/// use qasmsim::grammar::source_map::SourceMap;
+///
+/// let mut source_map = SourceMap::new("OPENQASM 2.0;\ninclude \"lib.inc\";\n");
+/// let library = source_map.add_file("lib.inc", "gate id q {}\n\ngate nop q {}\n".into());
+///
+/// assert_eq!(library, FileId(1));
+/// assert_eq!(source_map.name(library), Some("lib.inc"));
+/// assert_eq!(source_map.line_and_column(Location::new_in(library, 19)), Some((3, 6)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMap<'src> {
+    files: Vec<SourceFile<'src>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SourceFile<'src> {
+    name: Option<String>,
+    source: Cow<'src, str>,
+}
+
+impl Default for SourceMap<'_> {
+    fn default() -> Self {
+        SourceMap::new("")
+    }
+}
+
+impl<'src> SourceMap<'src> {
+    /// Create a new source map whose program is `source`.
+    pub fn new(source: &'src str) -> Self {
+        SourceMap {
+            files: vec![SourceFile {
+                name: None,
+                source: Cow::Borrowed(source),
+            }],
+        }
+    }
+
+    /// Name the file of the program, usually after its path.
+    pub fn with_name<N: Into<String>>(mut self, name: N) -> Self {
+        self.files[0].name = Some(name.into());
+        self
+    }
+
+    /// Add the file `name` with `source` and return its id.
+    pub fn add_file<N: Into<String>>(&mut self, name: N, source: String) -> FileId {
+        self.files.push(SourceFile {
+            name: Some(name.into()),
+            source: Cow::Owned(source),
+        });
+        FileId(self.files.len() - 1)
+    }
+
+    /// Return the id of the file `name`, if any.
+    pub fn file_id(&self, name: &str) -> Option<FileId> {
+        self.files
+            .iter()
+            .position(|file| file.name.as_deref() == Some(name))
+            .map(FileId)
+    }
+
+    /// Return the name of `file`, if it is known and has a name.
+    pub fn name(&self, file: FileId) -> Option<&str> {
+        self.files.get(file.0)?.name.as_deref()
+    }
+
+    /// Return the source code of `file`, if it is known.
+    pub fn source(&self, file: FileId) -> Option<&str> {
+        self.files.get(file.0).map(|file| file.source.as_ref())
+    }
+
+    /// Return the number of files in the source map, including the program.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Return `true` if the source map only contains the program.
+    pub fn is_empty(&self) -> bool {
+        self.files.len() == 1
+    }
+
+    /// Return the line number (1-based) and the column (1-based) of
+    /// `location`, if its file is known.
+    pub fn line_and_column(&self, location: Location) -> Option<(usize, usize)> {
+        let source = self.source(location.file)?;
+        if location.offset > source.len() {
+            return None;
+        }
+        let (_, lineno, startpos, _) = extract_line(location.offset, None, source);
+        Some((lineno, startpos + 1))
+    }
+
+    /// Return a source map that does not borrow the source of the program.
+    pub fn into_owned(self) -> SourceMap<'static> {
+        SourceMap {
+            files: self
+                .files
+                .into_iter()
+                .map(|file| SourceFile {
+                    name: file.name,
+                    source: Cow::Owned(file.source.into_owned()),
+                })
+                .collect(),
+        }
+    }
+
+    /// Return the line containing `location` along with its line number and
+    /// the positions of `location` and of the optional `end` inside the line.
+    /// Unknown files yield an empty line at line 0.
+    pub(crate) fn extract_line(
+        &self,
+        location: Location,
+        end: Option<Location>,
+    ) -> (Cow<'src, str>, usize, usize, Option<usize>) {
+        let source = match self.files.get(location.file.0) {
+            Some(file) if location.offset <= file.source.len() => &file.source,
+            _ => return (Cow::Borrowed(""), 0, 0, None),
+        };
+        let endoffset = end.map(|end| end.offset);
+        match source {
+            Cow::Borrowed(source) => {
+                let (line, lineno, startpos, endpos) =
+                    extract_line(location.offset, endoffset, source);
+                (Cow::Borrowed(line), lineno, startpos, endpos)
+            }
+            Cow::Owned(source) => {
+                let (line, lineno, startpos, endpos) =
+                    extract_line(location.offset, endoffset, source);
+                (Cow::Owned(line.into()), lineno, startpos, endpos)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_line_borrows_from_the_program_only() {
+        let mut source_map = SourceMap::new("OPENQASM 2.0;\nqreg q[1];\n");
+        let library = source_map.add_file("lib.inc", "gate id q {}\n".into());
+
+        let (line, lineno, startpos, _) = source_map.extract_line(Location::new_at(19), None);
+        assert!(matches!(line, Cow::Borrowed("qreg q[1];\n")));
+        assert_eq!((lineno, startpos), (2, 5));
+
+        let (line, lineno, startpos, _) =
+            source_map.extract_line(Location::new_in(library, 5), None);
+        assert!(matches!(line, Cow::Owned(ref line) if line == "gate id q {}\n"));
+        assert_eq!((lineno, startpos), (1, 5));
+    }
+
+    #[test]
+    fn test_extract_line_of_unknown_files() {
+        let source_map = SourceMap::new("OPENQASM 2.0;\n");
+        assert_eq!(
+            source_map.extract_line(Location::new_in(FileId(1), 0), None),
+            (Cow::Borrowed(""), 0, 0, None)
+        );
+    }
+}
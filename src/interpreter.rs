@@ -1,6 +1,10 @@
-mod argument_solver;
+pub(crate) mod argument_solver;
 mod computation;
-mod expression_solver;
+mod debugger;
+pub(crate) mod expression_solver;
+mod observer;
 pub mod runtime;
 
 pub use self::computation::{Computation, Histogram};
+pub use self::debugger::{Debugger, MacroFrame, Stop};
+pub use self::observer::Observer;
@@ -1,9 +1,10 @@
 use std::collections::HashMap;
+use std::ops::Range;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::statevector::StateVector;
+use crate::statevector::{DensityMatrix, StateVector};
 
 /// Map classical registers with values and number of outcomes.
 pub type Histogram = HashMap<String, Vec<(u64, usize)>>;
@@ -27,6 +28,8 @@ pub struct Computation {
     memory: HashMap<String, u64>,
     probabilities: Vec<f64>,
     histogram: Option<Histogram>,
+    quantum_registers: HashMap<String, Range<usize>>,
+    snapshots: HashMap<String, StateVector>,
 }
 
 impl Computation {
@@ -43,9 +46,24 @@ impl Computation {
             statevector,
             memory,
             histogram,
+            quantum_registers: HashMap::new(),
+            snapshots: HashMap::new(),
         }
     }
 
+    pub(crate) fn with_quantum_registers(
+        mut self,
+        quantum_registers: HashMap<String, Range<usize>>,
+    ) -> Self {
+        self.quantum_registers = quantum_registers;
+        self
+    }
+
+    pub(crate) fn with_snapshots(mut self, snapshots: HashMap<String, StateVector>) -> Self {
+        self.snapshots = snapshots;
+        self
+    }
+
     /// Return the statevector of the quantum system.
     pub fn statevector(&self) -> &StateVector {
         &self.statevector
@@ -65,6 +83,105 @@ impl Computation {
     pub fn histogram(&self) -> &Option<Histogram> {
         &self.histogram
     }
+
+    /// Return an associative map with quantum register names and the range
+    /// of qubits they occupy in the state-vector.
+    pub fn quantum_registers(&self) -> &HashMap<String, Range<usize>> {
+        &self.quantum_registers
+    }
+
+    /// Return an associative map with the labels of the `snapshot` statements
+    /// and the state-vector at those points of the program. When simulating
+    /// with several shots, the snapshots are those of the last shot. A label
+    /// used by several `snapshot` statements keeps the state-vector of the
+    /// last one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qasmsim::{parse_and_link, simulate};
+    ///
+    /// let program = parse_and_link(r#"
+    /// OPENQASM 2.0;
+    /// include "qelib1.inc";
+    /// qreg q[1];
+    /// snapshot "before";
+    /// x q[0];
+    /// "#)?;
+    /// let computation = simulate(&program)?;
+    /// let before = &computation.snapshots()["before"];
+    /// assert!((before.probabilities()[0] - 1.0).abs() < 1e-9);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn snapshots(&self) -> &HashMap<String, StateVector> {
+        &self.snapshots
+    }
+
+    /// Return the reduced density matrix of the quantum register `name`, or
+    /// `None` if there is no such a quantum register.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qasmsim::{parse_and_link, simulate};
+    ///
+    /// let program = parse_and_link(r#"
+    /// OPENQASM 2.0;
+    /// include "qelib1.inc";
+    /// qreg a[1];
+    /// qreg b[1];
+    /// h a[0];
+    /// cx a[0], b[0];
+    /// "#)?;
+    /// let computation = simulate(&program)?;
+    /// let density_matrix = computation.register_density_matrix("a").unwrap();
+    /// assert!((density_matrix.von_neumann_entropy() - 1.0).abs() < 1e-9);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn register_density_matrix(&self, name: &str) -> Option<DensityMatrix> {
+        let range = self.quantum_registers.get(name)?;
+        let qubits: Vec<usize> = range.clone().collect();
+        Some(self.statevector.reduced_density_matrix(&qubits))
+    }
+
+    /// Return the entanglement entropy of the quantum register `name` with
+    /// the rest of the qubits, or `None` if there is no such a quantum
+    /// register. Unlike [`register_density_matrix()`], it does not build the
+    /// density matrix, so it is cheap for large registers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qasmsim::{parse_and_link, simulate};
+    ///
+    /// let program = parse_and_link(r#"
+    /// OPENQASM 2.0;
+    /// include "qelib1.inc";
+    /// qreg a[1];
+    /// qreg b[1];
+    /// h a[0];
+    /// cx a[0], b[0];
+    /// "#)?;
+    /// let computation = simulate(&program)?;
+    /// assert!((computation.register_entropy("a").unwrap() - 1.0).abs() < 1e-9);
+    /// assert!((computation.register_purity("b").unwrap() - 0.5).abs() < 1e-9);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// [`register_density_matrix()`]: #method.register_density_matrix
+    pub fn register_entropy(&self, name: &str) -> Option<f64> {
+        let range = self.quantum_registers.get(name)?;
+        let qubits: Vec<usize> = range.clone().collect();
+        Some(self.statevector.entanglement_entropy(&qubits))
+    }
+
+    /// Return the purity of the reduced state of the quantum register
+    /// `name`, or `None` if there is no such a quantum register.
+    pub fn register_purity(&self, name: &str) -> Option<f64> {
+        let range = self.quantum_registers.get(name)?;
+        let qubits: Vec<usize> = range.clone().collect();
+        Some(self.statevector.reduced_purity(&qubits))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
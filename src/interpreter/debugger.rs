@@ -0,0 +1,541 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::api;
+use crate::error::QasmSimError;
+use crate::grammar::{ast, lexer::Location};
+use crate::interpreter::computation::Computation;
+use crate::interpreter::runtime::{Runtime, RuntimeError};
+use crate::semantics::check_semantics;
+use crate::statevector::StateVector;
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+/// Represent the reason why the [`Debugger`] stopped.
+///
+/// [`Debugger`]: ./struct.Debugger.html
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Stop {
+    /// The debugger completed a step and there are more statements to run.
+    Step,
+    /// The debugger reached a statement in a line with a breakpoint. The
+    /// variant contains the line number.
+    Breakpoint(usize),
+    /// The program has finished.
+    Finished,
+}
+
+/// Represent a gate call in progress, as reported by
+/// [`Debugger::macro_stack()`].
+///
+/// [`Debugger::macro_stack()`]: ./struct.Debugger.html#method.macro_stack
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MacroFrame {
+    name: String,
+    real_args: HashMap<String, f64>,
+    args: HashMap<String, ast::Argument>,
+    next_operation: usize,
+}
+
+impl MacroFrame {
+    /// Return the name of the gate.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the values bound to the formal real parameters of the gate.
+    pub fn real_args(&self) -> &HashMap<String, f64> {
+        &self.real_args
+    }
+
+    /// Return the qubits bound to the formal quantum parameters of the gate.
+    pub fn args(&self) -> &HashMap<String, ast::Argument> {
+        &self.args
+    }
+
+    /// Return the index, in the body of the gate, of the next operation to
+    /// run.
+    pub fn next_operation(&self) -> usize {
+        self.next_operation
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Frame {
+    name: String,
+    real_args: Vec<f64>,
+    pending_expansions: VecDeque<Vec<ast::Argument>>,
+    operations: Vec<ast::GateOperation>,
+    next_operation: usize,
+}
+
+impl Frame {
+    fn is_finished(&self) -> bool {
+        self.next_operation >= self.operations.len()
+    }
+}
+
+/// Run an OPENQASM program step by step.
+///
+/// The debugger stops before the first statement of the program that is not
+/// a declaration. From there, it can run the program one statement at a time
+/// with [`step()`], enter the body of gates with [`step_into()`], or run
+/// until reaching a line with a breakpoint with [`resume()`].
+///
+/// Stepping methods return a [`RuntimeError`] when the simulation fails. It
+/// can be related to the source code with `QasmSimError::from((debugger.source(), error))`.
+///
+/// [`step()`]: #method.step
+/// [`step_into()`]: #method.step_into
+/// [`resume()`]: #method.resume
+/// [`RuntimeError`]: ./error/enum.RuntimeError.html
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::{Debugger, Stop};
+///
+/// let mut debugger = Debugger::new(r#"
+/// OPENQASM 2.0;
+/// include "qelib1.inc";
+/// qreg q[2];
+/// h q[0];
+/// cx q[0], q[1];
+/// "#)?;
+/// assert_eq!(debugger.lineno(), Some(5));
+///
+/// assert_eq!(debugger.step_into()?, Stop::Step);
+/// assert_eq!(debugger.macro_stack()[0].name(), "h");
+///
+/// debugger.add_breakpoint(6);
+/// assert_eq!(debugger.resume()?, Stop::Breakpoint(6));
+/// assert!(debugger.macro_stack().is_empty());
+///
+/// assert_eq!(debugger.resume()?, Stop::Finished);
+/// assert!((debugger.statevector().probabilities()[3] - 0.5).abs() < 1e-9);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct Debugger {
+    source: String,
+    program: ast::OpenQasmProgram,
+    runtime: Runtime<'static>,
+    frames: Vec<Frame>,
+    next_statement: usize,
+    breakpoints: HashSet<usize>,
+}
+
+impl Debugger {
+    /// Parse and link `source` and prepare the debugger to run it.
+    ///
+    /// # Errors
+    ///
+    /// The function fails if the program cannot be parsed, linked, or if it
+    /// is semantically wrong.
+    pub fn new(source: &str) -> api::Result<'_, Self> {
+        let program = api::parse_and_link(source)?;
+        let semantics = check_semantics(&program)
+            .map_err(|mut errors| QasmSimError::from((source, errors.remove(0))))?;
+        let runtime = Runtime::new(semantics).map_err(|err| QasmSimError::from((source, err)))?;
+        let mut debugger = Debugger {
+            source: source.into(),
+            program,
+            runtime,
+            frames: Vec::new(),
+            next_statement: 0,
+            breakpoints: HashSet::new(),
+        };
+        debugger.skip_declarations();
+        Ok(debugger)
+    }
+
+    /// Return the source code of the program.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Add a breakpoint at line `lineno`.
+    pub fn add_breakpoint(&mut self, lineno: usize) {
+        self.breakpoints.insert(lineno);
+    }
+
+    /// Remove the breakpoint at line `lineno`, if any.
+    pub fn remove_breakpoint(&mut self, lineno: usize) {
+        self.breakpoints.remove(&lineno);
+    }
+
+    /// Return the lines with a breakpoint.
+    pub fn breakpoints(&self) -> &HashSet<usize> {
+        &self.breakpoints
+    }
+
+    /// Return `true` if there is nothing else to run.
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty() && self.next_statement >= self.program.program.len()
+    }
+
+    /// Return the location of the statement that is about to run or, when
+    /// inside a gate, of the statement that called the gate. Return `None`
+    /// if the program has finished.
+    pub fn location(&self) -> Option<Location> {
+        if !self.frames.is_empty() {
+            return self.runtime.location;
+        }
+        self.program
+            .program
+            .get(self.next_statement)
+            .map(|span| span.boundaries.0)
+    }
+
+    /// Return the line number of [`location()`].
+    ///
+    /// [`location()`]: #method.location
+    pub fn lineno(&self) -> Option<usize> {
+        self.location().map(|location| self.lineno_of(location))
+    }
+
+    /// Return the gate calls in progress, starting from the innermost one.
+    pub fn macro_stack(&self) -> Vec<MacroFrame> {
+        self.frames
+            .iter()
+            .rev()
+            .zip(self.runtime.macro_stack.iter())
+            .map(|(frame, (real_args, args))| MacroFrame {
+                name: frame.name.clone(),
+                real_args: real_args.clone(),
+                args: args.clone(),
+                next_operation: frame.next_operation,
+            })
+            .collect()
+    }
+
+    /// Return an associative map with classical names and their current
+    /// values.
+    pub fn memory(&self) -> &HashMap<String, u64> {
+        &self.runtime.memory
+    }
+
+    /// Return the current statevector of the quantum system.
+    pub fn statevector(&self) -> &StateVector {
+        &self.runtime.statevector
+    }
+
+    /// Return the snapshots taken so far.
+    pub fn snapshots(&self) -> &HashMap<String, StateVector> {
+        &self.runtime.snapshots
+    }
+
+    /// Return the computation with the current state of the simulation.
+    pub fn computation(&self) -> Computation {
+        Computation::new(
+            self.runtime.memory.clone(),
+            self.runtime.statevector.clone(),
+            None,
+        )
+        .with_quantum_registers(self.runtime.semantics.quantum_registers())
+        .with_snapshots(self.runtime.snapshots.clone())
+    }
+
+    /// Run the next statement or, inside a gate, the next operation of the
+    /// gate body, without stopping inside the gates it calls.
+    pub fn step(&mut self) -> Result<Stop> {
+        match self.frames.last_mut() {
+            None => {
+                if let Some(span) = self.program.program.get(self.next_statement) {
+                    self.runtime.apply_statement(span)?;
+                    self.next_statement += 1;
+                }
+            }
+            Some(frame) => {
+                let operation = frame.operations[frame.next_operation].clone();
+                frame.next_operation += 1;
+                if let ast::GateOperation::Unitary(unitary) = operation {
+                    self.runtime.apply_unitary(&unitary)?;
+                }
+            }
+        }
+        self.unwind()?;
+        Ok(self.stop())
+    }
+
+    /// Like [`step()`] but, if the next operation calls a gate which is not
+    /// `U` or `CX`, stop before the first operation of the gate body.
+    ///
+    /// [`step()`]: #method.step
+    pub fn step_into(&mut self) -> Result<Stop> {
+        let unitary = match self.next_unitary()? {
+            Some(unitary) => unitary,
+            None => return self.step(),
+        };
+        let name = unitary.0.clone();
+        if name == "U" || name == "CX" {
+            return self.step();
+        }
+
+        let (real_args, expansions) = self.runtime.expand_unitary(&unitary)?;
+        match self.frames.last_mut() {
+            None => self.next_statement += 1,
+            Some(frame) => frame.next_operation += 1,
+        }
+        let mut pending_expansions = VecDeque::from(expansions);
+        if let Some(args) = pending_expansions.pop_front() {
+            let bindings = self.runtime.bind(name.clone(), &real_args, &args)?;
+            let operations = self.runtime.semantics.macro_definitions[&name].3.clone();
+            self.runtime.enter_macro(bindings);
+            self.frames.push(Frame {
+                name,
+                real_args,
+                pending_expansions,
+                operations,
+                next_operation: 0,
+            });
+        }
+        self.unwind()?;
+        Ok(self.stop())
+    }
+
+    /// Run until leaving the innermost gate call. Outside gates, it behaves
+    /// like [`step()`].
+    ///
+    /// [`step()`]: #method.step
+    pub fn step_out(&mut self) -> Result<Stop> {
+        let depth = self.frames.len();
+        let mut stop = self.step()?;
+        while depth > 0 && self.frames.len() >= depth {
+            stop = self.step()?;
+        }
+        Ok(stop)
+    }
+
+    /// Run until reaching a statement in a line with a breakpoint, other
+    /// than the current one, or until the program finishes.
+    pub fn resume(&mut self) -> Result<Stop> {
+        let mut current_lineno = self.lineno();
+        while !self.is_finished() {
+            self.step()?;
+            if !self.frames.is_empty() {
+                continue;
+            }
+            let lineno = self.lineno();
+            if let Some(lineno) = lineno {
+                if Some(lineno) != current_lineno && self.breakpoints.contains(&lineno) {
+                    return Ok(Stop::Breakpoint(lineno));
+                }
+            }
+            current_lineno = lineno;
+        }
+        Ok(Stop::Finished)
+    }
+
+    /// Return the unitary operation that is about to run, if any. For
+    /// conditional statements, the unitary operation is returned only if
+    /// the condition holds.
+    fn next_unitary(&mut self) -> Result<Option<ast::UnitaryOperation>> {
+        if let Some(frame) = self.frames.last() {
+            return Ok(match &frame.operations[frame.next_operation] {
+                ast::GateOperation::Unitary(unitary) => Some(unitary.clone()),
+                _ => None,
+            });
+        }
+
+        let span = match self.program.program.get(self.next_statement) {
+            None => return Ok(None),
+            Some(span) => span,
+        };
+        self.runtime.location = Some(span.boundaries.0);
+        Ok(match &*span.node {
+            ast::Statement::QuantumOperation(ast::QuantumOperation::Unitary(unitary)) => {
+                Some(unitary.clone())
+            }
+            ast::Statement::Conditional(
+                register,
+                test,
+                ast::QuantumOperation::Unitary(unitary),
+            ) => {
+                if self.runtime.condition_holds(register, *test)? {
+                    Some(unitary.clone())
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+    }
+
+    /// Leave the finished gate calls, starting the next pending expansion of
+    /// each call, if any.
+    fn unwind(&mut self) -> Result<()> {
+        while let Some(frame) = self.frames.last_mut() {
+            if !frame.is_finished() {
+                break;
+            }
+            self.runtime.exit_macro();
+            if let Some(args) = frame.pending_expansions.pop_front() {
+                let bindings = self
+                    .runtime
+                    .bind(frame.name.clone(), &frame.real_args, &args)?;
+                self.runtime.enter_macro(bindings);
+                frame.next_operation = 0;
+                continue;
+            }
+            self.frames.pop();
+        }
+        if self.frames.is_empty() {
+            self.skip_declarations();
+        }
+        Ok(())
+    }
+
+    fn skip_declarations(&mut self) {
+        while let Some(span) = self.program.program.get(self.next_statement) {
+            match &*span.node {
+                ast::Statement::QRegDecl(..)
+                | ast::Statement::CRegDecl(..)
+                | ast::Statement::GateDecl(..)
+                | ast::Statement::OpaqueGateDecl(..)
+                | ast::Statement::Include(..) => self.next_statement += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn stop(&self) -> Stop {
+        if self.is_finished() {
+            Stop::Finished
+        } else {
+            Stop::Step
+        }
+    }
+
+    /// Return the line of `location`, whose offset counts bytes.
+    fn lineno_of(&self, location: Location) -> usize {
+        self.source
+            .bytes()
+            .take(location.offset)
+            .filter(|byte| *byte == b'\n')
+            .count()
+            + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+
+    #[test]
+    fn test_step_into_register_wide_call() {
+        let source = indoc!(
+            "
+        OPENQASM 2.0;
+        gate g a {
+          U(0, 0, 0) a;
+          barrier a;
+        }
+        qreg q[2];
+        g q;
+        U(0, 0, 0) q[0];
+        "
+        );
+        let mut debugger = Debugger::new(source).unwrap();
+        assert_eq!(debugger.lineno(), Some(7));
+
+        assert_eq!(debugger.step_into().unwrap(), Stop::Step);
+        let stack = debugger.macro_stack();
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack[0].args()["a"], ast::Argument::Item("q".into(), 0));
+        assert_eq!(stack[0].next_operation(), 0);
+        assert_eq!(debugger.lineno(), Some(7));
+
+        debugger.step().unwrap();
+        assert_eq!(debugger.macro_stack()[0].next_operation(), 1);
+        debugger.step().unwrap();
+        let stack = debugger.macro_stack();
+        assert_eq!(stack[0].args()["a"], ast::Argument::Item("q".into(), 1));
+        assert_eq!(stack[0].next_operation(), 0);
+
+        assert_eq!(debugger.step_out().unwrap(), Stop::Step);
+        assert!(debugger.macro_stack().is_empty());
+        assert_eq!(debugger.lineno(), Some(8));
+
+        assert_eq!(debugger.step_into().unwrap(), Stop::Finished);
+        assert_eq!(debugger.lineno(), None);
+    }
+
+    #[test]
+    fn test_nested_macro_stack() {
+        let source = indoc!(
+            "
+        OPENQASM 2.0;
+        gate inner(theta) b {
+          U(theta, 0, 0) b;
+        }
+        gate outer a {
+          inner(pi) a;
+        }
+        qreg q[1];
+        outer q[0];
+        "
+        );
+        let mut debugger = Debugger::new(source).unwrap();
+        debugger.step_into().unwrap();
+        debugger.step_into().unwrap();
+
+        let stack = debugger.macro_stack();
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack[0].name(), "inner");
+        assert_eq!(stack[0].real_args()["theta"], std::f64::consts::PI);
+        assert_eq!(stack[1].name(), "outer");
+        assert_eq!(stack[1].next_operation(), 1);
+
+        assert_eq!(debugger.step().unwrap(), Stop::Finished);
+        assert!(debugger.computation().statevector().probabilities()[0] < 1e-9);
+    }
+
+    #[test]
+    fn test_resume_stops_at_breakpoints() {
+        let source = indoc!(
+            "
+        OPENQASM 2.0;
+        qreg q[1];
+        creg c[1];
+        U(pi, 0, 0) q[0];
+        measure q[0] -> c[0];
+        U(pi, 0, 0) q[0];
+        "
+        );
+        let mut debugger = Debugger::new(source).unwrap();
+        debugger.add_breakpoint(4);
+        debugger.add_breakpoint(6);
+
+        assert_eq!(debugger.resume().unwrap(), Stop::Breakpoint(6));
+        assert_eq!(debugger.memory()["c"], 1);
+        assert_eq!(debugger.resume().unwrap(), Stop::Finished);
+        assert!(debugger.is_finished());
+    }
+
+    #[test]
+    fn test_lines_after_non_ascii_comments() {
+        let source = indoc!(
+            "
+        OPENQASM 2.0;
+        // Estado |ψ⟩ → |0⟩ ⊗ |1⟩ ⊗ |0⟩ ⊗ |1⟩ ⊗ |0⟩ ⊗ |1⟩ ⊗ |0⟩ ⊗ |1⟩.
+        qreg q[1];
+        U(pi, 0, 0) q[0];
+        U(pi, 0, 0) q[0];
+        "
+        );
+        let mut debugger = Debugger::new(source).unwrap();
+        assert_eq!(debugger.lineno(), Some(4));
+        debugger.add_breakpoint(5);
+
+        assert_eq!(debugger.resume().unwrap(), Stop::Breakpoint(5));
+        assert_eq!(debugger.lineno(), Some(5));
+    }
+}
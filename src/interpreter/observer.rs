@@ -0,0 +1,64 @@
+use crate::grammar::lexer::Location;
+
+/// Receive notifications as the runtime executes a program.
+///
+/// All the methods have an empty default implementation so implementors only
+/// need to override those they are interested in. Register an observer with
+/// [`SimulationOptions::with_observer()`] and simulate with
+/// [`simulate_with()`] or [`run_with()`]. [`simulate()`] and
+/// [`simulate_with_shots()`] do not take observers, to keep their signatures.
+///
+/// [`SimulationOptions::with_observer()`]: ./struct.SimulationOptions.html#method.with_observer
+/// [`simulate_with()`]: ./fn.simulate_with.html
+/// [`run_with()`]: ./fn.run_with.html
+/// [`simulate()`]: ./fn.simulate.html
+/// [`simulate_with_shots()`]: ./fn.simulate_with_shots.html
+///
+/// # Examples
+///
+/// Count the number of primitive gates applied during the simulation:
+///
+/// ```
+/// use qasmsim::{parse_and_link, simulate_with, Observer, SimulationOptions};
+///
+/// #[derive(Default)]
+/// struct GateCounter(usize);
+///
+/// impl Observer for GateCounter {
+///     fn on_gate(&mut self, _name: &str, _qubits: &[usize], _params: &[f64]) {
+///         self.0 += 1;
+///     }
+/// }
+///
+/// let program = parse_and_link(r#"
+/// OPENQASM 2.0;
+/// include "qelib1.inc";
+/// qreg q[2];
+/// h q;
+/// cx q[0], q[1];
+/// "#)?;
+/// let mut counter = GateCounter::default();
+/// simulate_with(&program, SimulationOptions::new().with_observer(&mut counter))?;
+/// assert_eq!(counter.0, 3);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub trait Observer {
+    /// Called before running a statement of the program at `location`.
+    fn on_statement(&mut self, _location: Location) {}
+
+    /// Called before applying one of the primitive gates, `U` or `CX`, to
+    /// `qubits`, with the values of the real parameters in `params`.
+    fn on_gate(&mut self, _name: &str, _qubits: &[usize], _params: &[f64]) {}
+
+    /// Called after measuring `qubit` into the `bit` of the classical
+    /// `register`, with the outcome of the measurement.
+    fn on_measure(&mut self, _qubit: usize, _register: &str, _bit: usize, _outcome: u64) {}
+
+    /// Called before running the body of the gate `name`, applied to `qubits`
+    /// with the values of the real parameters in `params`.
+    fn on_macro_enter(&mut self, _name: &str, _qubits: &[usize], _params: &[f64]) {}
+
+    /// Called after running the body of the gate `name`, even if running it
+    /// fails.
+    fn on_macro_exit(&mut self, _name: &str) {}
+}
@@ -1,7 +1,7 @@
 use std::collections::{HashMap, VecDeque};
-use std::iter::FromIterator;
 use std::error;
 use std::fmt;
+use std::iter::FromIterator;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -10,10 +10,17 @@ use crate::grammar::{ast, lexer::Location};
 use crate::interpreter::argument_solver::ArgumentSolver;
 use crate::interpreter::computation::{Computation, HistogramBuilder};
 use crate::interpreter::expression_solver::ExpressionSolver;
-use crate::semantics::{extract_semantics, QasmType, RegisterType, SemanticError, Semantics};
+use crate::interpreter::observer::Observer;
+use crate::semantics::{check_semantics, QasmType, RegisterType, SemanticError, Semantics};
 use crate::statevector::StateVector;
 
-type BindingMappings = (HashMap<String, f64>, HashMap<String, ast::Argument>);
+pub(crate) type BindingMappings = (HashMap<String, f64>, HashMap<String, ast::Argument>);
+
+/// Maximum deviation from 1 allowed for the squared norm of an initial state.
+const NORM_TOLERANCE: f64 = 1e-4;
+
+/// Maximum number of qubits the simulator allocates a state-vector for.
+pub const MAX_QUBITS: usize = 32;
 
 /// Represent one of the possible errors that can happen during runtime.
 #[non_exhaustive]
@@ -83,6 +90,59 @@ pub enum RuntimeError {
         /// Sizes of the different registers involved.
         sizes: Vec<usize>,
     },
+    /// The initial state does not have as many qubits as the program.
+    InitialStateWidthMismatch {
+        /// Number of qubits declared in the program.
+        expected: usize,
+        /// Number of qubits of the initial state.
+        given: usize,
+    },
+    /// The initial state is not a unit vector.
+    InitialStateNotNormalized,
+    /// The program declares more qubits than the simulator supports.
+    TooManyQubits {
+        /// Number of qubits declared in the program.
+        qubits: usize,
+        /// Maximum number of qubits of a simulation.
+        max_qubits: usize,
+    },
+}
+
+impl RuntimeError {
+    /// Return the stable code of the error. The code is kept when converting
+    /// the error into a [`QasmSimError`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qasmsim::grammar::lexer::Location;
+    /// use qasmsim::error::RuntimeError;
+    ///
+    /// let error = RuntimeError::IndexOutOfBounds {
+    ///     location: Location::new_at(0),
+    ///     symbol_name: "q".to_string(),
+    ///     index: 2,
+    ///     size: 2,
+    /// };
+    /// assert_eq!(error.code(), "E0101");
+    /// ```
+    ///
+    /// [`QasmSimError`]: ../error/enum.QasmSimError.html
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeError::Other => "E0001",
+            RuntimeError::SemanticError(semantic_error) => semantic_error.code(),
+            RuntimeError::IndexOutOfBounds { .. } => "E0101",
+            RuntimeError::SymbolNotFound { .. } => "E0102",
+            RuntimeError::WrongNumberOfParameters { .. } => "E0103",
+            RuntimeError::UndefinedGate { .. } => "E0104",
+            RuntimeError::TypeMismatch { .. } => "E0105",
+            RuntimeError::RegisterSizeMismatch { .. } => "E0106",
+            RuntimeError::InitialStateWidthMismatch { .. } => "E0107",
+            RuntimeError::InitialStateNotNormalized => "E0108",
+            RuntimeError::TooManyQubits { .. } => "E0109",
+        }
+    }
 }
 
 impl fmt::Display for RuntimeError {
@@ -90,7 +150,16 @@ impl fmt::Display for RuntimeError {
         let message = match self {
             RuntimeError::Other => "unknown error".to_string(),
             RuntimeError::SemanticError(semantic_error) => format!("{}", semantic_error),
-            _ => match lazy_humanize!{
+            RuntimeError::InitialStateWidthMismatch { expected, given } => format!(
+                "the initial state has {} qubits but the program declares {}",
+                given, expected
+            ),
+            RuntimeError::InitialStateNotNormalized => "the initial state is not normalized".into(),
+            RuntimeError::TooManyQubits { qubits, max_qubits } => format!(
+                "the program declares {} qubits but the simulator supports {} at most",
+                qubits, max_qubits
+            ),
+            _ => match lazy_humanize! {
                 self,
                 RuntimeError::IndexOutOfBounds,
                 RuntimeError::RegisterSizeMismatch,
@@ -100,8 +169,8 @@ impl fmt::Display for RuntimeError {
                 RuntimeError::WrongNumberOfParameters
             } {
                 Some(message) => message,
-                None => unreachable!()
-            }
+                None => unreachable!(),
+            },
         };
         write!(f, "{}", message)
     }
@@ -117,35 +186,96 @@ impl From<SemanticError> for RuntimeError {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-struct Runtime<'program> {
-    macro_stack: VecDeque<BindingMappings>,
-    semantics: Semantics,
-    statevector: StateVector,
-    memory: HashMap<String, u64>,
-    location: Option<&'program Location>,
+pub(super) struct Runtime<'obs> {
+    pub(super) macro_stack: VecDeque<BindingMappings>,
+    pub(super) semantics: Semantics,
+    pub(super) statevector: StateVector,
+    initial_state: Option<StateVector>,
+    pub(super) memory: HashMap<String, u64>,
+    pub(super) snapshots: HashMap<String, StateVector>,
+    pub(super) location: Option<Location>,
+    observer: Option<&'obs mut dyn Observer>,
+}
+
+impl fmt::Debug for Runtime<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Runtime")
+            .field("macro_stack", &self.macro_stack)
+            .field("semantics", &self.semantics)
+            .field("statevector", &self.statevector)
+            .field("initial_state", &self.initial_state)
+            .field("memory", &self.memory)
+            .field("snapshots", &self.snapshots)
+            .field("location", &self.location)
+            .field("observed", &self.observer.is_some())
+            .finish()
+    }
 }
 
-impl<'src, 'program> Runtime<'program> {
-    pub fn new(semantics: Semantics) -> Self {
+impl<'obs> Runtime<'obs> {
+    pub fn new(semantics: Semantics) -> Result<Self> {
         let memory_size = semantics.quantum_memory_size;
+        if memory_size > MAX_QUBITS {
+            return Err(RuntimeError::TooManyQubits {
+                qubits: memory_size,
+                max_qubits: MAX_QUBITS,
+            });
+        }
 
         let mut runtime = Runtime {
             macro_stack: VecDeque::new(),
             semantics,
             statevector: StateVector::new(memory_size),
+            initial_state: None,
             memory: HashMap::new(),
+            snapshots: HashMap::new(),
             location: None,
+            observer: None,
         };
 
         runtime.reset();
-        runtime
+        Ok(runtime)
+    }
+
+    pub fn with_initial_state(semantics: Semantics, initial_state: StateVector) -> Result<Self> {
+        let expected = semantics.quantum_memory_size;
+        let given = initial_state.qubit_width();
+        if given != expected || initial_state.len() != 1 << given {
+            return Err(RuntimeError::InitialStateWidthMismatch { expected, given });
+        }
+
+        let norm_sqr: f64 = initial_state.probabilities().iter().sum();
+        if (norm_sqr - 1.0).abs() > NORM_TOLERANCE {
+            return Err(RuntimeError::InitialStateNotNormalized);
+        }
+
+        let mut runtime = Runtime::new(semantics)?;
+        runtime.initial_state = Some(initial_state);
+        runtime.reset();
+        Ok(runtime)
+    }
+
+    pub fn set_observer(&mut self, observer: &'obs mut dyn Observer) {
+        self.observer = Some(observer);
+    }
+
+    fn notify<F>(&mut self, notification: F)
+    where
+        F: FnOnce(&mut dyn Observer),
+    {
+        if let Some(observer) = self.observer.as_mut() {
+            notification(&mut **observer);
+        }
     }
 
     pub fn reset(&mut self) {
         self.macro_stack.clear();
-        self.statevector.reset();
+        match &self.initial_state {
+            None => self.statevector.reset(),
+            Some(initial_state) => self.statevector = initial_state.clone(),
+        }
         self.clear_memory();
+        self.snapshots.clear();
     }
 
     fn clear_memory(&mut self) {
@@ -157,36 +287,52 @@ impl<'src, 'program> Runtime<'program> {
         }
     }
 
-    fn apply_gates(&mut self, statements: &'program [ast::Span<ast::Statement>]) -> Result<()> {
+    fn apply_gates(&mut self, statements: &[ast::Span<ast::Statement>]) -> Result<()> {
         for span in statements {
-            self.location = Some(&span.boundaries.0);
-            match &*span.node {
-                ast::Statement::QuantumOperation(operation) => {
-                    self.apply_quantum_operation(&operation)?;
-                }
-                ast::Statement::Conditional(register, test, operation) => {
-                    let actual_register = (register).clone();
-                    let register_name = self.register_name(&actual_register);
-                    self.assert_is_classical_register(register_name)?;
-
-                    let value = match actual_register {
-                        ast::Argument::Id(register_name) => self
-                            .memory
-                            .get(&register_name)
-                            .expect("after `assert_is_classical_register()`, must exist"),
-                        _ => unreachable!("cannot index a register inside the condition"),
-                    };
-                    if value == test {
-                        self.apply_quantum_operation(&operation)?;
-                    }
-                }
-                _ => (),
-            };
+            self.apply_statement(span)?;
         }
         Ok(())
     }
 
-    fn apply_quantum_operation(&mut self, operation: &ast::QuantumOperation) -> Result<()> {
+    pub(super) fn apply_statement(&mut self, span: &ast::Span<ast::Statement>) -> Result<()> {
+        self.location = Some(span.boundaries.0);
+        self.notify(|observer| observer.on_statement(span.boundaries.0));
+        match &*span.node {
+            ast::Statement::QuantumOperation(operation) => {
+                self.apply_quantum_operation(operation)?;
+            }
+            ast::Statement::Conditional(register, test, operation)
+                if self.condition_holds(register, *test)? =>
+            {
+                self.apply_quantum_operation(operation)?;
+            }
+            ast::Statement::Snapshot(label) => {
+                self.snapshots
+                    .insert(label.clone(), self.statevector.clone());
+            }
+            _ => (),
+        };
+        Ok(())
+    }
+
+    pub(super) fn condition_holds(&self, register: &ast::Argument, test: u64) -> Result<bool> {
+        let register_name = self.register_name(register);
+        self.assert_is_classical_register(register_name)?;
+
+        let value = match register {
+            ast::Argument::Id(register_name) => self
+                .memory
+                .get(register_name)
+                .expect("after `assert_is_classical_register()`, must exist"),
+            _ => unreachable!("cannot index a register inside the condition"),
+        };
+        Ok(*value == test)
+    }
+
+    pub(super) fn apply_quantum_operation(
+        &mut self,
+        operation: &ast::QuantumOperation,
+    ) -> Result<()> {
         match operation {
             ast::QuantumOperation::Unitary(unitary) => self.apply_unitary(unitary),
             ast::QuantumOperation::Measure(source, target) => {
@@ -196,7 +342,21 @@ impl<'src, 'program> Runtime<'program> {
         }
     }
 
-    fn apply_unitary(&mut self, unitary: &ast::UnitaryOperation) -> Result<()> {
+    pub(super) fn apply_unitary(&mut self, unitary: &ast::UnitaryOperation) -> Result<()> {
+        let name = &unitary.0;
+        let (solved_real_args, expanded_arguments) = self.expand_unitary(unitary)?;
+        for argument_expansion in expanded_arguments {
+            self.apply_one_gate(name, &solved_real_args, &argument_expansion)?;
+        }
+        Ok(())
+    }
+
+    /// Return the values of the real arguments of `unitary` and the list of
+    /// qubit arguments for each application of the gate.
+    pub(super) fn expand_unitary(
+        &self,
+        unitary: &ast::UnitaryOperation,
+    ) -> Result<(Vec<f64>, Vec<Vec<ast::Argument>>)> {
         let name = &unitary.0;
         let real_args = &unitary.1;
         let args = &unitary.2;
@@ -208,7 +368,7 @@ impl<'src, 'program> Runtime<'program> {
 
         let expanded_arguments = self.expand_arguments(&actual_args).map_err(|sizes| {
             RuntimeError::RegisterSizeMismatch {
-                location: *self
+                location: self
                     .location
                     .expect("after `apply_gates()`, the location of the statement"),
                 symbol_name: name.clone(),
@@ -216,11 +376,7 @@ impl<'src, 'program> Runtime<'program> {
             }
         })?;
 
-        for argument_expansion in expanded_arguments {
-            self.apply_one_gate(name, &solved_real_args, &argument_expansion)?;
-        }
-
-        Ok(())
+        Ok((solved_real_args, expanded_arguments))
     }
 
     fn resolve_actual_args(&self, args: &[ast::Argument]) -> Result<Vec<ast::Argument>> {
@@ -231,7 +387,7 @@ impl<'src, 'program> Runtime<'program> {
         } else {
             let stack_entry = self
                 .macro_stack
-                .get(0)
+                .front()
                 .expect("if `is_running_macro()`, get first entry of the stack");
             let arg_bindings = &stack_entry.1;
             let argument_solver = ArgumentSolver::new(arg_bindings);
@@ -239,7 +395,7 @@ impl<'src, 'program> Runtime<'program> {
                 .map(|argument| {
                     argument_solver.solve(argument).map_err(|symbol_name| {
                         RuntimeError::SymbolNotFound {
-                            location: *self
+                            location: self
                                 .location
                                 .expect("after `apply_gates()`, the location of the statement"),
                             symbol_name,
@@ -257,22 +413,22 @@ impl<'src, 'program> Runtime<'program> {
         if self.is_running_macro() {
             let stack_entry = self
                 .macro_stack
-                .get(0)
+                .front()
                 .expect("if `is_running_macro()`, get first stack entry");
             real_bindings = &stack_entry.0;
         };
         let expression_solver = ExpressionSolver::new(real_bindings);
         let mut solved = Vec::new();
         for expression in exprs {
-            let value = expression_solver
-                .solve(&expression)
-                .map_err(|symbol_name| RuntimeError::SymbolNotFound {
-                    location: *self
+            let value = expression_solver.solve(expression).map_err(|symbol_name| {
+                RuntimeError::SymbolNotFound {
+                    location: self
                         .location
                         .expect("after `apply_gates()`, the location of the statement"),
                     symbol_name,
                     expected: QasmType::RealValue,
-                })?;
+                }
+            })?;
             solved.push(value);
         }
         Ok(solved)
@@ -289,7 +445,7 @@ impl<'src, 'program> Runtime<'program> {
         let expanded_arguments =
             self.expand_arguments(&args)
                 .map_err(|sizes| RuntimeError::RegisterSizeMismatch {
-                    location: *self
+                    location: self
                         .location
                         .expect("after `apply_gates()`, the location of the statement"),
                     symbol_name: "measure".into(),
@@ -309,6 +465,9 @@ impl<'src, 'program> Runtime<'program> {
         let measurement = self.statevector.measure(source) as u64;
 
         let target = self.bit_mapping(&args[1])?;
+        self.notify(|observer| {
+            observer.on_measure(source, classical_register_name, target, measurement)
+        });
         let value = measurement * (1 << target);
         let prev_value = *(self
             .memory
@@ -320,7 +479,7 @@ impl<'src, 'program> Runtime<'program> {
         Ok(())
     }
 
-    fn apply_one_gate(
+    pub(super) fn apply_one_gate(
         &mut self,
         name: &str,
         real_args: &[f64],
@@ -332,16 +491,27 @@ impl<'src, 'program> Runtime<'program> {
                 let phi = real_args[1];
                 let lambda = real_args[2];
                 let target = self.bit_mapping(&args[0])?;
+                self.notify(|observer| observer.on_gate(name, &[target], real_args));
                 self.statevector.u(theta, phi, lambda, target);
             }
             "CX" => {
                 let control = self.bit_mapping(&args[0])?;
                 let target = self.bit_mapping(&args[1])?;
+                self.notify(|observer| observer.on_gate(name, &[control, target], real_args));
                 self.statevector.cnot(control, target);
             }
             macro_name => {
                 let binding_mappings = self.bind(macro_name.to_owned(), real_args, args)?;
-                self.call(macro_name.to_owned(), binding_mappings)?;
+                if self.observer.is_some() {
+                    let qubits = args
+                        .iter()
+                        .map(|argument| self.bit_mapping(argument))
+                        .collect::<Result<Vec<usize>>>()?;
+                    self.notify(|observer| observer.on_macro_enter(macro_name, &qubits, real_args));
+                }
+                let result = self.call(macro_name.to_owned(), binding_mappings);
+                self.notify(|observer| observer.on_macro_exit(macro_name));
+                result?;
             }
         };
         Ok(())
@@ -365,7 +535,7 @@ impl<'src, 'program> Runtime<'program> {
     fn assert_is_quantum_register(&self, name: &str) -> Result<()> {
         if !self.is_register_of_type(RegisterType::Q, name)? {
             Err(RuntimeError::TypeMismatch {
-                location: *self
+                location: self
                     .location
                     .expect("after `apply_gates()`, the location of the statement"),
                 symbol_name: name.into(),
@@ -379,7 +549,7 @@ impl<'src, 'program> Runtime<'program> {
     fn assert_is_classical_register(&self, name: &str) -> Result<()> {
         if !self.is_register_of_type(RegisterType::C, name)? {
             Err(RuntimeError::TypeMismatch {
-                location: *self
+                location: self
                     .location
                     .expect("after `apply_gates()`, the location of the statement"),
                 symbol_name: name.into(),
@@ -394,7 +564,7 @@ impl<'src, 'program> Runtime<'program> {
         match self.semantics.register_table.get(name) {
             Some(entry) => Ok(entry.1 == rtype),
             None => Err(RuntimeError::SymbolNotFound {
-                location: *self
+                location: self
                     .location
                     .expect("after `apply_gates()`, the location of the statement"),
                 symbol_name: name.into(),
@@ -427,7 +597,7 @@ impl<'src, 'program> Runtime<'program> {
         match argument {
             ast::Argument::Item(name, index) => match self.semantics.memory_map.get(name) {
                 None => Err(RuntimeError::SymbolNotFound {
-                    location: *self
+                    location: self
                         .location
                         .expect("after `apply_gates()`, location of the statement"),
                     symbol_name: name.into(),
@@ -437,7 +607,7 @@ impl<'src, 'program> Runtime<'program> {
                     let size = mapping.2 - mapping.1 + 1;
                     if *index >= size {
                         return Err(RuntimeError::IndexOutOfBounds {
-                            location: *self
+                            location: self
                                 .location
                                 .expect("after `apply_gates()`, location of the statement"),
                             symbol_name: name.into(),
@@ -460,10 +630,7 @@ impl<'src, 'program> Runtime<'program> {
 
         let whole_registers: Vec<&ast::Argument> = args
             .iter()
-            .filter(|arg| match arg {
-                ast::Argument::Id(_) => true,
-                _ => false,
-            })
+            .filter(|arg| matches!(arg, ast::Argument::Id(_)))
             .collect();
 
         // Return a one-iteration range, `specify()` takes care of ignoring Item arugments.
@@ -505,8 +672,8 @@ impl<'src, 'program> Runtime<'program> {
         result
     }
 
-    fn bind(
-        &mut self,
+    pub(super) fn bind(
+        &self,
         macro_name: String,
         real_args: &[f64],
         args: &[ast::Argument],
@@ -514,7 +681,7 @@ impl<'src, 'program> Runtime<'program> {
         let definition = match self.semantics.macro_definitions.get(&macro_name) {
             None => {
                 return Err(RuntimeError::UndefinedGate {
-                    location: *self
+                    location: self
                         .location
                         .expect("after `apply_gates()`, the location of the statement"),
                     symbol_name: macro_name,
@@ -526,7 +693,7 @@ impl<'src, 'program> Runtime<'program> {
         if real_args.len() != definition.1.len() {
             return Err(RuntimeError::WrongNumberOfParameters {
                 are_registers: false,
-                location: *self
+                location: self
                     .location
                     .expect("after `apply_gates()`, the location of the statement"),
                 symbol_name: macro_name,
@@ -545,7 +712,7 @@ impl<'src, 'program> Runtime<'program> {
         if args.len() != definition.2.len() {
             return Err(RuntimeError::WrongNumberOfParameters {
                 are_registers: true,
-                location: *self
+                location: self
                     .location
                     .expect("after `apply_gates()`, the location of the statement"),
                 symbol_name: macro_name,
@@ -567,13 +734,53 @@ impl<'src, 'program> Runtime<'program> {
     fn call(&mut self, macro_name: String, bindings: BindingMappings) -> Result<()> {
         // XXX: Why clonning is necessary??
         let definition = (*self.semantics.macro_definitions.get(&macro_name).unwrap()).clone();
+        self.enter_macro(bindings);
+        let result = self.apply_gate_operations(&definition.3);
+        self.exit_macro();
+        result
+    }
+
+    pub(super) fn enter_macro(&mut self, bindings: BindingMappings) {
         self.macro_stack.push_front(bindings);
-        self.apply_gate_operations(&definition.3)?;
+    }
+
+    pub(super) fn exit_macro(&mut self) {
         self.macro_stack.pop_front();
-        Ok(())
     }
 }
 
+/// Check the parsed `program` for semantic errors without simulating it.
+///
+/// # Errors
+///
+/// Unlike [`simulate()`], which fails with the first error it runs into,
+/// the function returns all the errors in `program`, including those in gate
+/// bodies and in operations under conditionals. Errors in gate bodies are
+/// located at the gate declaration.
+///
+/// [`simulate()`]: ./fn.simulate.html
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::{check, parse_and_link};
+///
+/// let program = parse_and_link(r#"
+///     OPENQASM 2.0;
+///     qreg q[2];
+///     creg c[2];
+///     if (c==1) U(0, 0, 0) q[2];
+///     undefined q;
+/// "#)?;
+/// let errors = check(&program).expect_err("an index out of bounds and an undefined gate");
+/// assert_eq!(errors.len(), 2);
+/// # use qasmsim::QasmSimError;
+/// # Ok::<(), QasmSimError>(())
+/// ```
+pub fn check(program: &ast::OpenQasmProgram) -> std::result::Result<(), Vec<RuntimeError>> {
+    check_semantics(program).map(|_| ())
+}
+
 /// Perform a simulation of the parsed `program`.
 ///
 /// # Errors
@@ -581,10 +788,14 @@ impl<'src, 'program> Runtime<'program> {
 /// Simulate can fail during runtime returning an `Err` variant with a value
 /// of the [`RuntimeError`] type. `RuntimeError` is a sourceless error. It
 /// can be related to a source code and converted into a more useful
-/// [`QasmSimError`] value.
+/// [`QasmSimError`] value. Programs declaring more than [`MAX_QUBITS`]
+/// qubits fail with [`RuntimeError::TooManyQubits`] before allocating the
+/// state-vector.
 ///
 /// [`QasmSimError`]: ./error/enum.QasmSimError.html
 /// [`RuntimeError`]: ./error/enum.RuntimeError.html
+/// [`MAX_QUBITS`]: ./constant.MAX_QUBITS.html
+/// [`RuntimeError::TooManyQubits`]: ./error/enum.RuntimeError.html#variant.TooManyQubits
 ///
 /// # Examples
 ///
@@ -616,10 +827,170 @@ impl<'src, 'program> Runtime<'program> {
 ///
 /// [`parse_and_link()`]: ./fn.parse_and_link.html
 pub fn simulate(program: &ast::OpenQasmProgram) -> Result<Computation> {
-    let semantics = extract_semantics(program)?;
-    let mut runtime = Runtime::new(semantics);
+    simulate_with(program, SimulationOptions::new())
+}
+
+/// Perform a simulation of the parsed `program` starting from the
+/// `initial_state` instead of |0⟩.
+///
+/// # Errors
+///
+/// The function fails in the same cases [`simulate_with()`] does.
+///
+/// [`simulate_with()`]: ./fn.simulate_with.html
+///
+/// # Examples
+///
+/// ```
+/// # use qasmsim::grammar::ast::OpenQasmProgram;
+/// # use qasmsim::parse_and_link;
+/// use qasmsim::simulate_from;
+/// use qasmsim::statevector::{Complex, StateVector};
+///
+/// # fn get_program_ast() -> OpenQasmProgram {
+/// #     let source = r#"
+/// #     OPENQASM 2.0;
+/// #     include "qelib1.inc";
+/// #     qreg q[1];
+/// #     x q[0];
+/// #     "#;
+/// #     parse_and_link(source).unwrap()
+/// # }
+///
+/// let one = StateVector::from_complex_bases(vec![Complex::from(0.0), Complex::from(1.0)]);
+/// let program = get_program_ast();
+/// let computation = simulate_from(&program, one)?;
+/// assert!((computation.probabilities()[0] - 1.0).abs() < 1e-9);
+/// # use qasmsim::error::RuntimeError;
+/// # Ok::<(), RuntimeError>(())
+/// ```
+pub fn simulate_from(
+    program: &ast::OpenQasmProgram,
+    initial_state: StateVector,
+) -> Result<Computation> {
+    simulate_with(
+        program,
+        SimulationOptions::new().with_initial_state(initial_state),
+    )
+}
+
+/// Options of a simulation: the number of shots, the state to start from and
+/// the [`Observer`] to notify as the simulation progresses. By default, the
+/// program runs once, starting from |0⟩, and nobody is notified.
+///
+/// [`Observer`]: ./trait.Observer.html
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::{parse_and_link, simulate_with, SimulationOptions};
+/// use qasmsim::statevector::{Complex, StateVector};
+///
+/// let program = parse_and_link(r#"
+/// OPENQASM 2.0;
+/// include "qelib1.inc";
+/// qreg q[1];
+/// creg c[1];
+/// measure q -> c;
+/// "#)?;
+/// let one = StateVector::from_complex_bases(vec![Complex::from(0.0), Complex::from(1.0)]);
+/// let options = SimulationOptions::new()
+///     .with_shots(10)
+///     .with_initial_state(one);
+/// let computation = simulate_with(&program, options)?;
+/// assert_eq!(computation.histogram().as_ref().unwrap()["c"], vec![(1, 10)]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Default)]
+pub struct SimulationOptions<'obs> {
+    shots: Option<usize>,
+    initial_state: Option<StateVector>,
+    observer: Option<&'obs mut dyn Observer>,
+}
+
+impl fmt::Debug for SimulationOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimulationOptions")
+            .field("shots", &self.shots)
+            .field("initial_state", &self.initial_state)
+            .field("observed", &self.observer.is_some())
+            .finish()
+    }
+}
+
+impl<'obs> SimulationOptions<'obs> {
+    /// Create new options for running the program once from |0⟩.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Run the program `shots` times, collecting the histogram of the
+    /// classical memory.
+    pub fn with_shots(mut self, shots: usize) -> Self {
+        self.shots = Some(shots);
+        self
+    }
+
+    /// Start the simulation from `initial_state` instead of |0⟩.
+    pub fn with_initial_state(mut self, initial_state: StateVector) -> Self {
+        self.initial_state = Some(initial_state);
+        self
+    }
+
+    /// Notify `observer` as the simulation progresses.
+    pub fn with_observer(mut self, observer: &'obs mut dyn Observer) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+}
+
+/// Perform a simulation of the parsed `program` with the given `options`.
+/// The rest of `simulate*()` functions are particular cases of this one,
+/// which is the only one accepting an [`Observer`].
+///
+/// [`Observer`]: ./trait.Observer.html
+///
+/// # Errors
+///
+/// Besides the errors [`simulate()`] can return, the function fails with
+/// [`RuntimeError::InitialStateWidthMismatch`] if the number of qubits of
+/// the initial state differs from the number of qubits declared in
+/// `program`, and with [`RuntimeError::InitialStateNotNormalized`] if the
+/// initial state is not a unit vector.
+///
+/// [`simulate()`]: ./fn.simulate.html
+/// [`RuntimeError::InitialStateWidthMismatch`]: ./error/enum.RuntimeError.html#variant.InitialStateWidthMismatch
+/// [`RuntimeError::InitialStateNotNormalized`]: ./error/enum.RuntimeError.html#variant.InitialStateNotNormalized
+///
+/// # Examples
+///
+/// See [`SimulationOptions`].
+///
+/// [`SimulationOptions`]: ./struct.SimulationOptions.html
+pub fn simulate_with(
+    program: &ast::OpenQasmProgram,
+    options: SimulationOptions,
+) -> Result<Computation> {
+    let semantics = check_semantics(program).map_err(|mut errors| errors.remove(0))?;
+    let mut runtime = match options.initial_state {
+        None => Runtime::new(semantics)?,
+        Some(initial_state) => Runtime::with_initial_state(semantics, initial_state)?,
+    };
+    if let Some(observer) = options.observer {
+        runtime.set_observer(observer);
+    }
+    match options.shots {
+        None => run_once(runtime, program),
+        Some(shots) => run_shots(runtime, program, shots),
+    }
+}
+
+fn run_once(mut runtime: Runtime, program: &ast::OpenQasmProgram) -> Result<Computation> {
+    let quantum_registers = runtime.semantics.quantum_registers();
     runtime.apply_gates(&program.program)?;
-    Ok(Computation::new(runtime.memory, runtime.statevector, None))
+    Ok(Computation::new(runtime.memory, runtime.statevector, None)
+        .with_quantum_registers(quantum_registers)
+        .with_snapshots(runtime.snapshots))
 }
 
 /// Perform `shots` number of simulations of the parsed proram `program`.
@@ -664,8 +1035,36 @@ pub fn simulate(program: &ast::OpenQasmProgram) -> Result<Computation> {
 ///
 /// [`parse_and_link()`]: ./fn.parse_and_link.html
 pub fn simulate_with_shots(program: &ast::OpenQasmProgram, shots: usize) -> Result<Computation> {
-    let semantics = extract_semantics(program)?;
-    let mut runtime = Runtime::new(semantics);
+    simulate_with(program, SimulationOptions::new().with_shots(shots))
+}
+
+/// Perform `shots` number of simulations of the parsed program `program`,
+/// all of them starting from the `initial_state` instead of |0⟩.
+///
+/// # Errors
+///
+/// The function fails in the same cases [`simulate_with()`] does.
+///
+/// [`simulate_with()`]: ./fn.simulate_with.html
+pub fn simulate_with_shots_from(
+    program: &ast::OpenQasmProgram,
+    shots: usize,
+    initial_state: StateVector,
+) -> Result<Computation> {
+    simulate_with(
+        program,
+        SimulationOptions::new()
+            .with_shots(shots)
+            .with_initial_state(initial_state),
+    )
+}
+
+fn run_shots(
+    mut runtime: Runtime,
+    program: &ast::OpenQasmProgram,
+    shots: usize,
+) -> Result<Computation> {
+    let quantum_registers = runtime.semantics.quantum_registers();
     let mut histogram_builder = HistogramBuilder::new();
     for _ in 0..shots {
         runtime.reset();
@@ -676,5 +1075,7 @@ pub fn simulate_with_shots(program: &ast::OpenQasmProgram, shots: usize) -> Resu
         runtime.memory,
         runtime.statevector,
         Some(histogram_builder.histogram()),
-    ))
+    )
+    .with_quantum_registers(quantum_registers)
+    .with_snapshots(runtime.snapshots))
 }
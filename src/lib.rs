@@ -1,5 +1,7 @@
 #![warn(missing_docs)]
 #![doc(html_root_url = "https://docs.rs/qasmsim/1.1.0")]
+// Errors keep the offending source line and file name for rendering them.
+#![allow(clippy::result_large_err)]
 //! The `qasmsim` library includes a
 //! [OPENQASM 2.0](https://github.com/Qiskit/openqasm/blob/master/spec-human/)
 //! parser and interpreter, along with a statevector simulator. Compiled with
@@ -35,29 +37,56 @@
 //! A QASM interpreter and quantum simulator in Rust.
 //!
 //! USAGE:
-//!     qasmsim [FLAGS] [OPTIONS] [source]
+//!     qasmsim [FLAGS] [OPTIONS] [source] [SUBCOMMAND]
 //!
 //! FLAGS:
 //!     -b, --binary           Prints the binary representation of the values
+//!         --bloch            Prints the Bloch vector of each qubit, per quantum register. Ignored if shots is set
+//!         --entropy          Prints the entanglement entropy and purity of each quantum register. Ignored if shots is set
 //!     -h, --help             Prints help information
 //!     -x, --hexadecimal      Prints the hexadecimal representation of the values
 //!     -i, --integer          Prints the interger representation of the values. Default option
-//!         --probabilities    Prints the probabilities vector of the simulation. Ignored if shots is set
-//!         --statevector      Prints the state vector of the simulation. Ignored if shots is set
-//!     -t, --times            Prints times measured for parsing and simulating
+//!         --optimize         Simulates the program after optimizing its circuit, and prints the gate count and depth
+//!                            before and after the optimization to stderr
+//!         --probabilities    Prints the probabilities vector of the simulation and its snapshots. Ignored if shots is set
+//!         --statevector      Prints the state vector of the simulation and its snapshots. Ignored if shots is set
+//!     -t, --times            Prints times measured for parsing and simulating, and the time spent in each gate
 //!     -V, --version          Prints version information
 //!     -v                     Verbosity of the output
 //!
 //! OPTIONS:
-//!         --out <out>        Output files prefix, print in the stdout if not present. The output format of each file is
-//!                            CSV. At most, three files are created with the names out.memory.csv, out.state.csv and
-//!                            out.times.csv
-//!         --shots <shots>    Specify the number of simulations
+//!         --error-format <error-format>        Format of the errors, either human or json. The json format prints an array
+//!                                              with all the errors [default: human]  [possible values: human, json]
+//!     -I, --include-path <include-paths>...    Adds a directory to the list of paths where to look for the libraries in
+//!                                              include directives, after the directory of the source file. Can be
+//!                                              repeated. The paths in the QASMSIM_PATH environment variable are looked up
+//!                                              after these
+//!         --initial-state <initial-state>      Starts the simulation from the given state instead of |0⟩. Either a
+//!                                              bitstring, with the most significant qubit first, or a CSV file with the
+//!                                              layout of out.state.csv
+//!         --out <out>                          Output files prefix, print in the stdout if not present. The output format
+//!                                              of each file is CSV. At most, six files are created with the names
+//!                                              out.memory.csv, out.state.csv, out.bloch.csv, out.entropy.csv,
+//!                                              out.times.csv and out.profile.csv, plus one out.snapshot.<label>.csv file
+//!                                              per snapshot, with the symbols of the label replaced by `_`
+//!         --shots <shots>                      Specify the number of simulations
 //!
 //! ARGS:
 //!     <source>    QASM program file, read from stdin if not present
+//!
+//! SUBCOMMANDS:
+//!     fmt          Formats the program, normalizing indentation, spacing and expressions, and keeping the comments
+//!     gates        Lists the available gates with their parameters and documentation
+//!     help         Prints this message or the help of the given subcommand(s)
+//!     lint         Checks the program without simulating it and warns about suspicious code
+//!     route        Maps the program onto the connectivity of a device, inserting swaps, and prints the routed program
+//!     stats        Reports the qubits, gates, depth and memory the program needs without simulating it
+//!     transpile    Inlines all the gate calls of the program down to a basis of gates and prints the flat program
 //! ```
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod analysis;
+
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg_attr(not(target_arch = "wasm32"), macro_use)]
 pub mod error;
@@ -68,14 +97,35 @@ pub mod grammar;
 #[cfg(not(target_arch = "wasm32"))]
 mod linker;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lint;
+
 #[cfg(not(target_arch = "wasm32"))]
 pub mod statevector;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod symbols;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod transpiler;
+
 #[cfg(not(target_arch = "wasm32"))]
 pub use crate::{
-    arch::native::{parse_and_link, run, simulate, simulate_with_shots, Execution, ExecutionTimes},
-    error::QasmSimError,
-    interpreter::{Computation, Histogram},
+    arch::native::{
+        check, gate_docs, list_gates, parse_and_link, parse_and_link_owned, parse_and_link_with,
+        parse_and_link_with_resolver, parse_and_link_with_source_map, run, run_from, run_owned,
+        run_with, simulate, simulate_from, simulate_with, simulate_with_shots,
+        simulate_with_shots_from, Execution, ExecutionTimes, GateInfo, GateKind,
+        SimulationOptions,
+    },
+    error::{OwnedQasmSimError, QasmSimError},
+    interpreter::{
+        runtime::MAX_QUBITS, Computation, Debugger, Histogram, MacroFrame, Observer, Stop,
+    },
+    linker::{
+        ChainedResolver, EmbeddedResolver, FilesystemResolver, IncludeResolver, LinkerOptions,
+        ResolvedLibrary, QASMSIM_PATH,
+    },
     semantics::QasmType,
 };
 
@@ -100,4 +150,4 @@ mod arch;
 mod complex;
 mod interpreter;
 mod qe;
-mod semantics;
\ No newline at end of file
+mod semantics;
@@ -1,14 +1,25 @@
 //! Contain utilities for combining multiple AST spread into several locations.
 //! The module is **unstable**.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use crate::error::ParseError;
 use crate::grammar::ast;
 use crate::grammar::lexer::Location;
-use crate::grammar::parse_library;
+#[cfg(feature = "serde")]
+use crate::grammar::lexer::{LexicalError, Tok};
+use crate::grammar::parse_library_source;
+use crate::grammar::source_map::SourceMap;
 
 /// Represent a filure during linkage.
 ///
@@ -28,12 +39,12 @@ use crate::grammar::parse_library;
 /// use qasmsim::linker::LinkerError;
 ///
 /// LinkerError::LibraryNotFound {
-///     location: Location(14),
+///     location: Location::new_at(14),
 ///     libpath: "qlib.inc".to_string()
 /// }
 /// ```
 #[non_exhaustive]
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum LinkerError {
     /// The `include` directive at `location` failed while importing `libpath`.
@@ -43,79 +54,646 @@ pub enum LinkerError {
         /// Library path passed to the `include` directive.
         libpath: String,
     },
+    /// The `include` directive at `location` found the library `libpath` but
+    /// failed to read it.
+    LibraryUnreadable {
+        /// Location of the `include` directive.
+        location: Location,
+        /// Library path passed to the `include` directive.
+        libpath: String,
+        /// Description of the failure.
+        reason: String,
+    },
+    /// The `include` directive at `location` led to a library including
+    /// itself, directly or through other libraries.
+    IncludeCycle {
+        /// Location of the `include` directive in the program.
+        location: Location,
+        /// Library paths in the order they were included, starting with the
+        /// path passed to the directive and ending with the path closing the
+        /// cycle.
+        chain: Vec<String>,
+    },
+    /// The library `libpath`, included by the directive at `location`,
+    /// contains a syntax error.
+    LibrarySyntaxError {
+        /// Location of the `include` directive in the program.
+        location: Location,
+        /// Path of the library with the syntax error.
+        libpath: String,
+        /// Source code of the library.
+        source: String,
+        /// The syntax error, located in `source`.
+        #[cfg_attr(feature = "serde", serde(with = "boxed_parse_error"))]
+        error: Box<ParseError>,
+    },
+}
+
+impl LinkerError {
+    /// Return the stable code of the error. The code is kept when converting
+    /// the error into a [`QasmSimError`].
+    ///
+    /// [`QasmSimError`]: ../error/enum.QasmSimError.html
+    pub fn code(&self) -> &'static str {
+        match self {
+            LinkerError::LibraryNotFound { .. } => "E0301",
+            LinkerError::IncludeCycle { .. } => "E0302",
+            LinkerError::LibrarySyntaxError { .. } => "E0303",
+            LinkerError::LibraryUnreadable { .. } => "E0304",
+        }
+    }
+}
+
+impl Hash for LinkerError {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            LinkerError::LibraryNotFound { location, libpath } => {
+                location.hash(state);
+                libpath.hash(state);
+            }
+            LinkerError::LibraryUnreadable {
+                location,
+                libpath,
+                reason,
+            } => {
+                location.hash(state);
+                libpath.hash(state);
+                reason.hash(state);
+            }
+            LinkerError::IncludeCycle { location, chain } => {
+                location.hash(state);
+                chain.hash(state);
+            }
+            // The syntax error is determined by the source of the library.
+            LinkerError::LibrarySyntaxError {
+                location,
+                libpath,
+                source,
+                ..
+            } => {
+                location.hash(state);
+                libpath.hash(state);
+                source.hash(state);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "ParseError")]
+enum ParseErrorDef {
+    InvalidToken {
+        location: Location,
+    },
+    UnrecognizedEOF {
+        location: Location,
+        expected: Vec<String>,
+    },
+    UnrecognizedToken {
+        token: (Location, Tok, Location),
+        expected: Vec<String>,
+    },
+    ExtraToken {
+        token: (Location, Tok, Location),
+    },
+    User {
+        error: LexicalError<Location>,
+    },
+}
+
+#[cfg(feature = "serde")]
+mod boxed_parse_error {
+    use serde::{Deserializer, Serializer};
+
+    use super::{ParseError, ParseErrorDef};
+
+    pub fn serialize<S: Serializer>(error: &ParseError, serializer: S) -> Result<S::Ok, S::Error> {
+        ParseErrorDef::serialize(error, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Box<ParseError>, D::Error> {
+        ParseErrorDef::deserialize(deserializer).map(Box::new)
+    }
+}
+
+/// Name of the environment variable with additional search paths for
+/// libraries.
+pub const QASMSIM_PATH: &str = "QASMSIM_PATH";
+
+/// Configure where the linker looks for the libraries in `include`
+/// directives.
+///
+/// A library is looked for, in order, in the embedded libraries (such as
+/// `qelib1.inc`), relative to the directory of the including file, and in
+/// each of the search paths. Embedded libraries keep the priority unless
+/// [`override_embedded()`] is set.
+///
+/// [`override_embedded()`]: #method.override_embedded
+///
+/// # Examples
+///
+/// ```no_run
+/// use qasmsim::{parse_and_link_with, LinkerOptions};
+///
+/// let options = LinkerOptions::new()
+///     .with_base_dir("circuits")
+///     .with_include_path("/usr/share/qasm")
+///     .with_env_include_paths();
+/// let program = parse_and_link_with(r#"
+/// OPENQASM 2.0;
+/// include "mylib.inc";
+/// "#, &options)?;
+/// # use qasmsim::QasmSimError;
+/// # Ok::<(), QasmSimError>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct LinkerOptions {
+    base_dir: Option<PathBuf>,
+    include_paths: Vec<PathBuf>,
+    override_embedded: bool,
+}
+
+impl LinkerOptions {
+    /// Create new options which only resolve embedded libraries.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Resolve relative library paths against `base_dir`, usually the
+    /// directory of the including file.
+    pub fn with_base_dir<P: Into<PathBuf>>(mut self, base_dir: P) -> Self {
+        self.base_dir = Some(base_dir.into());
+        self
+    }
+
+    /// Add `path` to the end of the list of search paths.
+    pub fn with_include_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.include_paths.push(path.into());
+        self
+    }
+
+    /// Add the paths in the `QASMSIM_PATH` environment variable to the end
+    /// of the list of search paths. The variable follows the conventions of
+    /// the `PATH` variable of the platform.
+    pub fn with_env_include_paths(mut self) -> Self {
+        if let Some(paths) = env::var_os(QASMSIM_PATH) {
+            self.include_paths.extend(env::split_paths(&paths));
+        }
+        self
+    }
+
+    /// Look for libraries in the filesystem before looking into the embedded
+    /// libraries.
+    pub fn override_embedded(mut self, override_embedded: bool) -> Self {
+        self.override_embedded = override_embedded;
+        self
+    }
+
+    /// Return the directory to resolve relative library paths against.
+    pub fn base_dir(&self) -> Option<&Path> {
+        self.base_dir.as_deref()
+    }
+
+    /// Return the list of search paths.
+    pub fn include_paths(&self) -> &[PathBuf] {
+        &self.include_paths
+    }
+}
+
+/// Provide the source code of the libraries in `include` directives.
+///
+/// The [`Linker`] consults a resolver for every `include` directive. Provided
+/// resolvers look into a map of embedded sources ([`EmbeddedResolver`]), into
+/// the filesystem ([`FilesystemResolver`]) or into a list of other resolvers
+/// ([`ChainedResolver`]). Implement the trait for serving libraries from any
+/// other place.
+///
+/// [`Linker`]: ./struct.Linker.html
+/// [`EmbeddedResolver`]: ./struct.EmbeddedResolver.html
+/// [`FilesystemResolver`]: ./struct.FilesystemResolver.html
+/// [`ChainedResolver`]: ./struct.ChainedResolver.html
+///
+/// # Examples
+///
+/// ```
+/// use std::io;
+/// use std::path::Path;
+///
+/// use qasmsim::{parse_and_link_with_resolver, IncludeResolver, ResolvedLibrary};
+///
+/// struct Identities;
+///
+/// impl IncludeResolver for Identities {
+///     fn resolve(
+///         &self,
+///         libpath: &str,
+///         _directory: Option<&Path>,
+///     ) -> io::Result<Option<ResolvedLibrary>> {
+///         let library = libpath.strip_suffix(".inc").map(|name| ResolvedLibrary {
+///             path: libpath.to_string(),
+///             directory: None,
+///             source: format!("gate {} q {{ }}", name),
+///         });
+///         Ok(library)
+///     }
+/// }
+///
+/// let program = parse_and_link_with_resolver(r#"
+/// OPENQASM 2.0;
+/// include "nop.inc";
+/// qreg q[1];
+/// nop q[0];
+/// "#, Identities)?;
+/// # use qasmsim::QasmSimError;
+/// # Ok::<(), QasmSimError>(())
+/// ```
+pub trait IncludeResolver {
+    /// Return the library at `libpath`, or `None` if the resolver does not
+    /// know about it. `directory` is the [`ResolvedLibrary::directory`] of
+    /// the library with the `include` directive, if any, and relative paths
+    /// are looked for there first.
+    ///
+    /// [`ResolvedLibrary::directory`]: ./struct.ResolvedLibrary.html#structfield.directory
+    ///
+    /// # Errors
+    ///
+    /// The function fails if the resolver knows about the library but cannot
+    /// read it.
+    fn resolve(
+        &self,
+        libpath: &str,
+        directory: Option<&Path>,
+    ) -> io::Result<Option<ResolvedLibrary>>;
+}
+
+impl<R: IncludeResolver + ?Sized> IncludeResolver for Box<R> {
+    fn resolve(
+        &self,
+        libpath: &str,
+        directory: Option<&Path>,
+    ) -> io::Result<Option<ResolvedLibrary>> {
+        (**self).resolve(libpath, directory)
+    }
+}
+
+/// A library found by an [`IncludeResolver`].
+///
+/// [`IncludeResolver`]: ./trait.IncludeResolver.html
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResolvedLibrary {
+    /// Path identifying the library, so different `include` directives
+    /// referring to the same library include it only once.
+    pub path: String,
+    /// Directory to look for the libraries included by this one, if the
+    /// library lives in the filesystem.
+    pub directory: Option<PathBuf>,
+    /// Source code of the library.
+    pub source: String,
+}
+
+/// Resolve libraries from a map relating paths with their sources.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmbeddedResolver {
+    sources: HashMap<String, String>,
+}
+
+impl EmbeddedResolver {
+    /// Create a new resolver serving `sources`.
+    pub fn new(sources: HashMap<String, String>) -> Self {
+        EmbeddedResolver { sources }
+    }
+
+    /// Add the library `source` under `libpath`.
+    pub fn with_library<P: Into<String>, S: Into<String>>(mut self, libpath: P, source: S) -> Self {
+        self.sources.insert(libpath.into(), source.into());
+        self
+    }
+}
+
+impl IncludeResolver for EmbeddedResolver {
+    fn resolve(
+        &self,
+        libpath: &str,
+        _directory: Option<&Path>,
+    ) -> io::Result<Option<ResolvedLibrary>> {
+        Ok(self.sources.get(libpath).map(|source| ResolvedLibrary {
+            path: libpath.into(),
+            directory: None,
+            source: source.clone(),
+        }))
+    }
+}
+
+/// Resolve libraries by reading them from the filesystem.
+///
+/// Relative library paths are looked for in the directory of the including
+/// library, and then in each of the search directories, in order. Absolute
+/// paths are read as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FilesystemResolver {
+    directories: Vec<PathBuf>,
+}
+
+impl FilesystemResolver {
+    /// Create a new resolver looking into `directories`, in order.
+    pub fn new(directories: Vec<PathBuf>) -> Self {
+        FilesystemResolver { directories }
+    }
+
+    /// Return the list of search directories.
+    pub fn directories(&self) -> &[PathBuf] {
+        &self.directories
+    }
+}
+
+impl From<&LinkerOptions> for FilesystemResolver {
+    fn from(options: &LinkerOptions) -> Self {
+        FilesystemResolver::new(
+            options
+                .base_dir
+                .iter()
+                .chain(options.include_paths.iter())
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+impl IncludeResolver for FilesystemResolver {
+    fn resolve(
+        &self,
+        libpath: &str,
+        directory: Option<&Path>,
+    ) -> io::Result<Option<ResolvedLibrary>> {
+        let libpath = Path::new(libpath);
+        if libpath.is_absolute() {
+            return read_library(libpath);
+        }
+        let directories = directory
+            .into_iter()
+            .chain(self.directories.iter().map(PathBuf::as_path));
+        for directory in directories {
+            if let Some(library) = read_library(&directory.join(libpath))? {
+                return Ok(Some(library));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Return the library at `path`, or `None` if there is no such a file.
+/// Entries which are not files, like directories, do not shadow the libraries
+/// in later search paths.
+fn read_library(path: &Path) -> io::Result<Option<ResolvedLibrary>> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => (),
+        Ok(_) => return Ok(None),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    }
+    let source = fs::read_to_string(path)?;
+    let path = fs::canonicalize(path)?;
+    Ok(Some(ResolvedLibrary {
+        path: path.to_string_lossy().into_owned(),
+        directory: path.parent().map(Path::to_path_buf),
+        source,
+    }))
+}
+
+/// Resolve libraries with the first resolver, in a list, knowing about them.
+#[derive(Default)]
+pub struct ChainedResolver {
+    resolvers: Vec<Box<dyn IncludeResolver>>,
+}
+
+impl ChainedResolver {
+    /// Create a new resolver with an empty list of resolvers.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add `resolver` to the end of the list of resolvers.
+    pub fn with_resolver<R: IncludeResolver + 'static>(mut self, resolver: R) -> Self {
+        self.resolvers.push(Box::new(resolver));
+        self
+    }
+}
+
+impl fmt::Debug for ChainedResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChainedResolver")
+            .field("resolvers", &self.resolvers.len())
+            .finish()
+    }
+}
+
+impl IncludeResolver for ChainedResolver {
+    fn resolve(
+        &self,
+        libpath: &str,
+        directory: Option<&Path>,
+    ) -> io::Result<Option<ResolvedLibrary>> {
+        for resolver in &self.resolvers {
+            if let Some(library) = resolver.resolve(libpath, directory)? {
+                return Ok(Some(library));
+            }
+        }
+        Ok(None)
+    }
 }
 
-#[derive(Debug, Clone, Default)]
+/// Combine the ASTs of the included libraries with the AST of the program.
 pub struct Linker {
-    embedded: HashMap<String, String>,
+    resolver: Box<dyn IncludeResolver>,
+}
+
+impl fmt::Debug for Linker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Linker").finish()
+    }
+}
+
+impl Default for Linker {
+    fn default() -> Self {
+        Linker::new(EmbeddedResolver::default())
+    }
 }
 
 type Result<T> = std::result::Result<T, LinkerError>;
 
 impl Linker {
-    /// Create a new linker with a hashmap relating paths with embedded sources.
-    pub fn with_embedded(embedded: HashMap<String, String>) -> Self {
-        Linker { embedded }
+    /// Create a new linker consulting `resolver` for the included libraries.
+    pub fn new<R: IncludeResolver + 'static>(resolver: R) -> Self {
+        Linker {
+            resolver: Box::new(resolver),
+        }
+    }
+
+    /// Also look for libraries in the filesystem, as indicated by `options`.
+    /// The current resolver is consulted first unless
+    /// [`LinkerOptions::override_embedded()`] is set.
+    ///
+    /// [`LinkerOptions::override_embedded()`]: ./struct.LinkerOptions.html#method.override_embedded
+    pub fn with_options(self, options: LinkerOptions) -> Self {
+        let filesystem = FilesystemResolver::from(&options);
+        let resolver = if options.override_embedded {
+            ChainedResolver::new()
+                .with_resolver(filesystem)
+                .with_resolver(self.resolver)
+        } else {
+            ChainedResolver::new()
+                .with_resolver(self.resolver)
+                .with_resolver(filesystem)
+        };
+        Linker::new(resolver)
     }
 
     /// Look into `tree` for `include` statements, parse the referred libraries,
     /// and integrate their ASTs into `tree`, effectively modifying `tree`.
-    pub fn link(&self, mut tree: ast::OpenQasmProgram) -> Result<ast::OpenQasmProgram> {
+    /// The libraries are added to `source_map`, so the locations of the
+    /// statements coming from the libraries can be related to their sources.
+    ///
+    /// Libraries can include other libraries, with relative paths resolved
+    /// against the directory of the including library first. Each library is
+    /// included only once, the first time it appears, no matter the path used
+    /// to refer to it, and including a library from itself, directly or not,
+    /// is an error.
+    pub fn link(
+        &self,
+        mut tree: ast::OpenQasmProgram,
+        source_map: &mut SourceMap<'_>,
+    ) -> Result<ast::OpenQasmProgram> {
+        let mut included = HashSet::new();
         let mut to_embed = vec![];
         for (index, span) in tree.program.iter().enumerate() {
             if let ast::Statement::Include(libpath) = &*span.node {
-                let source = self
-                    .sources(&libpath)
-                    .map_err(|_| LinkerError::LibraryNotFound {
-                        location: span.boundaries.0,
-                        libpath: libpath.into(),
-                    })?;
-                let library_tree = parse_library(&source).unwrap();
-                to_embed.push((index, span.boundaries, library_tree.definitions));
+                let mut chain = vec![];
+                let definitions = self.definitions(
+                    libpath,
+                    None,
+                    span.boundaries.0,
+                    &mut chain,
+                    &mut included,
+                    source_map,
+                )?;
+                to_embed.push((index, definitions));
             }
         }
+        for trivia in &mut tree.trivia {
+            trivia.anchor = match trivia.anchor {
+                ast::TriviaAnchor::Statement(index) => {
+                    ast::TriviaAnchor::Statement(linked_index(&to_embed, index))
+                }
+                ast::TriviaAnchor::GateOperation(index, operation) => {
+                    ast::TriviaAnchor::GateOperation(linked_index(&to_embed, index), operation)
+                }
+                anchor => anchor,
+            };
+        }
         to_embed.reverse();
-        for (index, boundaries, statements) in to_embed {
-            let mut inner_spans = vec![];
-            for one_statement in statements {
-                inner_spans.push(ast::Span {
-                    boundaries,
-                    node: Box::new(one_statement),
-                })
-            }
-            tree.program.splice(index..=index, inner_spans);
+        for (index, definitions) in to_embed {
+            tree.program.splice(index..=index, definitions);
         }
         Ok(tree)
     }
 
-    fn sources(&self, libpath: &str) -> std::result::Result<String, ()> {
-        if self.embedded.contains_key(libpath) {
-            return Ok(self.embedded.get(libpath).unwrap().clone());
+    /// Return the definitions in the library at `libpath` with its own
+    /// includes already expanded. `directory` is the directory of the
+    /// including library, `location` is the location of the `include`
+    /// directive, `chain` is the list of libraries being expanded, with their
+    /// resolved and original paths, and `included` the set of resolved paths
+    /// of the libraries already included.
+    fn definitions(
+        &self,
+        libpath: &str,
+        directory: Option<&Path>,
+        location: Location,
+        chain: &mut Vec<(String, String)>,
+        included: &mut HashSet<String>,
+        source_map: &mut SourceMap<'_>,
+    ) -> Result<Vec<ast::Span<ast::Statement>>> {
+        let library = self
+            .resolver
+            .resolve(libpath, directory)
+            .map_err(|error| LinkerError::LibraryUnreadable {
+                location,
+                libpath: libpath.into(),
+                reason: error.to_string(),
+            })?
+            .ok_or_else(|| LinkerError::LibraryNotFound {
+                location,
+                libpath: libpath.into(),
+            })?;
+        if chain.iter().any(|(path, _)| *path == library.path) {
+            let mut cycle: Vec<String> = chain.iter().map(|(_, libpath)| libpath.clone()).collect();
+            cycle.push(libpath.into());
+            return Err(LinkerError::IncludeCycle {
+                location,
+                chain: cycle,
+            });
         }
-        Err(())
+        if !included.insert(library.path.clone()) {
+            return Ok(vec![]);
+        }
+        let file = source_map.add_file(libpath, library.source);
+        let source = source_map.source(file).expect("the file was just added");
+        let statements = parse_library_source(source, file).map_err(|error| {
+            LinkerError::LibrarySyntaxError {
+                location,
+                libpath: libpath.into(),
+                source: source.into(),
+                error: Box::new(error),
+            }
+        })?;
+
+        chain.push((library.path, libpath.into()));
+        let mut definitions = vec![];
+        for span in statements {
+            if let ast::Statement::Include(inner_libpath) = &*span.node {
+                definitions.extend(self.definitions(
+                    inner_libpath,
+                    library.directory.as_deref(),
+                    span.boundaries.0,
+                    chain,
+                    included,
+                    source_map,
+                )?);
+            } else {
+                definitions.push(span);
+            }
+        }
+        chain.pop();
+        Ok(definitions)
     }
 }
 
+/// Return the index of the statement at `index` once the `include` statements
+/// in `to_embed` are replaced by their definitions.
+fn linked_index(to_embed: &[(usize, Vec<ast::Span<ast::Statement>>)], index: usize) -> usize {
+    to_embed
+        .iter()
+        .filter(|(include, _)| *include < index)
+        .fold(index, |index, (_, definitions)| {
+            index + definitions.len() - 1
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use std::iter::FromIterator;
 
     use indoc::indoc;
 
-    use crate::grammar::{ast::Span, parse_program};
+    use crate::grammar::lexer::FileId;
+    use crate::grammar::{ast::Span, parse_program, parse_program_with_trivia};
     use crate::linker::Location;
 
     use super::*;
 
-    macro_rules! span {
-        ($left:expr, $node:expr, $right:expr) => {
-            Span {
-                boundaries: (Location($left), Location($right)),
-                node: Box::new($node),
-            }
-        };
-    }
-
     #[test]
     fn test_linker_loads_embedded_libraries() {
         let source = indoc!(
@@ -124,27 +702,369 @@ mod tests {
     include \"test.inc\";
     "
         );
-        let linker = Linker::with_embedded(HashMap::from_iter(vec![(
+        let linker = Linker::new(EmbeddedResolver::new(HashMap::from_iter(vec![(
             "test.inc".to_owned(),
             "gate test () q {}".to_owned(),
-        )]));
-        let tree = parse_program(&source).unwrap();
-        let linked_tree = linker.link(tree).unwrap();
+        )])));
+        let tree = parse_program(source).unwrap();
+        let mut source_map = SourceMap::new(source);
+        let linked_tree = linker.link(tree, &mut source_map).unwrap();
+        let library = source_map.file_id("test.inc").unwrap();
         assert_eq!(
             linked_tree,
             ast::OpenQasmProgram {
                 version: "2.0".to_owned(),
-                program: vec![span!(
-                    14,
-                    ast::Statement::GateDecl(
+                program: vec![Span {
+                    boundaries: (Location::new_in(library, 0), Location::new_in(library, 17)),
+                    node: Box::new(ast::Statement::GateDecl(
                         "test".to_owned(),
                         vec![],
                         vec!["q".to_string()],
-                        vec![]
-                    ),
-                    33
-                )]
+                        vec![],
+                        None
+                    ))
+                }],
+                trivia: vec![]
             }
-        )
+        );
+        assert_eq!(source_map.source(library), Some("gate test () q {}"));
+    }
+
+    #[test]
+    fn test_linker_keeps_trivia_anchored() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    include \"test.inc\";
+    // Registers
+    qreg q[1];
+    "
+        );
+        let linker = Linker::new(EmbeddedResolver::new(HashMap::from_iter(vec![(
+            "test.inc".to_owned(),
+            "gate a q {}\ngate b q {}".to_owned(),
+        )])));
+        let tree = parse_program_with_trivia(source).0.unwrap();
+        let mut source_map = SourceMap::new(source);
+        let linked_tree = linker.link(tree, &mut source_map).unwrap();
+        assert_eq!(
+            linked_tree.trivia,
+            vec![ast::Trivia {
+                kind: ast::TriviaKind::Comment("// Registers".to_owned()),
+                anchor: ast::TriviaAnchor::Statement(2)
+            }]
+        );
+    }
+
+    /// A temporary directory with libraries, removed when dropped.
+    struct LibraryDir(PathBuf);
+
+    impl LibraryDir {
+        fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for LibraryDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn library_dir(name: &str, files: &[(&str, &str)]) -> LibraryDir {
+        let directory =
+            env::temp_dir().join(format!("qasmsim-linker-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&directory).unwrap();
+        for (filename, contents) in files {
+            let path = directory.join(filename);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        LibraryDir(directory)
+    }
+
+    fn linked_gate_names(linker: &Linker, source: &str) -> Result<Vec<String>> {
+        let tree = parse_program(source).unwrap();
+        Ok(linker
+            .link(tree, &mut SourceMap::default())?
+            .program
+            .into_iter()
+            .filter_map(|span| match *span.node {
+                ast::Statement::GateDecl(name, ..) => Some(name),
+                _ => None,
+            })
+            .collect())
+    }
+
+    #[test]
+    fn test_linker_loads_libraries_from_base_dir_and_include_paths() {
+        let base_dir = library_dir("base", &[("base.inc", "gate base q {}")]);
+        let first = library_dir("first", &[("lib.inc", "gate first q {}")]);
+        let second = library_dir(
+            "second",
+            &[
+                ("lib.inc", "gate second q {}"),
+                ("other.inc", "gate other q {}"),
+            ],
+        );
+        let linker = Linker::default().with_options(
+            LinkerOptions::new()
+                .with_base_dir(base_dir.path())
+                .with_include_path(first.path())
+                .with_include_path(second.path()),
+        );
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    include \"base.inc\";
+    include \"lib.inc\";
+    include \"other.inc\";
+    "
+        );
+        assert_eq!(
+            linked_gate_names(&linker, source).unwrap(),
+            vec!["base", "first", "other"]
+        );
+    }
+
+    #[test]
+    fn test_linker_resolves_nested_includes_relative_to_the_including_library() {
+        let base_dir = library_dir(
+            "nested",
+            &[
+                ("sub/a.inc", "include \"b.inc\"; gate a q {}"),
+                ("sub/b.inc", "gate b q {}"),
+            ],
+        );
+        let linker =
+            Linker::default().with_options(LinkerOptions::new().with_base_dir(base_dir.path()));
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    include \"sub/a.inc\";
+    include \"sub/b.inc\";
+    "
+        );
+        assert_eq!(linked_gate_names(&linker, source).unwrap(), vec!["b", "a"]);
+    }
+
+    #[test]
+    fn test_linker_gives_priority_to_embedded_libraries() {
+        let directory = library_dir("override", &[("test.inc", "gate fs q {}")]);
+        let embedded = HashMap::from_iter(vec![(
+            "test.inc".to_owned(),
+            "gate embedded q {}".to_owned(),
+        )]);
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    include \"test.inc\";
+    "
+        );
+
+        let options = LinkerOptions::new().with_include_path(directory.path());
+        let linker =
+            Linker::new(EmbeddedResolver::new(embedded.clone())).with_options(options.clone());
+        assert_eq!(
+            linked_gate_names(&linker, source).unwrap(),
+            vec!["embedded"]
+        );
+
+        let linker = Linker::new(EmbeddedResolver::new(embedded))
+            .with_options(options.override_embedded(true));
+        assert_eq!(linked_gate_names(&linker, source).unwrap(), vec!["fs"]);
+    }
+
+    #[test]
+    fn test_linker_does_not_look_into_the_filesystem_by_default() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    include \"Cargo.toml\";
+    "
+        );
+        assert_eq!(
+            linked_gate_names(&Linker::default(), source),
+            Err(LinkerError::LibraryNotFound {
+                location: Location::new_at(14),
+                libpath: "Cargo.toml".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_linker_reports_libraries_it_cannot_read() {
+        let directory = library_dir("unreadable", &[]);
+        fs::write(directory.path().join("binary.inc"), [0xff, 0xfe, 0xfd]).unwrap();
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    include \"binary.inc\";
+    "
+        );
+        let linker =
+            Linker::default().with_options(LinkerOptions::new().with_base_dir(directory.path()));
+        assert!(matches!(
+            linked_gate_names(&linker, source),
+            Err(LinkerError::LibraryUnreadable { ref libpath, .. }) if libpath == "binary.inc"
+        ));
+    }
+
+    #[test]
+    fn test_linker_skips_directories_named_as_libraries() {
+        let base_dir = library_dir("shadowed", &[]);
+        fs::create_dir(base_dir.path().join("lib.inc")).unwrap();
+        let include_path = library_dir("shadowing", &[("lib.inc", "gate lib q {}")]);
+        let linker = Linker::default().with_options(
+            LinkerOptions::new()
+                .with_base_dir(base_dir.path())
+                .with_include_path(include_path.path()),
+        );
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    include \"lib.inc\";
+    "
+        );
+        assert_eq!(linked_gate_names(&linker, source).unwrap(), vec!["lib"]);
+    }
+
+    struct Prefixed(&'static str);
+
+    impl IncludeResolver for Prefixed {
+        fn resolve(
+            &self,
+            libpath: &str,
+            _directory: Option<&Path>,
+        ) -> io::Result<Option<ResolvedLibrary>> {
+            let library = libpath
+                .strip_prefix(self.0)
+                .and_then(|libpath| libpath.strip_suffix(".inc"))
+                .map(|name| ResolvedLibrary {
+                    path: libpath.to_owned(),
+                    directory: None,
+                    source: format!("gate {} q {{}}", name),
+                });
+            Ok(library)
+        }
+    }
+
+    fn resolved_source(resolver: &impl IncludeResolver, libpath: &str) -> Option<String> {
+        resolver
+            .resolve(libpath, None)
+            .unwrap()
+            .map(|library| library.source)
+    }
+
+    #[test]
+    fn test_chained_resolver_asks_resolvers_in_order() {
+        let resolver = ChainedResolver::new()
+            .with_resolver(
+                EmbeddedResolver::default().with_library("a_first.inc", "gate embedded q {}"),
+            )
+            .with_resolver(Prefixed("a_"))
+            .with_resolver(Prefixed("b_"));
+        assert_eq!(
+            resolved_source(&resolver, "a_first.inc"),
+            Some("gate embedded q {}".to_owned())
+        );
+        assert_eq!(
+            resolved_source(&resolver, "a_second.inc"),
+            Some("gate second q {}".to_owned())
+        );
+        assert_eq!(
+            resolved_source(&resolver, "b_third.inc"),
+            Some("gate third q {}".to_owned())
+        );
+        assert_eq!(resolved_source(&resolver, "c_fourth.inc"), None);
+    }
+
+    #[test]
+    fn test_linker_consults_custom_resolvers() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    include \"lib_custom.inc\";
+    include \"missing.inc\";
+    "
+        );
+        assert_eq!(
+            linked_gate_names(&Linker::new(Prefixed("lib_")), source),
+            Err(LinkerError::LibraryNotFound {
+                location: Location::new_at(40),
+                libpath: "missing.inc".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_linker_expands_nested_includes_once() {
+        let resolver = EmbeddedResolver::default()
+            .with_library("a.inc", "include \"b.inc\"; gate a q {} include \"c.inc\";")
+            .with_library("b.inc", "include \"c.inc\"; gate b q {}")
+            .with_library("c.inc", "gate c q {}");
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    include \"a.inc\";
+    include \"b.inc\";
+    "
+        );
+        assert_eq!(
+            linked_gate_names(&Linker::new(resolver), source).unwrap(),
+            vec!["c", "b", "a"]
+        );
+    }
+
+    #[test]
+    fn test_linker_detects_include_cycles() {
+        let resolver = EmbeddedResolver::default()
+            .with_library("a.inc", "include \"b.inc\"; gate a q {}")
+            .with_library("b.inc", "include \"c.inc\"; gate b q {}")
+            .with_library("c.inc", "include \"b.inc\"; gate c q {}");
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    include \"a.inc\";
+    "
+        );
+        assert_eq!(
+            linked_gate_names(&Linker::new(resolver), source),
+            Err(LinkerError::IncludeCycle {
+                location: Location::new_in(FileId(3), 0),
+                chain: vec![
+                    "a.inc".to_owned(),
+                    "b.inc".to_owned(),
+                    "c.inc".to_owned(),
+                    "b.inc".to_owned()
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_linker_reports_syntax_errors_in_libraries() {
+        let resolver = EmbeddedResolver::default()
+            .with_library("a.inc", "include \"b.inc\";")
+            .with_library("b.inc", "gate b q {");
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    include \"a.inc\";
+    "
+        );
+        let error = linked_gate_names(&Linker::new(resolver), source).unwrap_err();
+        assert!(matches!(
+            error,
+            LinkerError::LibrarySyntaxError {
+                location,
+                ref libpath,
+                ref source,
+                ref error,
+            } if location == Location::new_in(FileId(1), 0)
+                && libpath == "b.inc"
+                && source == "gate b q {"
+                && matches!(**error, ParseError::UnrecognizedEOF { .. })
+        ));
     }
 }
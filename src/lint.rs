@@ -0,0 +1,768 @@
+//! Contain the linter, which warns about suspicious but legal programs. The
+//! module is **unstable**.
+//!
+//! Each warning has a stable code, such as `W0001`, and a name, such as
+//! `unused-register`, that can be used for allowing or denying it in a
+//! [`LintConfig`].
+//!
+//! [`LintConfig`]: ./struct.LintConfig.html
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::diagnostic::columns;
+use crate::error::humanize::{humanize_with_severity, HumanDescription};
+use crate::error::{Diagnostic, Severity};
+use crate::grammar::ast;
+use crate::grammar::lexer::{FileId, Location};
+use crate::grammar::source_map::SourceMap;
+
+/// Each of the warnings the linter can emit.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LintCode {
+    /// A quantum or classical register is declared but never used.
+    UnusedRegister,
+    /// A qubit is operated after its final measurement.
+    GateAfterMeasurement,
+    /// A measurement overwrites a bit that has not been read since the
+    /// previous measurement.
+    RepeatedMeasurement,
+    /// A gate is declared in the program but never used.
+    UnusedGate,
+    /// An opaque gate is used, although opaque gates cannot be simulated.
+    OpaqueGate,
+}
+
+impl LintCode {
+    /// Return all the lint codes.
+    pub fn all() -> &'static [LintCode] {
+        &[
+            LintCode::UnusedRegister,
+            LintCode::GateAfterMeasurement,
+            LintCode::RepeatedMeasurement,
+            LintCode::UnusedGate,
+            LintCode::OpaqueGate,
+        ]
+    }
+
+    /// Return the stable code of the lint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qasmsim::lint::LintCode;
+    ///
+    /// assert_eq!(LintCode::UnusedRegister.code(), "W0001");
+    /// ```
+    pub fn code(self) -> &'static str {
+        match self {
+            LintCode::UnusedRegister => "W0001",
+            LintCode::GateAfterMeasurement => "W0002",
+            LintCode::RepeatedMeasurement => "W0003",
+            LintCode::UnusedGate => "W0004",
+            LintCode::OpaqueGate => "W0005",
+        }
+    }
+
+    /// Return the name of the lint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qasmsim::lint::LintCode;
+    ///
+    /// assert_eq!(LintCode::UnusedRegister.name(), "unused-register");
+    /// ```
+    pub fn name(self) -> &'static str {
+        match self {
+            LintCode::UnusedRegister => "unused-register",
+            LintCode::GateAfterMeasurement => "gate-after-measurement",
+            LintCode::RepeatedMeasurement => "repeated-measurement",
+            LintCode::UnusedGate => "unused-gate",
+            LintCode::OpaqueGate => "opaque-gate",
+        }
+    }
+
+    fn help(self) -> &'static str {
+        match self {
+            LintCode::UnusedRegister => "remove the declaration",
+            LintCode::GateAfterMeasurement => "the operation does not affect any measured result",
+            LintCode::RepeatedMeasurement => "the previous result is lost",
+            LintCode::UnusedGate => "remove the declaration",
+            LintCode::OpaqueGate => "opaque gates have no body to simulate",
+        }
+    }
+}
+
+impl fmt::Display for LintCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for LintCode {
+    type Err = String;
+
+    /// Parse either the code or the name of a lint.
+    fn from_str(code_or_name: &str) -> Result<Self, Self::Err> {
+        LintCode::all()
+            .iter()
+            .copied()
+            .find(|lint| {
+                lint.code().eq_ignore_ascii_case(code_or_name) || lint.name() == code_or_name
+            })
+            .ok_or_else(|| format!("unknown lint `{}`", code_or_name))
+    }
+}
+
+/// How the linter treats a lint.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Level {
+    /// The lint is not reported.
+    Allow,
+    /// The lint is reported as a warning.
+    Warn,
+    /// The lint is reported as an error.
+    Deny,
+}
+
+/// Set the level of each lint. Lints warn by default.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::lint::{Level, LintCode, LintConfig};
+///
+/// let config = LintConfig::new()
+///     .allow(LintCode::UnusedGate)
+///     .deny(LintCode::OpaqueGate);
+///
+/// assert_eq!(config.level(LintCode::UnusedGate), Level::Allow);
+/// assert_eq!(config.level(LintCode::UnusedRegister), Level::Warn);
+/// assert_eq!(config.level(LintCode::OpaqueGate), Level::Deny);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintConfig {
+    levels: HashMap<LintCode, Level>,
+}
+
+impl LintConfig {
+    /// Create a configuration where all the lints warn.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Do not report `code`.
+    pub fn allow(self, code: LintCode) -> Self {
+        self.with_level(code, Level::Allow)
+    }
+
+    /// Report `code` as a warning.
+    pub fn warn(self, code: LintCode) -> Self {
+        self.with_level(code, Level::Warn)
+    }
+
+    /// Report `code` as an error.
+    pub fn deny(self, code: LintCode) -> Self {
+        self.with_level(code, Level::Deny)
+    }
+
+    /// Set the level of `code`.
+    pub fn with_level(mut self, code: LintCode, level: Level) -> Self {
+        self.levels.insert(code, level);
+        self
+    }
+
+    /// Return the level of `code`.
+    pub fn level(&self, code: LintCode) -> Level {
+        self.levels.get(&code).copied().unwrap_or(Level::Warn)
+    }
+}
+
+/// A warning about a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Lint {
+    /// The kind of warning.
+    pub code: LintCode,
+    /// The level of the lint in the configuration used for linting.
+    pub level: Level,
+    /// Description of the warning.
+    pub message: String,
+    /// Location of the statement causing the warning.
+    pub location: Location,
+}
+
+/// Return the lints of `program` that are not allowed by `config`, in the
+/// order of the statements causing them.
+///
+/// The linter assumes the program is linked and free of errors, see
+/// [`check()`]. It does not fail with wrong programs but the lints could
+/// be inaccurate.
+///
+/// [`check()`]: ../fn.check.html
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::lint::{lint, LintCode, LintConfig};
+///
+/// let program = qasmsim::parse_and_link(r#"
+/// OPENQASM 2.0;
+/// include "qelib1.inc";
+/// qreg q[1];
+/// qreg unused[1];
+/// creg c[1];
+/// measure q -> c;
+/// h q;
+/// "#)?;
+///
+/// let lints = lint(&program, &LintConfig::new());
+/// assert_eq!(lints.len(), 2);
+/// assert_eq!(lints[0].code, LintCode::UnusedRegister);
+/// assert_eq!(lints[1].code, LintCode::GateAfterMeasurement);
+/// # use qasmsim::QasmSimError;
+/// # Ok::<(), QasmSimError>(())
+/// ```
+pub fn lint(program: &ast::OpenQasmProgram, config: &LintConfig) -> Vec<Lint> {
+    let mut linter = Linter::default();
+    linter.lint(&program.program);
+    let mut lints: Vec<Lint> = linter
+        .lints
+        .into_iter()
+        .filter_map(|(code, location, message)| match config.level(code) {
+            Level::Allow => None,
+            level => Some(Lint {
+                code,
+                level,
+                message,
+                location,
+            }),
+        })
+        .collect();
+    lints.sort_by_key(|lint| lint.location);
+    lints
+}
+
+#[derive(Debug, Clone, Default)]
+struct Linter {
+    registers: HashMap<String, usize>,
+    used_registers: HashSet<String>,
+    gates: HashMap<String, Vec<String>>,
+    opaque_gates: HashSet<String>,
+    measured_qubits: HashMap<(String, usize), usize>,
+    unread_bits: HashSet<(String, usize)>,
+    lints: Vec<(LintCode, Location, String)>,
+}
+
+impl Linter {
+    fn lint(&mut self, statements: &[ast::Span<ast::Statement>]) {
+        for span in statements {
+            if let ast::Statement::QRegDecl(name, size) | ast::Statement::CRegDecl(name, size) =
+                &*span.node
+            {
+                self.registers.insert(name.clone(), *size);
+            }
+        }
+
+        let mut operations = vec![];
+        for span in statements {
+            let location = span.boundaries.0;
+            match &*span.node {
+                ast::Statement::GateDecl(name, _, _, body, _) => {
+                    let callees = body
+                        .iter()
+                        .filter_map(|operation| match operation {
+                            ast::GateOperation::Unitary(ast::UnitaryOperation(name, _, _)) => {
+                                Some(name.clone())
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    self.gates.insert(name.clone(), callees);
+                }
+                ast::Statement::OpaqueGateDecl(name, ..) => {
+                    self.opaque_gates.insert(name.clone());
+                }
+                ast::Statement::Barrier(ast::BarrierPragma(args)) => {
+                    self.use_registers(args);
+                }
+                ast::Statement::QuantumOperation(operation) => {
+                    operations.push((location, operation));
+                    self.lint_operation(location, operation);
+                }
+                ast::Statement::Conditional(register, _, operation) => {
+                    self.use_registers(std::slice::from_ref(register));
+                    for bit in self.expand(register) {
+                        self.unread_bits.remove(&bit);
+                    }
+                    operations.push((location, operation));
+                    self.lint_operation(location, operation);
+                }
+                _ => (),
+            }
+        }
+
+        self.lint_gates_after_measurement(&operations);
+        self.lint_declarations(statements, &operations);
+    }
+
+    fn lint_operation(&mut self, location: Location, operation: &ast::QuantumOperation) {
+        match operation {
+            ast::QuantumOperation::Unitary(ast::UnitaryOperation(name, _, args)) => {
+                self.use_registers(args);
+                if self.opaque_gates.contains(name) {
+                    self.lints.push((
+                        LintCode::OpaqueGate,
+                        location,
+                        format!("opaque gate `{}` cannot be simulated", name),
+                    ));
+                }
+            }
+            ast::QuantumOperation::Measure(source, target) => {
+                self.use_registers(&[source.clone(), target.clone()]);
+                for bit in self.expand(target) {
+                    if !self.unread_bits.insert(bit.clone()) {
+                        self.lints.push((
+                            LintCode::RepeatedMeasurement,
+                            location,
+                            format!(
+                                "bit `{}[{}]` is measured again before being read",
+                                bit.0, bit.1
+                            ),
+                        ));
+                    }
+                }
+            }
+            ast::QuantumOperation::Reset(register) => {
+                self.use_registers(std::slice::from_ref(register));
+            }
+        }
+    }
+
+    /// Warn about the unitary operations happening after the final
+    /// measurement of a qubit, unless the qubit is reset in between.
+    fn lint_gates_after_measurement(&mut self, operations: &[(Location, &ast::QuantumOperation)]) {
+        for (index, (_, operation)) in operations.iter().enumerate() {
+            if let ast::QuantumOperation::Measure(qubits, _) = operation {
+                for qubit in self.expand(qubits) {
+                    self.measured_qubits.insert(qubit, index);
+                }
+            }
+        }
+
+        let mut measured: HashSet<(String, usize)> = HashSet::new();
+        for (index, (location, operation)) in operations.iter().enumerate() {
+            match operation {
+                ast::QuantumOperation::Measure(qubits, _) => {
+                    measured.extend(self.expand(qubits));
+                }
+                ast::QuantumOperation::Reset(qubits) => {
+                    for qubit in self.expand(qubits) {
+                        measured.remove(&qubit);
+                    }
+                }
+                ast::QuantumOperation::Unitary(ast::UnitaryOperation(_, _, args)) => {
+                    let late_qubit = args.iter().flat_map(|arg| self.expand(arg)).find(|qubit| {
+                        measured.contains(qubit) && self.measured_qubits[qubit] < index
+                    });
+                    if let Some((name, index)) = late_qubit {
+                        self.lints.push((
+                            LintCode::GateAfterMeasurement,
+                            *location,
+                            format!(
+                                "qubit `{}[{}]` is operated after its final measurement",
+                                name, index
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Warn about the registers and gates declared in the program but never
+    /// used.
+    fn lint_declarations(
+        &mut self,
+        statements: &[ast::Span<ast::Statement>],
+        operations: &[(Location, &ast::QuantumOperation)],
+    ) {
+        let mut used_gates = HashSet::new();
+        let mut pending: Vec<&String> = operations
+            .iter()
+            .filter_map(|(_, operation)| match operation {
+                ast::QuantumOperation::Unitary(ast::UnitaryOperation(name, _, _)) => Some(name),
+                _ => None,
+            })
+            .collect();
+        while let Some(name) = pending.pop() {
+            if used_gates.insert(name.clone()) {
+                if let Some(callees) = self.gates.get(name) {
+                    pending.extend(callees);
+                }
+            }
+        }
+
+        for span in statements {
+            let location = span.boundaries.0;
+            if location.file != FileId(0) {
+                continue;
+            }
+            match &*span.node {
+                ast::Statement::QRegDecl(name, _) | ast::Statement::CRegDecl(name, _)
+                    if !self.used_registers.contains(name) =>
+                {
+                    self.lints.push((
+                        LintCode::UnusedRegister,
+                        location,
+                        format!("register `{}` is never used", name),
+                    ));
+                }
+                ast::Statement::GateDecl(name, ..) | ast::Statement::OpaqueGateDecl(name, ..)
+                    if !used_gates.contains(name) =>
+                {
+                    self.lints.push((
+                        LintCode::UnusedGate,
+                        location,
+                        format!("gate `{}` is never used", name),
+                    ));
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn use_registers(&mut self, args: &[ast::Argument]) {
+        for arg in args {
+            let (ast::Argument::Id(name) | ast::Argument::Item(name, _)) = arg;
+            self.used_registers.insert(name.clone());
+        }
+    }
+
+    /// Return the bits or qubits referred by `arg`.
+    fn expand(&self, arg: &ast::Argument) -> Vec<(String, usize)> {
+        match arg {
+            ast::Argument::Id(name) => {
+                let size = self.registers.get(name).copied().unwrap_or(0);
+                (0..size).map(|index| (name.clone(), index)).collect()
+            }
+            ast::Argument::Item(name, index) => vec![(name.clone(), *index)],
+        }
+    }
+}
+
+/// A lint related to the source line where it happens, ready for being
+/// presented to the user.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::grammar::source_map::SourceMap;
+/// use qasmsim::lint::{lint, LintConfig, LintDiagnostic};
+///
+/// let source = "OPENQASM 2.0;\nqreg q[1];\n";
+/// let program = qasmsim::parse_and_link(source)?;
+/// let lints = lint(&program, &LintConfig::new());
+/// let diagnostic = LintDiagnostic::from((&SourceMap::new(source), lints[0].clone()));
+///
+/// assert_eq!(diagnostic.lineno, 2);
+/// assert_eq!(format!("{}", diagnostic), "\
+/// warning[W0001]: register `q` is never used
+///   |
+/// 2 | qreg q[1];
+///   | ^ help: remove the declaration
+/// ");
+/// # use qasmsim::QasmSimError;
+/// # Ok::<(), QasmSimError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LintDiagnostic<'src> {
+    /// The stable code of the lint, such as `W0001`.
+    pub code: String,
+    /// The name of the lint, such as `unused-register`.
+    pub name: String,
+    /// The level of the lint.
+    pub level: Level,
+    /// Description of the warning.
+    pub message: String,
+    /// Suggestion for fixing the warning.
+    pub help: Option<String>,
+    /// Line source.
+    pub source: Cow<'src, str>,
+    /// Name of the file containing the line, if it is not the program.
+    pub file: Option<String>,
+    /// Line number.
+    pub lineno: usize,
+    /// Position inside the line (0-based) where the statement starts.
+    pub startpos: usize,
+}
+
+impl<'src> From<(&SourceMap<'src>, Lint)> for LintDiagnostic<'src> {
+    fn from(map_and_lint: (&SourceMap<'src>, Lint)) -> Self {
+        let (source_map, lint) = map_and_lint;
+        let (source, lineno, startpos, _) = source_map.extract_line(lint.location, None);
+        LintDiagnostic {
+            code: lint.code.code().into(),
+            name: lint.code.name().into(),
+            level: lint.level,
+            message: lint.message,
+            help: Some(lint.code.help().into()),
+            source,
+            file: source_map.name(lint.location.file).map(String::from),
+            lineno,
+            startpos,
+        }
+    }
+}
+
+impl From<&LintDiagnostic<'_>> for Diagnostic {
+    fn from(lint: &LintDiagnostic<'_>) -> Self {
+        Diagnostic {
+            code: lint.code.clone(),
+            severity: match lint.level {
+                Level::Deny => Severity::Error,
+                _ => Severity::Warning,
+            },
+            message: lint.message.clone(),
+            hint: lint.help.clone(),
+            file: lint.file.clone(),
+            line: Some(lint.lineno),
+            columns: Some(columns(lint.startpos, None)),
+            related: vec![],
+        }
+    }
+}
+
+impl fmt::Display for LintDiagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.level {
+            Level::Deny => format!("error[{}]", self.code),
+            _ => format!("warning[{}]", self.code),
+        };
+        let description = HumanDescription {
+            msg: self.message.clone(),
+            file: self.file.clone(),
+            lineno: self.lineno,
+            startpos: self.startpos,
+            endpos: None,
+            linesrc: self.source.to_string(),
+            help: self.help.clone(),
+        };
+        let mut buffer = String::new();
+        humanize_with_severity(&mut buffer, &severity, &description)?;
+        write!(f, "{}", buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use crate::grammar::parse_program;
+
+    fn lint_codes(source: &str) -> Vec<(LintCode, usize)> {
+        lint(&parse_program(source).unwrap(), &LintConfig::new())
+            .into_iter()
+            .map(|lint| (lint.code, lint.location.offset))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_code_from_code_or_name() {
+        assert_eq!("W0003".parse(), Ok(LintCode::RepeatedMeasurement));
+        assert_eq!("w0003".parse(), Ok(LintCode::RepeatedMeasurement));
+        assert_eq!("unused-gate".parse(), Ok(LintCode::UnusedGate));
+        assert_eq!(
+            "W9999".parse::<LintCode>(),
+            Err("unknown lint `W9999`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_lint_codes_are_unique() {
+        let codes: HashSet<&str> = LintCode::all().iter().map(|code| code.code()).collect();
+        let names: HashSet<&str> = LintCode::all().iter().map(|code| code.name()).collect();
+        assert_eq!(codes.len(), LintCode::all().len());
+        assert_eq!(names.len(), LintCode::all().len());
+    }
+
+    #[test]
+    fn test_clean_program() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    gate h q { U(pi/2, 0, pi) q; }
+    qreg q[2];
+    creg c[2];
+    h q[0];
+    CX q[0], q[1];
+    measure q -> c;
+    if (c == 1) reset q;
+    h q;
+    measure q -> c;
+    "
+        );
+        assert_eq!(lint_codes(source), vec![]);
+    }
+
+    #[test]
+    fn test_unused_registers_and_gates() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    gate id q { }
+    gate x q { U(pi, 0, pi) q; }
+    gate unused q { x q; }
+    opaque gate magic q;
+    qreg q[1];
+    creg c[1];
+    qreg r[1];
+    id q[0];
+    "
+        );
+        assert_eq!(
+            lint_codes(source),
+            vec![
+                (LintCode::UnusedGate, 28),
+                (LintCode::UnusedGate, 57),
+                (LintCode::UnusedGate, 80),
+                (LintCode::UnusedRegister, 112),
+                (LintCode::UnusedRegister, 123)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gates_used_by_used_gates_are_used() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    gate x q { U(pi, 0, pi) q; }
+    gate xx q { x q; x q; }
+    qreg q[1];
+    xx q[0];
+    "
+        );
+        assert_eq!(lint_codes(source), vec![]);
+    }
+
+    #[test]
+    fn test_gate_after_final_measurement() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    qreg q[2];
+    creg c[2];
+    measure q[0] -> c[0];
+    U(0, 0, 0) q[1];
+    U(0, 0, 0) q;
+    reset q[1];
+    U(0, 0, 0) q[1];
+    "
+        );
+        assert_eq!(
+            lint_codes(source),
+            vec![(LintCode::GateAfterMeasurement, 75)]
+        );
+    }
+
+    #[test]
+    fn test_repeated_measurement() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    qreg q[2];
+    creg c[2];
+    measure q -> c;
+    if (c == 0) U(0, 0, 0) q;
+    measure q -> c;
+    measure q[1] -> c[1];
+    "
+        );
+        assert_eq!(
+            lint_codes(source),
+            vec![(LintCode::RepeatedMeasurement, 94)]
+        );
+    }
+
+    #[test]
+    fn test_opaque_gate() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    opaque gate magic q;
+    qreg q[1];
+    creg c[1];
+    magic q;
+    measure q[0] -> c[0];
+    "
+        );
+        assert_eq!(lint_codes(source), vec![(LintCode::OpaqueGate, 57)]);
+        let program = parse_program(source).unwrap();
+        assert_eq!(crate::check(&program), Ok(()));
+    }
+
+    #[test]
+    fn test_configured_levels() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    qreg q[1];
+    gate g a { U(0, 0, 0) a; }
+    "
+        );
+        let config = LintConfig::new()
+            .allow(LintCode::UnusedRegister)
+            .deny(LintCode::UnusedGate);
+        let lints = lint(&parse_program(source).unwrap(), &config);
+        assert_eq!(
+            lints,
+            vec![Lint {
+                code: LintCode::UnusedGate,
+                level: Level::Deny,
+                message: "gate `g` is never used".into(),
+                location: Location::new_at(25),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_denied_lints_render_as_errors() {
+        let source = "OPENQASM 2.0;\ncreg c[1];\n";
+        let lint = Lint {
+            code: LintCode::UnusedRegister,
+            level: Level::Deny,
+            message: "register `c` is never used".into(),
+            location: Location::new_at(14),
+        };
+        let diagnostic = LintDiagnostic::from((&SourceMap::new(source), lint));
+        assert_eq!(
+            format!("{}", diagnostic),
+            indoc!(
+                "
+    error[W0001]: register `c` is never used
+      |
+    2 | creg c[1];
+      | ^ help: remove the declaration
+    "
+            )
+        );
+        let diagnostic = Diagnostic::from(&diagnostic);
+        assert_eq!(diagnostic.code, "W0001");
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!((diagnostic.line, diagnostic.columns), (Some(2), Some(1..2)));
+    }
+}
@@ -35,8 +35,13 @@ gate sdg a { u1(-pi/2) a; }
 gate t a { u1(pi/4) a; }
 // C3 gate: conjugate of sqrt(S)
 gate tdg a { u1(-pi/4) a; }
+// sqrt(X) gate
+gate sx a { sdg a; h a; sdg a; }
+// conjugate of sqrt(X)
+gate sxdg a { s a; h a; s a; }
 
 // --- Standard rotations ---
+
 // Rotation around X-axis
 gate rx(theta) a { u3(theta, -pi/2,pi/2) a; }
 // rotation around Y-axis
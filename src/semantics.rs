@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::error;
 use std::fmt;
+use std::ops::Range;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -8,6 +9,10 @@ use serde::{Deserialize, Serialize};
 use crate::grammar::ast;
 use crate::grammar::lexer::Location;
 
+mod checker;
+
+pub use self::checker::check_semantics;
+
 /// The different types for OPENQASM values.
 #[non_exhaustive]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -59,18 +64,126 @@ pub enum SemanticError {
         /// Location of the original definition.
         previous_location: Location,
     },
+    /// Use of a gate before its definition.
+    UseBeforeDefinition {
+        /// Name of the gate.
+        symbol_name: String,
+        /// Location where the gate is used.
+        location: Location,
+        /// Location of the definition of the gate.
+        definition_location: Location,
+    },
+    /// A gate calling itself inside its body.
+    RecursiveGate {
+        /// Name of the gate.
+        symbol_name: String,
+        /// Location of the definition of the gate.
+        location: Location,
+    },
+    /// Use of a qubit not among the formal arguments inside a gate body.
+    UndeclaredGateArgument {
+        /// Name of the gate.
+        gate_name: String,
+        /// Name of the unknown argument.
+        symbol_name: String,
+        /// Location of the definition of the gate.
+        location: Location,
+    },
+    /// Use of a real value not among the formal parameters inside a gate
+    /// body.
+    UndeclaredGateParameter {
+        /// Name of the gate.
+        gate_name: String,
+        /// Name of the unknown parameter.
+        symbol_name: String,
+        /// Location of the definition of the gate.
+        location: Location,
+    },
+    /// Indexing of a formal argument inside a gate body.
+    IndexedGateArgument {
+        /// Name of the gate.
+        gate_name: String,
+        /// Name of the indexed argument.
+        symbol_name: String,
+        /// Index tried to access.
+        index: usize,
+        /// Location of the definition of the gate.
+        location: Location,
+    },
+    /// Declaration of a register with no elements.
+    EmptyRegister {
+        /// Name of the register.
+        symbol_name: String,
+        /// Location of the declaration.
+        location: Location,
+    },
+    /// Declaration of a classical register with more bits than supported.
+    RegisterTooLarge {
+        /// Name of the register.
+        symbol_name: String,
+        /// Size of the register.
+        size: usize,
+        /// Maximum size the register could have.
+        max_size: usize,
+        /// Location of the declaration.
+        location: Location,
+    },
+    /// A conditional testing the value of a quantum register.
+    QuantumCondition {
+        /// Name of the register.
+        symbol_name: String,
+        /// Location of the conditional.
+        location: Location,
+    },
+    /// A gate application passing the same qubit several times.
+    QubitAliasing {
+        /// Name of the gate.
+        gate_name: String,
+        /// Name of the register, or gate argument, passed several times.
+        symbol_name: String,
+        /// Location of the gate application.
+        location: Location,
+    },
+}
+
+impl SemanticError {
+    /// Return the stable code of the error. The code is kept when converting
+    /// the error into a [`QasmSimError`].
+    ///
+    /// [`QasmSimError`]: ../error/enum.QasmSimError.html
+    pub fn code(&self) -> &'static str {
+        match self {
+            SemanticError::RedefinitionError { .. } => "E0201",
+            SemanticError::UseBeforeDefinition { .. } => "E0202",
+            SemanticError::RecursiveGate { .. } => "E0203",
+            SemanticError::UndeclaredGateArgument { .. } => "E0204",
+            SemanticError::UndeclaredGateParameter { .. } => "E0205",
+            SemanticError::IndexedGateArgument { .. } => "E0206",
+            SemanticError::EmptyRegister { .. } => "E0207",
+            SemanticError::RegisterTooLarge { .. } => "E0208",
+            SemanticError::QuantumCondition { .. } => "E0209",
+            SemanticError::QubitAliasing { .. } => "E0210",
+        }
+    }
 }
 
 impl fmt::Display for SemanticError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let message = match self {
-            _ => match lazy_humanize!{
-                self,
-                SemanticError::RedefinitionError
-            } {
-                Some(message) => message,
-                None => unreachable!()
-            }
+        let message = match lazy_humanize! {
+            self,
+            SemanticError::RedefinitionError,
+            SemanticError::UseBeforeDefinition,
+            SemanticError::RecursiveGate,
+            SemanticError::UndeclaredGateArgument,
+            SemanticError::UndeclaredGateParameter,
+            SemanticError::IndexedGateArgument,
+            SemanticError::EmptyRegister,
+            SemanticError::RegisterTooLarge,
+            SemanticError::QuantumCondition,
+            SemanticError::QubitAliasing
+        } {
+            Some(message) => message,
+            None => unreachable!(),
         };
         write!(f, "{}", message)
     }
@@ -80,6 +193,10 @@ impl error::Error for SemanticError {}
 
 type Result<T> = std::result::Result<T, SemanticError>;
 
+/// Maximum size of a classical register, the bits of the `u64` holding its
+/// value in memory.
+const MAX_CLASSICAL_REGISTER_SIZE: usize = 64;
+
 /// Register name, type, size and definition location.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct RegisterEntry(pub String, pub RegisterType, pub usize, pub Location);
@@ -88,7 +205,8 @@ pub struct RegisterEntry(pub String, pub RegisterType, pub usize, pub Location);
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MemoryMapEntry(pub String, pub usize, pub usize);
 
-/// Macro name, real arguments, register arguments, list of statements and definition location.
+/// Macro name, real arguments, register arguments, list of statements,
+/// definition location and documentation.
 #[derive(Debug, Clone, PartialEq)]
 pub struct MacroDefinition(
     pub String,
@@ -96,6 +214,7 @@ pub struct MacroDefinition(
     pub Vec<String>,
     pub Vec<ast::GateOperation>,
     pub Location,
+    pub Option<String>,
 );
 
 #[derive(Debug, Clone, PartialEq, Default)]
@@ -109,6 +228,21 @@ pub struct Semantics {
     pub classical_memory_size: usize,
 }
 
+impl Semantics {
+    /// Return the range of qubits each quantum register occupies in the
+    /// unified quantum memory.
+    pub fn quantum_registers(&self) -> HashMap<String, Range<usize>> {
+        self.register_table
+            .values()
+            .filter(|entry| entry.1 == RegisterType::Q)
+            .map(|entry| {
+                let MemoryMapEntry(name, start, end) = &self.memory_map[&entry.0];
+                (name.clone(), *start..(*end + 1))
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Default)]
 struct SemanticsBuilder {
     semantics: Semantics,
@@ -128,6 +262,7 @@ impl SemanticsBuilder {
         location: Location,
     ) -> Result<()> {
         self.new_register(name.clone(), RegisterType::Q, size, location)?;
+        self.check_register_size(&name, RegisterType::Q, size, location)?;
         self.map_register(name.clone(), RegisterType::Q, size);
         self.semantics.quantum_memory_size += size;
         self.last_quantum_register = Some(name);
@@ -141,6 +276,7 @@ impl SemanticsBuilder {
         location: Location,
     ) -> Result<()> {
         self.new_register(name.clone(), RegisterType::C, size, location)?;
+        self.check_register_size(&name, RegisterType::C, size, location)?;
         self.map_register(name.clone(), RegisterType::C, size);
         self.semantics.classical_memory_size += size;
         self.last_classical_register = Some(name);
@@ -154,9 +290,10 @@ impl SemanticsBuilder {
         args: Vec<String>,
         body: Vec<ast::GateOperation>,
         location: Location,
+        doc: Option<String>,
     ) -> Result<()> {
         let entry = self.semantics.macro_definitions.get(&name);
-        if let Some(MacroDefinition(_, _, _, _, previous_location)) = entry {
+        if let Some(MacroDefinition(_, _, _, _, previous_location, _)) = entry {
             return Err(SemanticError::RedefinitionError {
                 symbol_name: name,
                 location,
@@ -166,7 +303,7 @@ impl SemanticsBuilder {
 
         self.semantics.macro_definitions.insert(
             name.clone(),
-            MacroDefinition(name, real_args, args, body, location),
+            MacroDefinition(name, real_args, args, body, location, doc),
         );
 
         Ok(())
@@ -195,6 +332,32 @@ impl SemanticsBuilder {
         Ok(())
     }
 
+    /// Check the size of a new register. Registers with a wrong size are
+    /// kept in the register table but not mapped into memory.
+    fn check_register_size(
+        &self,
+        name: &str,
+        kind: RegisterType,
+        size: usize,
+        location: Location,
+    ) -> Result<()> {
+        if size == 0 {
+            return Err(SemanticError::EmptyRegister {
+                symbol_name: name.into(),
+                location,
+            });
+        }
+        if kind == RegisterType::C && size > MAX_CLASSICAL_REGISTER_SIZE {
+            return Err(SemanticError::RegisterTooLarge {
+                symbol_name: name.into(),
+                size,
+                max_size: MAX_CLASSICAL_REGISTER_SIZE,
+                location,
+            });
+        }
+        Ok(())
+    }
+
     fn map_register(&mut self, name: String, kind: RegisterType, size: usize) {
         match &kind {
             RegisterType::Q => self.map_quantum_register(name, size),
@@ -225,28 +388,35 @@ impl SemanticsBuilder {
     }
 }
 
-pub fn extract_semantics(tree: &ast::OpenQasmProgram) -> Result<Semantics> {
+/// Return the semantics of `tree` along with all the errors found while
+/// extracting them. Offending declarations are ignored.
+pub(crate) fn collect_semantics(tree: &ast::OpenQasmProgram) -> (Semantics, Vec<SemanticError>) {
     let mut builder = SemanticsBuilder::new();
+    let mut errors = Vec::new();
     for span in &tree.program {
         let location = span.boundaries.0;
-        match &*span.node {
+        let result = match &*span.node {
             ast::Statement::QRegDecl(name, size) => {
-                builder.new_quantum_register(name.clone(), *size, location)?
+                builder.new_quantum_register(name.clone(), *size, location)
             }
             ast::Statement::CRegDecl(name, size) => {
-                builder.new_classical_register(name.clone(), *size, location)?
+                builder.new_classical_register(name.clone(), *size, location)
             }
-            ast::Statement::GateDecl(name, real_args, args, operations) => builder.new_gate(
+            ast::Statement::GateDecl(name, real_args, args, operations, doc) => builder.new_gate(
                 name.clone(),
                 real_args.to_vec(),
                 args.to_vec(),
                 operations.to_vec(),
                 location,
-            )?,
-            _ => (),
+                doc.clone(),
+            ),
+            _ => Ok(()),
+        };
+        if let Err(error) = result {
+            errors.push(error);
         }
     }
-    Ok(builder.semantics)
+    (builder.semantics, errors)
 }
 
 #[cfg(test)]
@@ -258,6 +428,15 @@ mod test {
 
     use crate::grammar::parse_program;
 
+    fn extract_semantics(tree: &ast::OpenQasmProgram) -> Result<Semantics> {
+        let (semantics, mut errors) = collect_semantics(tree);
+        if errors.is_empty() {
+            Ok(semantics)
+        } else {
+            Err(errors.remove(0))
+        }
+    }
+
     #[test]
     fn test_symbol_table_stores_register_info() {
         let source = indoc!(
@@ -276,19 +455,19 @@ mod test {
         let expected_register_table = HashMap::from_iter(vec![
             (
                 "q".to_owned(),
-                RegisterEntry("q".to_owned(), RegisterType::Q, 2, Location(14)),
+                RegisterEntry("q".to_owned(), RegisterType::Q, 2, Location::new_at(14)),
             ),
             (
                 "r".to_owned(),
-                RegisterEntry("r".to_owned(), RegisterType::Q, 10, Location(36)),
+                RegisterEntry("r".to_owned(), RegisterType::Q, 10, Location::new_at(36)),
             ),
             (
                 "c".to_owned(),
-                RegisterEntry("c".to_owned(), RegisterType::C, 2, Location(25)),
+                RegisterEntry("c".to_owned(), RegisterType::C, 2, Location::new_at(25)),
             ),
             (
                 "d".to_owned(),
-                RegisterEntry("d".to_owned(), RegisterType::C, 10, Location(48)),
+                RegisterEntry("d".to_owned(), RegisterType::C, 10, Location::new_at(48)),
             ),
         ]);
         if let Ok(semantics) = semantics_result {
@@ -332,7 +511,7 @@ mod test {
 
     #[test]
     fn test_cannot_redeclare_a_register() {
-        let sources = vec![
+        let sources = [
             indoc!(
                 "
       OPENQASM 2.0;
@@ -384,8 +563,8 @@ mod test {
                 error,
                 SemanticError::RedefinitionError {
                     symbol_name: "r".into(),
-                    location: Location(25),
-                    previous_location: Location(14)
+                    location: Location::new_at(25),
+                    previous_location: Location::new_at(14)
                 }
             );
         }
@@ -444,7 +623,8 @@ mod test {
                         vec![],
                         vec![ast::Argument::Id("q".to_owned())],
                     ))],
-                    Location(14),
+                    Location::new_at(14),
+                    None,
                 ),
             ),
             (
@@ -466,7 +646,8 @@ mod test {
                         ],
                         vec![ast::Argument::Id("q".to_owned())],
                     ))],
-                    Location(69),
+                    Location::new_at(69),
+                    None,
                 ),
             ),
         ]);
@@ -0,0 +1,724 @@
+//! Validate every statement of a program, and every gate body, before
+//! running it.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::grammar::ast;
+use crate::grammar::lexer::Location;
+use crate::interpreter::runtime::RuntimeError;
+
+use super::{collect_semantics, QasmType, RegisterEntry, RegisterType, SemanticError, Semantics};
+
+/// Return the semantics of `tree` after checking all the statements and gate
+/// bodies against the register table and the gate definitions.
+///
+/// Unlike the runtime, which stops at the first error in the path of the
+/// execution, the checker visits the whole program, including operations
+/// under conditionals that may never hold, and reports all the errors found
+/// in the order they appear.
+///
+/// Gates must be defined before being used and cannot call themselves.
+/// Errors inside gate bodies are located at the gate declaration. Calls to
+/// opaque gates are valid: they have no body to simulate but the runtime is
+/// the one rejecting them.
+pub fn check_semantics(tree: &ast::OpenQasmProgram) -> Result<Semantics, Vec<RuntimeError>> {
+    let (semantics, errors) = collect_semantics(tree);
+    let opaque_gates = tree
+        .program
+        .iter()
+        .filter_map(|span| match &*span.node {
+            ast::Statement::OpaqueGateDecl(name, real_args, args, _) => Some((
+                name.as_str(),
+                (real_args.as_slice(), args.as_slice(), span.boundaries.0),
+            )),
+            _ => None,
+        })
+        .collect();
+    let mut checker = Checker {
+        semantics: &semantics,
+        opaque_gates,
+        defined_gates: HashSet::new(),
+        errors: errors.into_iter().map(RuntimeError::from).collect(),
+    };
+    for span in &tree.program {
+        checker.check_statement(span);
+    }
+    let errors = checker.errors;
+    if errors.is_empty() {
+        Ok(semantics)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Formal real parameters, formal arguments and location of the declaration
+/// of an opaque gate.
+type OpaqueGate<'a> = (&'a [String], &'a [String], Location);
+
+struct Checker<'a> {
+    semantics: &'a Semantics,
+    opaque_gates: HashMap<&'a str, OpaqueGate<'a>>,
+    /// Gates, opaque or not, defined up to the statement being checked.
+    defined_gates: HashSet<&'a str>,
+    errors: Vec<RuntimeError>,
+}
+
+impl<'a> Checker<'a> {
+    fn check_statement(&mut self, span: &'a ast::Span<ast::Statement>) {
+        let location = span.boundaries.0;
+        match &*span.node {
+            ast::Statement::GateDecl(name, real_args, args, operations, _) => {
+                self.check_gate_body(name, real_args, args, operations, location);
+                if let Some((name, _)) = self.semantics.macro_definitions.get_key_value(name) {
+                    self.defined_gates.insert(name);
+                }
+            }
+            ast::Statement::OpaqueGateDecl(name, ..) => {
+                self.defined_gates.insert(name);
+            }
+            ast::Statement::Barrier(ast::BarrierPragma(args)) => {
+                for argument in args {
+                    self.check_register(argument, RegisterType::Q, location);
+                }
+            }
+            ast::Statement::QuantumOperation(operation) => {
+                self.check_quantum_operation(operation, location)
+            }
+            ast::Statement::Conditional(register, _, operation) => {
+                self.check_condition(register, location);
+                self.check_quantum_operation(operation, location);
+            }
+            _ => (),
+        }
+    }
+
+    fn check_quantum_operation(&mut self, operation: &ast::QuantumOperation, location: Location) {
+        match operation {
+            ast::QuantumOperation::Unitary(ast::UnitaryOperation(name, real_args, args)) => {
+                self.check_gate_call(name, real_args.len(), args.len(), None, location);
+                for expression in real_args {
+                    self.check_expression(expression, None, location);
+                }
+                // Check all the arguments before looking for the sizes.
+                let sizes: Vec<Option<usize>> = args
+                    .iter()
+                    .map(|argument| self.check_register(argument, RegisterType::Q, location))
+                    .collect();
+                if let Some(sizes) = sizes.into_iter().collect::<Option<Vec<usize>>>() {
+                    self.check_broadcasting(name, &sizes, location);
+                    self.check_aliasing(name, args, location);
+                }
+            }
+            ast::QuantumOperation::Measure(source, target) => {
+                let source_size = self.check_register(source, RegisterType::Q, location);
+                let target_size = self.check_register(target, RegisterType::C, location);
+                if let (Some(source_size), Some(target_size)) = (source_size, target_size) {
+                    self.check_broadcasting("measure", &[source_size, target_size], location);
+                }
+            }
+            ast::QuantumOperation::Reset(target) => {
+                self.check_register(target, RegisterType::Q, location);
+            }
+        }
+    }
+
+    fn check_gate_body(
+        &mut self,
+        gate_name: &str,
+        real_args: &[String],
+        args: &[String],
+        operations: &[ast::GateOperation],
+        location: Location,
+    ) {
+        for operation in operations {
+            let arguments = match operation {
+                ast::GateOperation::Unitary(ast::UnitaryOperation(name, real_exprs, arguments)) => {
+                    self.check_gate_call(
+                        name,
+                        real_exprs.len(),
+                        arguments.len(),
+                        Some(gate_name),
+                        location,
+                    );
+                    for expression in real_exprs {
+                        self.check_expression(expression, Some((gate_name, real_args)), location);
+                    }
+                    self.check_aliasing(name, arguments, location);
+                    arguments
+                }
+                ast::GateOperation::Barrier(ast::BarrierPragma(arguments)) => arguments,
+            };
+            for argument in arguments {
+                let error = match argument {
+                    ast::Argument::Id(name) if !args.contains(name) => {
+                        SemanticError::UndeclaredGateArgument {
+                            gate_name: gate_name.into(),
+                            symbol_name: name.clone(),
+                            location,
+                        }
+                    }
+                    ast::Argument::Item(name, index) => SemanticError::IndexedGateArgument {
+                        gate_name: gate_name.into(),
+                        symbol_name: name.clone(),
+                        index: *index,
+                        location,
+                    },
+                    _ => continue,
+                };
+                self.errors.push(error.into());
+            }
+        }
+    }
+
+    /// Check `name` is a gate, defined before the statement, accepting
+    /// `real_count` real parameters and `register_count` registers. Calls
+    /// inside the body of a gate pass its name in `caller`.
+    fn check_gate_call(
+        &mut self,
+        name: &str,
+        real_count: usize,
+        register_count: usize,
+        caller: Option<&str>,
+        location: Location,
+    ) {
+        // The grammar makes sure of the arity of the built-in gates.
+        if name == "U" || name == "CX" {
+            return;
+        }
+        if caller == Some(name) {
+            self.errors.push(
+                SemanticError::RecursiveGate {
+                    symbol_name: name.into(),
+                    location,
+                }
+                .into(),
+            );
+            return;
+        }
+        let definition = self
+            .semantics
+            .macro_definitions
+            .get(name)
+            .map(|definition| {
+                (
+                    definition.1.as_slice(),
+                    definition.2.as_slice(),
+                    definition.4,
+                )
+            })
+            .or_else(|| self.opaque_gates.get(name).copied());
+        let (real_formals, formals) = match definition {
+            None => {
+                self.errors.push(RuntimeError::UndefinedGate {
+                    location,
+                    symbol_name: name.into(),
+                });
+                return;
+            }
+            Some((_, _, definition_location)) if !self.defined_gates.contains(name) => {
+                self.errors.push(
+                    SemanticError::UseBeforeDefinition {
+                        symbol_name: name.into(),
+                        location,
+                        definition_location,
+                    }
+                    .into(),
+                );
+                return;
+            }
+            Some((real_formals, formals, _)) => (real_formals, formals),
+        };
+        if real_count != real_formals.len() {
+            self.errors.push(RuntimeError::WrongNumberOfParameters {
+                are_registers: false,
+                location,
+                symbol_name: name.into(),
+                expected: real_formals.len(),
+                given: real_count,
+            });
+        }
+        if register_count != formals.len() {
+            self.errors.push(RuntimeError::WrongNumberOfParameters {
+                are_registers: true,
+                location,
+                symbol_name: name.into(),
+                expected: formals.len(),
+                given: register_count,
+            });
+        }
+    }
+
+    /// Check `argument` refers to a register of type `kind` and, if indexed,
+    /// the index is inside the register. Return the size of the register if
+    /// the argument is valid.
+    fn check_register(
+        &mut self,
+        argument: &ast::Argument,
+        kind: RegisterType,
+        location: Location,
+    ) -> Option<usize> {
+        let (name, index) = match argument {
+            ast::Argument::Id(name) => (name, None),
+            ast::Argument::Item(name, index) => (name, Some(*index)),
+        };
+        let expected = match kind {
+            RegisterType::Q => QasmType::QuantumRegister,
+            RegisterType::C => QasmType::ClassicalRegister,
+        };
+        let entry = match self.semantics.register_table.get(name) {
+            None => {
+                self.errors.push(RuntimeError::SymbolNotFound {
+                    location,
+                    symbol_name: name.clone(),
+                    expected,
+                });
+                return None;
+            }
+            Some(entry) => entry,
+        };
+        if entry.1 != kind {
+            self.errors.push(RuntimeError::TypeMismatch {
+                location,
+                symbol_name: name.clone(),
+                expected,
+            });
+            return None;
+        }
+        match index {
+            Some(index) if index >= entry.2 => {
+                self.errors.push(RuntimeError::IndexOutOfBounds {
+                    location,
+                    symbol_name: name.clone(),
+                    index,
+                    size: entry.2,
+                });
+                None
+            }
+            // Indexed arguments do not take part in broadcasting.
+            Some(_) => Some(0),
+            None => Some(entry.2),
+        }
+    }
+
+    /// Check the register of a conditional is a classical register.
+    fn check_condition(&mut self, register: &ast::Argument, location: Location) {
+        if let ast::Argument::Id(name) = register {
+            if let Some(RegisterEntry(_, RegisterType::Q, _, _)) =
+                self.semantics.register_table.get(name)
+            {
+                self.errors.push(
+                    SemanticError::QuantumCondition {
+                        symbol_name: name.clone(),
+                        location,
+                    }
+                    .into(),
+                );
+                return;
+            }
+        }
+        self.check_register(register, RegisterType::C, location);
+    }
+
+    /// Check no qubit is passed twice to gate `name`, either directly or when
+    /// broadcasting the operation over whole registers.
+    fn check_aliasing(&mut self, name: &str, args: &[ast::Argument], location: Location) {
+        let mut aliased: Vec<&str> = Vec::new();
+        for (position, argument) in args.iter().enumerate() {
+            for other in &args[position + 1..] {
+                let register = match (argument, other) {
+                    (ast::Argument::Item(left, i), ast::Argument::Item(right, j))
+                        if left == right && i == j =>
+                    {
+                        left
+                    }
+                    (ast::Argument::Item(left, _), ast::Argument::Id(right))
+                    | (ast::Argument::Id(left), ast::Argument::Item(right, _))
+                    | (ast::Argument::Id(left), ast::Argument::Id(right))
+                        if left == right =>
+                    {
+                        left
+                    }
+                    _ => continue,
+                };
+                if !aliased.contains(&register.as_str()) {
+                    aliased.push(register);
+                    self.errors.push(
+                        SemanticError::QubitAliasing {
+                            gate_name: name.into(),
+                            symbol_name: register.clone(),
+                            location,
+                        }
+                        .into(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Check the whole registers in an operation, those with a non-zero size
+    /// in `sizes`, have all the same size.
+    fn check_broadcasting(&mut self, name: &str, sizes: &[usize], location: Location) {
+        let sizes: Vec<usize> = sizes.iter().copied().filter(|size| *size > 0).collect();
+        if sizes.iter().any(|size| *size != sizes[0]) {
+            self.errors.push(RuntimeError::RegisterSizeMismatch {
+                location,
+                symbol_name: name.into(),
+                sizes,
+            });
+        }
+    }
+
+    /// Check the identifiers in `expression` are among the formal parameters
+    /// of the `gate` whose body contains the expression, if any.
+    fn check_expression(
+        &mut self,
+        expression: &ast::Expression,
+        gate: Option<(&str, &[String])>,
+        location: Location,
+    ) {
+        match expression {
+            ast::Expression::Id(name) => match gate {
+                None => self.errors.push(RuntimeError::SymbolNotFound {
+                    location,
+                    symbol_name: name.clone(),
+                    expected: QasmType::RealValue,
+                }),
+                Some((gate_name, formals)) if !formals.contains(name) => self.errors.push(
+                    SemanticError::UndeclaredGateParameter {
+                        gate_name: gate_name.into(),
+                        symbol_name: name.clone(),
+                        location,
+                    }
+                    .into(),
+                ),
+                Some(_) => (),
+            },
+            ast::Expression::Op(_, left, right) => {
+                self.check_expression(left, gate, location);
+                self.check_expression(right, gate, location);
+            }
+            ast::Expression::Function(_, expression) | ast::Expression::Minus(expression) => {
+                self.check_expression(expression, gate, location)
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use crate::grammar::parse_program;
+
+    fn check(source: &str) -> Result<Semantics, Vec<RuntimeError>> {
+        check_semantics(&parse_program(source).unwrap())
+    }
+
+    #[test]
+    fn test_check_reports_all_errors() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    qreg q[2];
+    creg c[2];
+    qreg q[3];
+    U(0, 0, 0) q[2];
+    CX q, c;
+    undefined q;
+    measure c -> q;
+    "
+        );
+        assert_eq!(
+            check(source).unwrap_err(),
+            vec![
+                RuntimeError::from(SemanticError::RedefinitionError {
+                    symbol_name: "q".into(),
+                    location: Location::new_at(36),
+                    previous_location: Location::new_at(14),
+                }),
+                RuntimeError::IndexOutOfBounds {
+                    location: Location::new_at(47),
+                    symbol_name: "q".into(),
+                    index: 2,
+                    size: 2,
+                },
+                RuntimeError::TypeMismatch {
+                    location: Location::new_at(64),
+                    symbol_name: "c".into(),
+                    expected: QasmType::QuantumRegister,
+                },
+                RuntimeError::UndefinedGate {
+                    location: Location::new_at(73),
+                    symbol_name: "undefined".into(),
+                },
+                RuntimeError::TypeMismatch {
+                    location: Location::new_at(86),
+                    symbol_name: "c".into(),
+                    expected: QasmType::QuantumRegister,
+                },
+                RuntimeError::TypeMismatch {
+                    location: Location::new_at(86),
+                    symbol_name: "q".into(),
+                    expected: QasmType::ClassicalRegister,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_operations_under_conditionals() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    qreg q[2];
+    creg c[2];
+    if (c==3) U(0, 0, 0) q[5];
+    if (d==1) U(0, 0, 0) q[0];
+    "
+        );
+        assert_eq!(
+            check(source).unwrap_err(),
+            vec![
+                RuntimeError::IndexOutOfBounds {
+                    location: Location::new_at(36),
+                    symbol_name: "q".into(),
+                    index: 5,
+                    size: 2,
+                },
+                RuntimeError::SymbolNotFound {
+                    location: Location::new_at(63),
+                    symbol_name: "d".into(),
+                    expected: QasmType::ClassicalRegister,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_gate_bodies() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    gate g(theta) a, b {
+      U(theta, phi, 0) a;
+      CX a, c;
+      h a;
+    }
+    gate id a {}
+    qreg q[2];
+    qreg r[3];
+    id(pi) q, r;
+    CX q, r;
+    "
+        );
+        assert_eq!(
+            check(source).unwrap_err(),
+            vec![
+                RuntimeError::from(SemanticError::UndeclaredGateParameter {
+                    gate_name: "g".into(),
+                    symbol_name: "phi".into(),
+                    location: Location::new_at(14),
+                }),
+                RuntimeError::from(SemanticError::UndeclaredGateArgument {
+                    gate_name: "g".into(),
+                    symbol_name: "c".into(),
+                    location: Location::new_at(14),
+                }),
+                RuntimeError::UndefinedGate {
+                    location: Location::new_at(14),
+                    symbol_name: "h".into(),
+                },
+                RuntimeError::WrongNumberOfParameters {
+                    are_registers: false,
+                    location: Location::new_at(112),
+                    symbol_name: "id".into(),
+                    expected: 0,
+                    given: 1,
+                },
+                RuntimeError::WrongNumberOfParameters {
+                    are_registers: true,
+                    location: Location::new_at(112),
+                    symbol_name: "id".into(),
+                    expected: 1,
+                    given: 2,
+                },
+                RuntimeError::RegisterSizeMismatch {
+                    location: Location::new_at(112),
+                    symbol_name: "id".into(),
+                    sizes: vec![2, 3],
+                },
+                RuntimeError::RegisterSizeMismatch {
+                    location: Location::new_at(125),
+                    symbol_name: "CX".into(),
+                    sizes: vec![2, 3],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_gates_are_defined_before_use() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    qreg q[1];
+    later q;
+    gate first a { later a; }
+    gate later a { U(0, 0, 0) a; }
+    later q;
+    "
+        );
+        assert_eq!(
+            check(source).unwrap_err(),
+            vec![
+                RuntimeError::from(SemanticError::UseBeforeDefinition {
+                    symbol_name: "later".into(),
+                    location: Location::new_at(25),
+                    definition_location: Location::new_at(60),
+                }),
+                RuntimeError::from(SemanticError::UseBeforeDefinition {
+                    symbol_name: "later".into(),
+                    location: Location::new_at(34),
+                    definition_location: Location::new_at(60),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_opaque_gates() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    qreg q[2];
+    magic q[0];
+    opaque gate magic(theta) a;
+    gate wrap a { magic(pi) a; }
+    magic(pi) q;
+    magic q[0], q[1];
+    "
+        );
+        assert_eq!(
+            check(source).unwrap_err(),
+            vec![
+                RuntimeError::from(SemanticError::UseBeforeDefinition {
+                    symbol_name: "magic".into(),
+                    location: Location::new_at(25),
+                    definition_location: Location::new_at(37),
+                }),
+                RuntimeError::WrongNumberOfParameters {
+                    are_registers: false,
+                    location: Location::new_at(107),
+                    symbol_name: "magic".into(),
+                    expected: 1,
+                    given: 0,
+                },
+                RuntimeError::WrongNumberOfParameters {
+                    are_registers: true,
+                    location: Location::new_at(107),
+                    symbol_name: "magic".into(),
+                    expected: 1,
+                    given: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_recursive_gates() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    gate loop a { loop a; }
+    qreg q[1];
+    loop q;
+    "
+        );
+        assert_eq!(
+            check(source).unwrap_err(),
+            vec![RuntimeError::from(SemanticError::RecursiveGate {
+                symbol_name: "loop".into(),
+                location: Location::new_at(14),
+            })]
+        );
+    }
+
+    #[test]
+    fn test_check_indexed_gate_arguments() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    gate g a { CX a[0], a[1]; }
+    "
+        );
+        assert_eq!(
+            check(source).unwrap_err(),
+            vec![
+                RuntimeError::from(SemanticError::IndexedGateArgument {
+                    gate_name: "g".into(),
+                    symbol_name: "a".into(),
+                    index: 0,
+                    location: Location::new_at(14),
+                }),
+                RuntimeError::from(SemanticError::IndexedGateArgument {
+                    gate_name: "g".into(),
+                    symbol_name: "a".into(),
+                    index: 1,
+                    location: Location::new_at(14),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_qubit_aliasing() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    gate g a, b { CX a, a; }
+    qreg q[2];
+    qreg r[2];
+    g q, q;
+    g q, r;
+    g q[0], q[1];
+    "
+        );
+        assert_eq!(
+            check(source).unwrap_err(),
+            vec![
+                RuntimeError::from(SemanticError::QubitAliasing {
+                    gate_name: "CX".into(),
+                    symbol_name: "a".into(),
+                    location: Location::new_at(14),
+                }),
+                RuntimeError::from(SemanticError::QubitAliasing {
+                    gate_name: "g".into(),
+                    symbol_name: "q".into(),
+                    location: Location::new_at(61),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_valid_program() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    gate bell a, b {
+      U(pi/2, 0, pi) a;
+      CX a, b;
+    }
+    qreg q[2];
+    creg c[2];
+    bell q[0], q[1];
+    barrier q;
+    measure q -> c;
+    if (c==3) bell q[1], q[0];
+    "
+        );
+        assert!(check(source).is_ok());
+    }
+}
@@ -12,6 +12,10 @@ use self::cached_fns::{build_u, find_exchangeable_rows, find_target_rows};
 use crate::complex;
 pub use crate::complex::{Complex, ComplexMargin};
 
+mod density;
+
+pub use self::density::DensityMatrix;
+
 /// Represent the state vector of a quantum system simulation.
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -99,9 +103,148 @@ impl StateVector {
         }
         self.bases[0].re = 1.0;
     }
+
+    /// Return the density matrix of the subsystem formed by `qubits`, tracing
+    /// out the rest of the qubits. The first qubit in the list corresponds to
+    /// the least significant bit of the rows and columns of the matrix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if some of the `qubits` is out of range or repeated.
+    pub fn reduced_density_matrix(&self, qubits: &[usize]) -> DensityMatrix {
+        let subsystem_mask = self.subsystem_mask(qubits);
+        let dimension = exp2(qubits.len());
+        let mut entries = vec![Complex::from(0.0); dimension * dimension];
+        for (index, amplitude) in self.bases.iter().enumerate() {
+            let environment = index & !subsystem_mask;
+            let row = gather_bits(index, qubits);
+            for column in 0..dimension {
+                let other_index = environment | scatter_bits(column, qubits);
+                entries[row * dimension + column] += amplitude * self.bases[other_index].conj();
+            }
+        }
+        DensityMatrix::from_complex_entries(entries)
+    }
+
+    /// Return the eigenvalues of the reduced density matrix of `qubits`
+    /// different from zero, or some zeroes, in descending order. These are
+    /// the squares of the Schmidt coefficients of the state-vector, split
+    /// into `qubits` and the rest of the qubits.
+    ///
+    /// The coefficients come from the smaller side of the split, so it is
+    /// cheaper than computing the eigenvalues of the
+    /// [`reduced_density_matrix()`] when `qubits` are most of the qubits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if some of the `qubits` is out of range or repeated.
+    ///
+    /// [`reduced_density_matrix()`]: #method.reduced_density_matrix
+    pub fn schmidt_probabilities(&self, qubits: &[usize]) -> Vec<f64> {
+        let subsystem_mask = self.subsystem_mask(qubits);
+        let environment: Vec<usize> = (0..self.qubit_width)
+            .filter(|qubit| check_bit(subsystem_mask, *qubit) == 0)
+            .collect();
+        if environment.is_empty() {
+            return vec![self.probabilities().iter().sum()];
+        }
+
+        // Amplitudes form a matrix with a row per state of `qubits` and a
+        // column per state of the environment. Its Gram matrix on the
+        // smaller side has the same non-zero eigenvalues as the reduced
+        // density matrix.
+        let (rows, columns) = if qubits.len() <= environment.len() {
+            (qubits, environment.as_slice())
+        } else {
+            (environment.as_slice(), qubits)
+        };
+        let row_offsets: Vec<usize> = (0..exp2(rows.len()))
+            .map(|row| scatter_bits(row, rows))
+            .collect();
+        let column_offsets: Vec<usize> = (0..exp2(columns.len()))
+            .map(|column| scatter_bits(column, columns))
+            .collect();
+        let dimension = row_offsets.len();
+        let mut gram = vec![Complex::from(0.0); dimension * dimension];
+        for (row, row_offset) in row_offsets.iter().enumerate() {
+            for (other_row, other_row_offset) in row_offsets.iter().enumerate().skip(row) {
+                let entry: Complex = column_offsets
+                    .iter()
+                    .map(|column_offset| {
+                        self.bases[row_offset | column_offset]
+                            * self.bases[other_row_offset | column_offset].conj()
+                    })
+                    .sum();
+                gram[row * dimension + other_row] = entry;
+                gram[other_row * dimension + row] = entry.conj();
+            }
+        }
+        density::hermitian_eigenvalues(gram, dimension)
+            .into_iter()
+            .map(|eigenvalue| eigenvalue.max(0.0))
+            .collect()
+    }
+
+    /// Return the von Neumann entropy, in bits, of the reduced state of
+    /// `qubits`, which is the entanglement entropy of `qubits` with the rest
+    /// of the qubits. It is 0 if `qubits` are all the qubits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if some of the `qubits` is out of range or repeated.
+    pub fn entanglement_entropy(&self, qubits: &[usize]) -> f64 {
+        let subsystem_mask = self.subsystem_mask(qubits);
+        // The whole system is in a pure state.
+        if subsystem_mask.count_ones() as usize == self.qubit_width {
+            return 0.0;
+        }
+        density::entropy(&self.schmidt_probabilities(qubits))
+    }
+
+    /// Return the purity `Tr(ρ²)` of the reduced state of `qubits`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if some of the `qubits` is out of range or repeated.
+    pub fn reduced_purity(&self, qubits: &[usize]) -> f64 {
+        self.schmidt_probabilities(qubits)
+            .iter()
+            .map(|probability| probability * probability)
+            .sum()
+    }
+
+    fn subsystem_mask(&self, qubits: &[usize]) -> usize {
+        let mut subsystem_mask = 0;
+        for qubit in qubits {
+            assert!(
+                *qubit < self.qubit_width,
+                "qubit {} must be in the range 0..{}",
+                qubit,
+                self.qubit_width
+            );
+            assert!(
+                check_bit(subsystem_mask, *qubit) == 0,
+                "qubit {} cannot appear twice",
+                qubit
+            );
+            subsystem_mask |= 1 << qubit;
+        }
+        subsystem_mask
+    }
+
+    /// Return the Bloch vector `(x, y, z)` of the reduced state of `qubit`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `qubit` is out of range.
+    pub fn bloch_vector(&self, qubit: usize) -> [f64; 3] {
+        self.reduced_density_matrix(&[qubit])
+            .bloch_vector()
+            .expect("the density matrix of one qubit")
+    }
 }
 
-impl<'a> ApproxEq for &'a StateVector {
+impl ApproxEq for &StateVector {
     type Margin = ComplexMargin;
 
     fn approx_eq<T: Into<Self::Margin>>(self, other: Self, margin: T) -> bool {
@@ -147,7 +290,7 @@ impl<'a> Measurement<'a> {
 
     pub fn collapse(&mut self, fate: f64) -> bool {
         assert!(
-            0.0 <= fate && fate < 1.0,
+            (0.0..1.0).contains(&fate),
             "Fate must be a f64 value in [0.0, 1.0)"
         );
         let value = (fate >= self.chances[0]) as usize;
@@ -167,8 +310,7 @@ impl<'a> Measurement<'a> {
 /// the f64 margin for each of the complex components.
 pub fn assert_approx_eq(v1: &StateVector, v2: &StateVector) {
     if !v1.approx_eq(v2, complex::ComplexMargin::default()) {
-        assert!(
-            false,
+        panic!(
             "assertion failed `(left ~= right)`\n  left: `{:?}`\n right: `{:?}`",
             v1, v2
         );
@@ -180,6 +322,30 @@ fn check_bit(value: usize, index: usize) -> usize {
     (value & (1 << index)) >> index
 }
 
+/// Return the integer formed by the bits of `value` at positions `indices`,
+/// being the first index the least significant bit.
+#[inline]
+fn gather_bits(value: usize, indices: &[usize]) -> usize {
+    indices
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (position, index)| {
+            acc | (check_bit(value, *index) << position)
+        })
+}
+
+/// Inverse of [`gather_bits()`]: place the bits of `value` at positions
+/// `indices`.
+#[inline]
+fn scatter_bits(value: usize, indices: &[usize]) -> usize {
+    indices
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (position, index)| {
+            acc | (check_bit(value, position) << index)
+        })
+}
+
 #[inline]
 fn exp2(power: usize) -> usize {
     1_usize << power
@@ -354,7 +520,7 @@ mod tests {
             f64,
             (accum as f64) / (size as f64),
             0.5,
-            epsilon = std::f64::EPSILON
+            epsilon = f64::EPSILON
         );
     }
 
@@ -418,4 +584,111 @@ mod tests {
             ]),
         );
     }
+
+    #[test]
+    fn test_reduced_density_matrix_of_product_state() {
+        // |+⟩ on qubit 0 and |1⟩ on qubit 1.
+        let v = StateVector::from_complex_bases(vec![
+            Complex::from(0.0),
+            Complex::from(0.0),
+            Complex::from(FRAC_1_SQRT_2),
+            Complex::from(FRAC_1_SQRT_2),
+        ]);
+        let rho_0 = v.reduced_density_matrix(&[0]);
+        for entry in rho_0.as_complex_entries() {
+            assert!(approx_eq!(f64, entry.re, 0.5, epsilon = 1e-9));
+        }
+        assert!(approx_eq!(f64, rho_0.purity(), 1.0, epsilon = 1e-9));
+        assert!(approx_eq!(
+            f64,
+            rho_0.von_neumann_entropy(),
+            0.0,
+            epsilon = 1e-9
+        ));
+        let [x, y, z] = v.bloch_vector(1);
+        assert!(approx_eq!(f64, x, 0.0, epsilon = 1e-9));
+        assert!(approx_eq!(f64, y, 0.0, epsilon = 1e-9));
+        assert!(approx_eq!(f64, z, -1.0, epsilon = 1e-9));
+    }
+
+    #[test]
+    fn test_reduced_density_matrix_of_bell_pair() {
+        let v = StateVector::from_complex_bases(vec![
+            Complex::from(FRAC_1_SQRT_2),
+            Complex::from(0.0),
+            Complex::from(0.0),
+            Complex::from(FRAC_1_SQRT_2),
+        ]);
+        for qubit in 0..2 {
+            let rho = v.reduced_density_matrix(&[qubit]);
+            assert!(approx_eq!(f64, rho.purity(), 0.5, epsilon = 1e-9));
+            assert!(approx_eq!(
+                f64,
+                rho.von_neumann_entropy(),
+                1.0,
+                epsilon = 1e-9
+            ));
+            for component in &v.bloch_vector(qubit) {
+                assert!(approx_eq!(f64, *component, 0.0, epsilon = 1e-9));
+            }
+        }
+        let whole = v.reduced_density_matrix(&[0, 1]);
+        assert!(approx_eq!(f64, whole.purity(), 1.0, epsilon = 1e-9));
+        assert!(approx_eq!(
+            f64,
+            whole.von_neumann_entropy(),
+            0.0,
+            epsilon = 1e-9
+        ));
+    }
+
+    #[test]
+    fn test_reduced_density_matrix_follows_qubit_order() {
+        // |01⟩ (qubit 0 is 1, qubit 1 is 0).
+        let p = Complex::from(0.0);
+        let v = StateVector::from_complex_bases(vec![p, Complex::from(1.0), p, p]);
+        let rho_01 = v.reduced_density_matrix(&[0, 1]);
+        let rho_10 = v.reduced_density_matrix(&[1, 0]);
+        assert_eq!(rho_01.get(1, 1), Complex::from(1.0));
+        assert_eq!(rho_10.get(2, 2), Complex::from(1.0));
+    }
+
+    #[test]
+    fn test_schmidt_probabilities_match_the_reduced_density_matrix() {
+        let mut v = StateVector::new(4);
+        for (target, theta) in [0.3, 1.1, 2.0, 0.7].iter().enumerate() {
+            v.u(*theta, 0.5 * theta, 0.25, target);
+        }
+        v.cnot(0, 2);
+        v.cnot(3, 1);
+        v.cnot(1, 0);
+        for qubits in &[vec![0], vec![2, 1], vec![0, 1, 3], vec![]] {
+            let rho = v.reduced_density_matrix(qubits);
+            let expected = rho.eigenvalues();
+            let actual = v.schmidt_probabilities(qubits);
+            for (index, expected) in expected.iter().enumerate() {
+                let actual = actual.get(index).copied().unwrap_or_default();
+                assert!(approx_eq!(f64, actual, *expected, epsilon = 1e-9));
+            }
+            assert!(approx_eq!(
+                f64,
+                v.entanglement_entropy(qubits),
+                rho.von_neumann_entropy(),
+                epsilon = 1e-9
+            ));
+            assert!(approx_eq!(
+                f64,
+                v.reduced_purity(qubits),
+                rho.purity(),
+                epsilon = 1e-9
+            ));
+        }
+        assert_eq!(v.entanglement_entropy(&[3, 2, 1, 0]), 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot appear twice")]
+    fn test_reduced_density_matrix_rejects_repeated_qubits() {
+        StateVector::new(2).reduced_density_matrix(&[1, 1]);
+    }
 }
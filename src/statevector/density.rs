@@ -0,0 +1,239 @@
+//! Contain utilities for inspecting subsystems of a quantum state.
+use std::f64;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::complex::Complex;
+
+/// Below this value, an eigenvalue is considered to be zero when computing
+/// entropies.
+const ZERO_EIGENVALUE: f64 = 1e-12;
+
+/// Maximum number of sweeps of the Jacobi eigenvalue algorithm.
+const MAX_JACOBI_SWEEPS: usize = 100;
+
+/// Represent the density matrix of a (sub)system of qubits.
+///
+/// Density matrices are usually obtained from a [`StateVector`] by tracing
+/// out the qubits that are not of interest with
+/// [`StateVector::reduced_density_matrix()`].
+///
+/// # Examples
+///
+/// The reduced state of any of the qubits in a Bell pair is maximally mixed:
+///
+/// ```
+/// use std::f64::consts::FRAC_1_SQRT_2;
+/// use qasmsim::statevector::{Complex, StateVector};
+///
+/// let bell = StateVector::from_complex_bases(vec![
+///     Complex::from(FRAC_1_SQRT_2),
+///     Complex::from(0.0),
+///     Complex::from(0.0),
+///     Complex::from(FRAC_1_SQRT_2),
+/// ]);
+/// let density_matrix = bell.reduced_density_matrix(&[0]);
+///
+/// assert!((density_matrix.purity() - 0.5).abs() < 1e-9);
+/// assert!((density_matrix.von_neumann_entropy() - 1.0).abs() < 1e-9);
+/// ```
+///
+/// [`StateVector`]: ./struct.StateVector.html
+/// [`StateVector::reduced_density_matrix()`]: ./struct.StateVector.html#method.reduced_density_matrix
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DensityMatrix {
+    entries: Vec<Complex>,
+    qubit_width: usize,
+}
+
+impl DensityMatrix {
+    /// Create a new density matrix from its entries in row-major order. It
+    /// does not check the number of entries is a square of a power of two,
+    /// nor the matrix is hermitian, nor its trace is 1.
+    pub fn from_complex_entries(entries: Vec<Complex>) -> Self {
+        let qubit_width = ((entries.len() as f64).log2() / 2.0) as usize;
+        DensityMatrix {
+            entries,
+            qubit_width,
+        }
+    }
+
+    /// Return the entries of the matrix in row-major order.
+    pub fn as_complex_entries(&self) -> &[Complex] {
+        &self.entries
+    }
+
+    /// Return the number of qubits the matrix describes.
+    pub fn qubit_width(&self) -> usize {
+        self.qubit_width
+    }
+
+    /// Return the number of rows (or columns) of the matrix.
+    pub fn dimension(&self) -> usize {
+        1 << self.qubit_width
+    }
+
+    /// Return the entry at `row` and `column`.
+    pub fn get(&self, row: usize, column: usize) -> Complex {
+        self.entries[row * self.dimension() + column]
+    }
+
+    /// Return the purity of the state, `Tr(ρ²)`. The purity is 1 for pure
+    /// states and `1/d` for a maximally mixed state of dimension `d`.
+    pub fn purity(&self) -> f64 {
+        // For hermitian matrices, Tr(ρ²) is the sum of the squared norms.
+        self.entries.iter().map(|entry| entry.norm_sqr()).sum()
+    }
+
+    /// Return the eigenvalues of the matrix, sorted in descending order.
+    pub fn eigenvalues(&self) -> Vec<f64> {
+        hermitian_eigenvalues(self.entries.clone(), self.dimension())
+    }
+
+    /// Return the von Neumann entropy of the state, in bits.
+    pub fn von_neumann_entropy(&self) -> f64 {
+        entropy(&self.eigenvalues())
+    }
+
+    /// Return the Bloch vector `(x, y, z)` of a single-qubit density matrix,
+    /// or `None` if the matrix describes more than one qubit.
+    pub fn bloch_vector(&self) -> Option<[f64; 3]> {
+        if self.qubit_width != 1 {
+            return None;
+        }
+        let rho_10 = self.get(1, 0);
+        let x = 2.0 * rho_10.re;
+        let y = 2.0 * rho_10.im;
+        let z = self.get(0, 0).re - self.get(1, 1).re;
+        Some([x, y, z])
+    }
+}
+
+/// Return the von Neumann entropy, in bits, of a state with the given
+/// eigenvalues.
+pub(crate) fn entropy(eigenvalues: &[f64]) -> f64 {
+    let entropy: f64 = eigenvalues
+        .iter()
+        .filter(|eigenvalue| **eigenvalue > ZERO_EIGENVALUE)
+        .map(|eigenvalue| -eigenvalue * eigenvalue.log2())
+        .sum();
+    if entropy > 0.0 {
+        entropy
+    } else {
+        0.0
+    }
+}
+
+/// Return the eigenvalues of the hermitian matrix of `dimension` rows with
+/// `entries` in row-major order, sorted in descending order. It uses the
+/// cyclic Jacobi eigenvalue algorithm, with complex rotations.
+#[allow(clippy::needless_range_loop)]
+pub(crate) fn hermitian_eigenvalues(mut m: Vec<Complex>, dimension: usize) -> Vec<f64> {
+    let at = |row: usize, column: usize| row * dimension + column;
+    for _ in 0..MAX_JACOBI_SWEEPS {
+        let mut off_diagonal = 0.0;
+        for p in 0..dimension {
+            for q in (p + 1)..dimension {
+                off_diagonal += m[at(p, q)].norm_sqr();
+            }
+        }
+        if off_diagonal < f64::EPSILON * f64::EPSILON {
+            break;
+        }
+
+        for p in 0..dimension {
+            for q in (p + 1)..dimension {
+                let norm = m[at(p, q)].norm();
+                if norm < f64::MIN_POSITIVE {
+                    continue;
+                }
+                // Rotate the phase of the q-th basis vector for making the
+                // entry real, then apply a real Jacobi rotation.
+                let phase = m[at(p, q)] / norm;
+                let theta = (m[at(q, q)].re - m[at(p, p)].re) / (2.0 * norm);
+                let sign = if theta >= 0.0 { 1.0 } else { -1.0 };
+                let t = sign / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for k in 0..dimension {
+                    let (mkp, mkq) = (m[at(k, p)], m[at(k, q)] * phase.conj());
+                    m[at(k, p)] = mkp * c - mkq * s;
+                    m[at(k, q)] = mkp * s + mkq * c;
+                }
+                for k in 0..dimension {
+                    let (mpk, mqk) = (m[at(p, k)], m[at(q, k)] * phase);
+                    m[at(p, k)] = mpk * c - mqk * s;
+                    m[at(q, k)] = mpk * s + mqk * c;
+                }
+            }
+        }
+    }
+    let mut eigenvalues: Vec<f64> = (0..dimension).map(|index| m[at(index, index)].re).collect();
+    eigenvalues.sort_by(|a, b| b.partial_cmp(a).expect("eigenvalues are not NaN"));
+    eigenvalues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use float_cmp::approx_eq;
+
+    #[test]
+    fn test_eigenvalues_of_diagonal_matrix() {
+        let matrix = DensityMatrix::from_complex_entries(vec![
+            Complex::from(0.25),
+            Complex::from(0.0),
+            Complex::from(0.0),
+            Complex::from(0.75),
+        ]);
+        let eigenvalues = matrix.eigenvalues();
+        assert!(approx_eq!(f64, eigenvalues[0], 0.75, epsilon = 1e-9));
+        assert!(approx_eq!(f64, eigenvalues[1], 0.25, epsilon = 1e-9));
+    }
+
+    #[test]
+    fn test_eigenvalues_of_complex_hermitian_matrix() {
+        // The pure state (|0⟩ + i|1⟩)/√2 has eigenvalues 1 and 0.
+        let matrix = DensityMatrix::from_complex_entries(vec![
+            Complex::from(0.5),
+            Complex::new(0.0, -0.5),
+            Complex::new(0.0, 0.5),
+            Complex::from(0.5),
+        ]);
+        let eigenvalues = matrix.eigenvalues();
+        assert!(approx_eq!(f64, eigenvalues[0], 1.0, epsilon = 1e-9));
+        assert!(approx_eq!(f64, eigenvalues[1], 0.0, epsilon = 1e-9));
+        assert!(approx_eq!(
+            f64,
+            matrix.von_neumann_entropy(),
+            0.0,
+            epsilon = 1e-9
+        ));
+    }
+
+    #[test]
+    fn test_bloch_vector_of_y_eigenstate() {
+        let matrix = DensityMatrix::from_complex_entries(vec![
+            Complex::from(0.5),
+            Complex::new(0.0, -0.5),
+            Complex::new(0.0, 0.5),
+            Complex::from(0.5),
+        ]);
+        let [x, y, z] = matrix.bloch_vector().expect("single qubit matrix");
+        assert!(approx_eq!(f64, x, 0.0, epsilon = 1e-9));
+        assert!(approx_eq!(f64, y, 1.0, epsilon = 1e-9));
+        assert!(approx_eq!(f64, z, 0.0, epsilon = 1e-9));
+    }
+
+    #[test]
+    fn test_bloch_vector_is_only_defined_for_one_qubit() {
+        let mut entries = vec![Complex::from(0.0); 16];
+        entries[0] = Complex::from(1.0);
+        let matrix = DensityMatrix::from_complex_entries(entries);
+        assert_eq!(matrix.qubit_width(), 2);
+        assert_eq!(matrix.bloch_vector(), None);
+    }
+}
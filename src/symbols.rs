@@ -0,0 +1,239 @@
+//! Contain the symbols a program declares, along with their locations and
+//! documentation, for tools such as editors. The module is **unstable**.
+
+use std::fmt;
+use std::ops::Range;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::grammar::ast;
+use crate::grammar::lexer::{Lexer, Location, Tok};
+use crate::semantics::{collect_semantics, MacroDefinition, RegisterEntry, RegisterType};
+
+/// The kinds of symbols a program can declare.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SymbolKind {
+    /// A quantum register, declared with `qreg`.
+    QuantumRegister,
+    /// A classical register, declared with `creg`.
+    ClassicalRegister,
+    /// A gate, declared with `gate`.
+    Gate,
+    /// An opaque gate, declared with `opaque gate`.
+    OpaqueGate,
+}
+
+/// A register or a gate declared in a program.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Symbol {
+    /// Name of the symbol.
+    pub name: String,
+    /// What the symbol is.
+    pub kind: SymbolKind,
+    /// Location of the declaration.
+    pub location: Location,
+    /// Size of the register, or `None` for gates.
+    pub size: Option<usize>,
+    /// Names of the real parameters of the gate.
+    pub real_args: Vec<String>,
+    /// Names of the quantum arguments of the gate.
+    pub args: Vec<String>,
+    /// Documentation of the gate, taken from the comment preceding it.
+    pub doc: Option<String>,
+}
+
+impl Symbol {
+    /// Return the declaration of the symbol, without the body of gates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qasmsim::symbols::symbols;
+    ///
+    /// let program = qasmsim::parse_and_link(r#"
+    /// OPENQASM 2.0;
+    /// include "qelib1.inc";
+    /// qreg q[2];
+    /// "#)?;
+    /// let symbols = symbols(&program);
+    /// let u2 = symbols.iter().find(|symbol| symbol.name == "u2").unwrap();
+    /// let q = symbols.iter().find(|symbol| symbol.name == "q").unwrap();
+    ///
+    /// assert_eq!(u2.signature(), "gate u2(phi, lambda) q");
+    /// assert_eq!(q.signature(), "qreg q[2]");
+    /// # use qasmsim::QasmSimError;
+    /// # Ok::<(), QasmSimError>(())
+    /// ```
+    pub fn signature(&self) -> String {
+        let keyword = match self.kind {
+            SymbolKind::QuantumRegister => "qreg",
+            SymbolKind::ClassicalRegister => "creg",
+            SymbolKind::Gate => "gate",
+            SymbolKind::OpaqueGate => "opaque gate",
+        };
+        match self.size {
+            Some(size) => format!("{} {}[{}]", keyword, self.name, size),
+            None if self.real_args.is_empty() => {
+                format!("{} {} {}", keyword, self.name, self.args.join(", "))
+            }
+            None => format!(
+                "{} {}({}) {}",
+                keyword,
+                self.name,
+                self.real_args.join(", "),
+                self.args.join(", ")
+            ),
+        }
+    }
+
+    /// Return the byte range of the name of the symbol inside `source`, the
+    /// source code of the file declaring it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qasmsim::symbols::symbols;
+    ///
+    /// let source = "OPENQASM 2.0;\nqreg q[2];\n";
+    /// let program = qasmsim::parse_and_link(source)?;
+    /// let q = &symbols(&program)[0];
+    ///
+    /// assert_eq!(q.name_range(source), 19..20);
+    /// # use qasmsim::QasmSimError;
+    /// # Ok::<(), QasmSimError>(())
+    /// ```
+    pub fn name_range(&self, source: &str) -> Range<usize> {
+        let start = self.location.offset.min(source.len());
+        identifiers(&source[start..])
+            .find(|(name, _)| *name == self.name)
+            .map_or(start..start, |(_, range)| {
+                start + range.start..start + range.end
+            })
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.signature())
+    }
+}
+
+/// Return the registers and gates declared in `program`, including those of
+/// the linked libraries, in the order they are declared.
+///
+/// When a symbol is declared more than once, only the first declaration is
+/// returned.
+pub fn symbols(program: &ast::OpenQasmProgram) -> Vec<Symbol> {
+    let (semantics, _) = collect_semantics(program);
+    let registers = semantics.register_table.into_iter().map(
+        |(_, RegisterEntry(name, kind, size, location))| Symbol {
+            name,
+            kind: match kind {
+                RegisterType::Q => SymbolKind::QuantumRegister,
+                RegisterType::C => SymbolKind::ClassicalRegister,
+            },
+            location,
+            size: Some(size),
+            real_args: vec![],
+            args: vec![],
+            doc: None,
+        },
+    );
+    let gates = semantics.macro_definitions.into_iter().map(
+        |(_, MacroDefinition(name, real_args, args, _, location, doc))| Symbol {
+            name,
+            kind: SymbolKind::Gate,
+            location,
+            size: None,
+            real_args,
+            args,
+            doc,
+        },
+    );
+    let opaque_gates = program.program.iter().filter_map(|span| match &*span.node {
+        ast::Statement::OpaqueGateDecl(name, real_args, args, doc) => Some(Symbol {
+            name: name.clone(),
+            kind: SymbolKind::OpaqueGate,
+            location: span.boundaries.0,
+            size: None,
+            real_args: real_args.clone(),
+            args: args.clone(),
+            doc: doc.clone(),
+        }),
+        _ => None,
+    });
+
+    let mut symbols: Vec<Symbol> = registers.chain(gates).chain(opaque_gates).collect();
+    symbols.sort_by_key(|symbol| symbol.location);
+    symbols
+}
+
+/// Return the identifier at `offset` of `source` and its byte range, if
+/// any. An identifier ending right at `offset` counts, so the identifier is
+/// found while the cursor of an editor is at its end.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::symbols::identifier_at;
+///
+/// let source = "OPENQASM 2.0;\nqreg q[2];\n";
+///
+/// assert_eq!(identifier_at(source, 20), Some(("q".to_string(), 19..20)));
+/// assert_eq!(identifier_at(source, 16), None);
+/// ```
+pub fn identifier_at(source: &str, offset: usize) -> Option<(String, Range<usize>)> {
+    identifiers(source).find(|(_, range)| range.start <= offset && offset <= range.end)
+}
+
+fn identifiers(source: &str) -> impl Iterator<Item = (String, Range<usize>)> + '_ {
+    Lexer::new(source)
+        .map_while(Result::ok)
+        .filter_map(|(start, token, end)| match token {
+            Tok::Id { repr } => Some((repr, start.offset..end.offset)),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use crate::grammar::parse_program;
+
+    #[test]
+    fn test_symbols_in_declaration_order() {
+        let source = indoc!(
+            "
+    OPENQASM 2.0;
+    opaque gate magic(theta) a, b;
+    gate id q { }
+    qreg q[2];
+    creg c[2];
+    "
+        );
+        let symbols = symbols(&parse_program(source).unwrap());
+        let summary: Vec<(SymbolKind, String, usize)> = symbols
+            .iter()
+            .map(|symbol| (symbol.kind, symbol.signature(), symbol.location.offset))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    SymbolKind::OpaqueGate,
+                    "opaque gate magic(theta) a, b".to_string(),
+                    14
+                ),
+                (SymbolKind::Gate, "gate id q".to_string(), 45),
+                (SymbolKind::QuantumRegister, "qreg q[2]".to_string(), 59),
+                (SymbolKind::ClassicalRegister, "creg c[2]".to_string(), 70),
+            ]
+        );
+    }
+}
@@ -0,0 +1,503 @@
+//! Contain the transpiler, which flattens programs into a basis of gates for
+//! toolchains accepting a fixed set of gates only. The module is
+//! **unstable**.
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fmt;
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::RuntimeError;
+use crate::grammar::ast;
+use crate::grammar::lexer::Location;
+use crate::interpreter::argument_solver::ArgumentSolver;
+use crate::interpreter::expression_solver::ExpressionSolver;
+use crate::interpreter::runtime::BindingMappings;
+use crate::semantics::{check_semantics, QasmType, Semantics};
+
+mod optimizer;
+mod router;
+
+pub use self::optimizer::{optimize, stats, CircuitStats, Optimization};
+pub use self::router::{route, CouplingMap, Routing, RoutingError};
+
+/// The sets of gates a program can be transpiled to.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Basis {
+    /// The primitive gates `U` and `CX`, the default.
+    #[default]
+    Primitive,
+    /// The gates `rz`, `sx` and `cx` of `qelib1.inc`.
+    RzSxCx,
+    /// The gates `u3` and `cx` of `qelib1.inc`.
+    U3Cx,
+}
+
+impl Basis {
+    /// Return all the bases.
+    pub fn all() -> &'static [Basis] {
+        &[Basis::Primitive, Basis::RzSxCx, Basis::U3Cx]
+    }
+
+    /// Return the names of the gates in the basis, separated by commas, as
+    /// in `rz,sx,cx`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Basis::Primitive => "U,CX",
+            Basis::RzSxCx => "rz,sx,cx",
+            Basis::U3Cx => "u3,cx",
+        }
+    }
+}
+
+impl fmt::Display for Basis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Basis {
+    type Err = String;
+
+    /// Parse the names of the gates in the basis, in any order.
+    fn from_str(names: &str) -> Result<Self, Self::Err> {
+        let mut gates: Vec<&str> = names.split(',').map(str::trim).collect();
+        gates.sort_unstable();
+        Basis::all()
+            .iter()
+            .copied()
+            .find(|basis| {
+                let mut basis_gates: Vec<&str> = basis.name().split(',').collect();
+                basis_gates.sort_unstable();
+                basis_gates == gates
+            })
+            .ok_or_else(|| format!("unknown basis `{}`", names))
+    }
+}
+
+/// Return `program` with every gate call inlined, recursively, down to the
+/// gates of `basis`, and the parameters of the gates evaluated. Register
+/// declarations, measurements, resets, barriers and snapshots are kept, while
+/// gate declarations are dropped. Operations on whole registers are expanded
+/// into one operation per qubit.
+///
+/// The program includes `qelib1.inc` if the gates of `basis` come from it.
+/// Each statement of the result keeps the location of the statement it comes
+/// from. The result has no comments.
+///
+/// # Errors
+///
+/// The function fails with the first semantic error of `program`, which must
+/// be linked, or with [`RuntimeError::UndefinedGate`] if the program calls an
+/// opaque gate, since opaque gates cannot be inlined.
+///
+/// [`RuntimeError::UndefinedGate`]: ../error/enum.RuntimeError.html#variant.UndefinedGate
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::parse_and_link;
+/// use qasmsim::transpiler::{transpile, Basis};
+///
+/// let program = parse_and_link(r#"
+/// OPENQASM 2.0;
+/// include "qelib1.inc";
+/// qreg q[2];
+/// h q[0];
+/// cx q[0], q[1];
+/// "#)?;
+/// let transpiled = transpile(&program, Basis::Primitive)?;
+///
+/// assert_eq!(transpiled.to_string(), "\
+/// OPENQASM 2.0;
+/// qreg q[2];
+/// U(1.5707963267948966, 0, 3.141592653589793) q[0];
+/// CX q[0], q[1];
+/// ");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn transpile(
+    program: &ast::OpenQasmProgram,
+    basis: Basis,
+) -> Result<ast::OpenQasmProgram, RuntimeError> {
+    let semantics = check_semantics(program).map_err(|mut errors| errors.remove(0))?;
+    let mut transpiler = Transpiler {
+        semantics: &semantics,
+        basis,
+        boundaries: (Location::new_at(0), Location::new_at(0)),
+        condition: None,
+        statements: vec![],
+    };
+    if basis != Basis::Primitive {
+        transpiler.emit(ast::Statement::Include("qelib1.inc".into()));
+    }
+    for span in &program.program {
+        transpiler.boundaries = span.boundaries;
+        transpiler.condition = None;
+        match &*span.node {
+            ast::Statement::QuantumOperation(ast::QuantumOperation::Unitary(unitary)) => {
+                transpiler.unitary(unitary, None)?;
+            }
+            ast::Statement::Conditional(
+                register,
+                value,
+                ast::QuantumOperation::Unitary(unitary),
+            ) => {
+                transpiler.condition = Some((register.clone(), *value));
+                transpiler.unitary(unitary, None)?;
+            }
+            ast::Statement::GateDecl(..)
+            | ast::Statement::OpaqueGateDecl(..)
+            | ast::Statement::Include(_) => (),
+            statement => transpiler.emit(statement.clone()),
+        }
+    }
+    Ok(ast::OpenQasmProgram {
+        version: program.version.clone(),
+        program: transpiler.statements,
+        trivia: vec![],
+    })
+}
+
+struct Transpiler<'a> {
+    semantics: &'a Semantics,
+    basis: Basis,
+    /// Boundaries of the statement being transpiled.
+    boundaries: (Location, Location),
+    /// Condition of the statement being transpiled, if any.
+    condition: Option<(ast::Argument, u64)>,
+    statements: Vec<ast::Span<ast::Statement>>,
+}
+
+impl Transpiler<'_> {
+    fn emit(&mut self, statement: ast::Statement) {
+        self.statements.push(ast::Span {
+            boundaries: self.boundaries,
+            node: Box::new(statement),
+        });
+    }
+
+    fn emit_gate(&mut self, name: &str, real_args: &[f64], args: &[ast::Argument]) {
+        let unitary = ast::UnitaryOperation(
+            name.into(),
+            real_args.iter().copied().map(real).collect(),
+            args.to_vec(),
+        );
+        let operation = ast::QuantumOperation::Unitary(unitary);
+        match &self.condition {
+            None => self.emit(ast::Statement::QuantumOperation(operation)),
+            Some((register, value)) => {
+                self.emit(ast::Statement::Conditional(
+                    register.clone(),
+                    *value,
+                    operation,
+                ));
+            }
+        }
+    }
+
+    /// Inline `unitary`, called from the body of a gate with `bindings`, or
+    /// from the program if `bindings` is `None`.
+    fn unitary(
+        &mut self,
+        unitary: &ast::UnitaryOperation,
+        bindings: Option<&BindingMappings>,
+    ) -> Result<(), RuntimeError> {
+        let ast::UnitaryOperation(name, real_args, args) = unitary;
+        let empty = HashMap::new();
+        let real_bindings = bindings.map_or(&empty, |bindings| &bindings.0);
+        let expression_solver = ExpressionSolver::new(real_bindings);
+        let real_args = real_args
+            .iter()
+            .map(|expression| {
+                expression_solver
+                    .solve(expression)
+                    .map_err(|symbol_name| self.symbol_not_found(symbol_name, QasmType::RealValue))
+            })
+            .collect::<Result<Vec<f64>, RuntimeError>>()?;
+        let args = match bindings {
+            None => args.clone(),
+            Some(bindings) => {
+                let argument_solver = ArgumentSolver::new(&bindings.1);
+                args.iter()
+                    .map(|argument| {
+                        argument_solver.solve(argument).map_err(|symbol_name| {
+                            self.symbol_not_found(symbol_name, QasmType::QuantumRegister)
+                        })
+                    })
+                    .collect::<Result<Vec<ast::Argument>, RuntimeError>>()?
+            }
+        };
+        for args in self.expand_arguments(&args) {
+            self.gate(name, &real_args, &args)?;
+        }
+        Ok(())
+    }
+
+    /// Apply the gate `name` on qubits, emitting the gates of the basis.
+    fn gate(
+        &mut self,
+        name: &str,
+        real_args: &[f64],
+        args: &[ast::Argument],
+    ) -> Result<(), RuntimeError> {
+        match (name, self.basis) {
+            ("U", Basis::Primitive) => self.emit_gate("U", real_args, args),
+            ("U", Basis::U3Cx) => self.emit_gate("u3", real_args, args),
+            ("U", Basis::RzSxCx) => {
+                let (theta, phi, lambda) = (real_args[0], real_args[1], real_args[2]);
+                if theta == 0.0 {
+                    self.emit_gate("rz", &[phi + lambda], args);
+                } else {
+                    // U(theta, phi, lambda) = Rz(phi + pi) SX Rz(theta + pi) SX Rz(lambda),
+                    // up to a global phase.
+                    self.emit_gate("rz", &[lambda], args);
+                    self.emit_gate("sx", &[], args);
+                    self.emit_gate("rz", &[theta + PI], args);
+                    self.emit_gate("sx", &[], args);
+                    self.emit_gate("rz", &[phi + PI], args);
+                }
+            }
+            ("CX", Basis::Primitive) => self.emit_gate("CX", &[], args),
+            ("CX", _) => self.emit_gate("cx", &[], args),
+            (macro_name, _) => {
+                let definition = match self.semantics.macro_definitions.get(macro_name) {
+                    None => {
+                        return Err(RuntimeError::UndefinedGate {
+                            location: self.boundaries.0,
+                            symbol_name: macro_name.into(),
+                        })
+                    }
+                    Some(definition) => definition,
+                };
+                let bindings: BindingMappings = (
+                    HashMap::from_iter(definition.1.iter().cloned().zip(real_args.iter().copied())),
+                    HashMap::from_iter(definition.2.iter().cloned().zip(args.iter().cloned())),
+                );
+                for operation in &definition.3 {
+                    match operation {
+                        ast::GateOperation::Unitary(unitary) => {
+                            self.unitary(unitary, Some(&bindings))?
+                        }
+                        ast::GateOperation::Barrier(ast::BarrierPragma(barrier_args))
+                            if self.condition.is_none() =>
+                        {
+                            let argument_solver = ArgumentSolver::new(&bindings.1);
+                            let barrier_args = barrier_args
+                                .iter()
+                                .map(|argument| {
+                                    argument_solver.solve(argument).map_err(|symbol_name| {
+                                        self.symbol_not_found(
+                                            symbol_name,
+                                            QasmType::QuantumRegister,
+                                        )
+                                    })
+                                })
+                                .collect::<Result<Vec<ast::Argument>, RuntimeError>>()?;
+                            self.emit(ast::Statement::Barrier(ast::BarrierPragma(barrier_args)));
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Expand the whole registers in `args` into one list of arguments per
+    /// qubit, as the runtime does.
+    fn expand_arguments(&self, args: &[ast::Argument]) -> Vec<Vec<ast::Argument>> {
+        let size = args.iter().find_map(|argument| match argument {
+            ast::Argument::Id(name) => Some(
+                self.semantics
+                    .register_table
+                    .get(name)
+                    .expect("after `check_semantics()`, the register exists")
+                    .2,
+            ),
+            _ => None,
+        });
+        match size {
+            None => vec![args.to_vec()],
+            Some(size) => (0..size)
+                .map(|index| {
+                    args.iter()
+                        .map(|argument| match argument {
+                            ast::Argument::Id(name) => ast::Argument::Item(name.clone(), index),
+                            item => item.clone(),
+                        })
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    fn symbol_not_found(&self, symbol_name: String, expected: QasmType) -> RuntimeError {
+        RuntimeError::SymbolNotFound {
+            location: self.boundaries.0,
+            symbol_name,
+            expected,
+        }
+    }
+}
+
+/// A register name and the index of a qubit in the register.
+pub(crate) type Qubit = (String, usize);
+
+/// Return the size of each register of `program`, either quantum or
+/// classical.
+pub(crate) fn register_sizes(program: &ast::OpenQasmProgram) -> HashMap<String, usize> {
+    program
+        .program
+        .iter()
+        .filter_map(|span| match &*span.node {
+            ast::Statement::QRegDecl(name, size) | ast::Statement::CRegDecl(name, size) => {
+                Some((name.clone(), *size))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Return the qubits, or bits, of each application of an operation on
+/// `args`, which can be whole registers.
+pub(crate) fn expand(args: &[ast::Argument], sizes: &HashMap<String, usize>) -> Vec<Vec<Qubit>> {
+    let size = args.iter().find_map(|argument| match argument {
+        ast::Argument::Id(name) => Some(sizes.get(name).copied().unwrap_or_default()),
+        _ => None,
+    });
+    (0..size.unwrap_or(1))
+        .map(|index| {
+            args.iter()
+                .map(|argument| match argument {
+                    ast::Argument::Id(name) => (name.clone(), index),
+                    ast::Argument::Item(name, index) => (name.clone(), *index),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Return the expression for `value`. Negative numbers are negations, as the
+/// parser reads them, since the grammar has no negative literals.
+fn real(value: f64) -> ast::Expression {
+    if value < 0.0 {
+        ast::Expression::Minus(Box::new(ast::Expression::Real(-value)))
+    } else {
+        ast::Expression::Real(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use crate::statevector::Complex;
+    use crate::{parse_and_link, simulate};
+
+    #[test]
+    fn test_basis_from_str() {
+        assert_eq!("U,CX".parse(), Ok(Basis::Primitive));
+        assert_eq!("rz,sx,cx".parse(), Ok(Basis::RzSxCx));
+        assert_eq!("cx, u3".parse(), Ok(Basis::U3Cx));
+        assert!("u,cx".parse::<Basis>().is_err());
+    }
+
+    #[test]
+    fn test_transpiled_programs_are_equivalent() {
+        let source = indoc!(
+            "
+            OPENQASM 2.0;
+            include \"qelib1.inc\";
+            gate entangle(theta) a, b { ry(theta) a; cx a, b; }
+            qreg q[3];
+            qreg r[3];
+            h q;
+            entangle(pi/3) q[0], r[0];
+            rx(0.3) q[1];
+            cu3(0.1, 0.2, 0.3) q[1], r[1];
+            ccx q[0], q[1], q[2];
+            swap q, r;
+            cz r[1], q[2];
+            u2(-pi/4, 1.5) r[2];
+            sxdg r[0];
+            "
+        );
+        let program = parse_and_link(source).unwrap();
+        let expected = simulate(&program).unwrap();
+        for basis in Basis::all() {
+            let transpiled = transpile(&program, *basis).unwrap();
+            let reparsed = parse_and_link(&transpiled.to_string()).unwrap();
+            let computation = simulate(&reparsed).unwrap();
+            let overlap: Complex = expected
+                .statevector()
+                .as_complex_bases()
+                .iter()
+                .zip(computation.statevector().as_complex_bases())
+                .map(|(expected, actual)| expected.conj() * actual)
+                .sum();
+            assert!(
+                (overlap.norm() - 1.0).abs() < 1e-9,
+                "basis {} gives a different state",
+                basis
+            );
+        }
+    }
+
+    #[test]
+    fn test_whole_registers_and_conditionals_are_expanded() {
+        let source = indoc!(
+            "
+            OPENQASM 2.0;
+            include \"qelib1.inc\";
+            qreg q[2];
+            creg c[2];
+            barrier q;
+            measure q -> c;
+            if (c == 1) z q;
+            "
+        );
+        let program = parse_and_link(source).unwrap();
+        let transpiled = transpile(&program, Basis::RzSxCx).unwrap();
+        assert_eq!(
+            transpiled.to_string(),
+            indoc!(
+                "
+                OPENQASM 2.0;
+                include \"qelib1.inc\";
+                qreg q[2];
+                creg c[2];
+                barrier q;
+                measure q -> c;
+                if (c == 1) rz(3.141592653589793) q[0];
+                if (c == 1) rz(3.141592653589793) q[1];
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_opaque_gates_cannot_be_inlined() {
+        let source = indoc!(
+            "
+            OPENQASM 2.0;
+            opaque gate magic a;
+            qreg q[1];
+            magic q[0];
+            "
+        );
+        let program = parse_and_link(source).unwrap();
+        let error = transpile(&program, Basis::Primitive).unwrap_err();
+        assert!(matches!(
+            error,
+            RuntimeError::UndefinedGate { symbol_name, .. } if symbol_name == "magic"
+        ));
+    }
+}
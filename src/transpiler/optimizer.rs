@@ -0,0 +1,547 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::complex::Complex;
+use crate::error::RuntimeError;
+use crate::grammar::ast;
+use crate::interpreter::expression_solver::ExpressionSolver;
+
+use super::{expand, real, register_sizes, transpile, Basis, Qubit};
+
+/// Tolerance for considering two angles the same.
+const TOLERANCE: f64 = 1e-9;
+
+/// The size of a flat circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CircuitStats {
+    /// Number of gates, including those under conditionals.
+    pub gate_count: usize,
+    /// Number of layers of gates, where the gates of a layer act on
+    /// different qubits.
+    pub depth: usize,
+}
+
+/// An optimized program along with the size of its circuit before and after
+/// the optimization.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Optimization {
+    /// The optimized program, made of `U` and `CX` gates.
+    pub program: ast::OpenQasmProgram,
+    /// Size of the circuit once flattened, before optimizing it.
+    pub before: CircuitStats,
+    /// Size of the optimized circuit.
+    pub after: CircuitStats,
+}
+
+/// Flatten `program` into `U` and `CX` gates, as [`transpile()`] does, and
+/// optimize the circuit. The optimizer cancels adjacent `CX` gates on the
+/// same qubits, merges consecutive gates on a qubit into one `U`, drops the
+/// gates equivalent to the identity, and moves diagonal gates through the
+/// controls of `CX` gates for merging them with the gates before. The
+/// optimized program is the same up to a global phase.
+///
+/// Gates are never combined across a `barrier`, a measurement, a reset or a
+/// conditional on the same qubits, nor across a `snapshot`.
+///
+/// [`transpile()`]: ./fn.transpile.html
+///
+/// # Errors
+///
+/// The function fails like [`transpile()`] does.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::parse_and_link;
+/// use qasmsim::transpiler::optimize;
+///
+/// let program = parse_and_link(r#"
+/// OPENQASM 2.0;
+/// include "qelib1.inc";
+/// qreg q[2];
+/// h q[0];
+/// h q[0];
+/// cx q[0], q[1];
+/// cx q[0], q[1];
+/// "#)?;
+/// let optimization = optimize(&program)?;
+///
+/// assert_eq!(optimization.before.gate_count, 4);
+/// assert_eq!(optimization.after.gate_count, 0);
+/// assert_eq!(optimization.program.to_string(), "OPENQASM 2.0;\nqreg q[2];\n");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn optimize(program: &ast::OpenQasmProgram) -> Result<Optimization, RuntimeError> {
+    let flat = transpile(program, Basis::Primitive)?;
+    let before = stats(&flat);
+    let mut optimizer = Optimizer::new(&flat);
+    for index in 0..optimizer.slots.len() {
+        optimizer.add(index);
+    }
+    let program = optimizer.into_program(&flat);
+    let after = stats(&program);
+    Ok(Optimization {
+        program,
+        before,
+        after,
+    })
+}
+
+/// Return the size of the circuit of `program`, a flat program such as the
+/// result of [`transpile()`]. Calls to gates other than `U` and `CX` count as
+/// one gate.
+///
+/// [`transpile()`]: ./fn.transpile.html
+pub fn stats(program: &ast::OpenQasmProgram) -> CircuitStats {
+    let sizes = register_sizes(program);
+    let mut layers: HashMap<Qubit, usize> = HashMap::new();
+    let mut stats = CircuitStats::default();
+    for span in &program.program {
+        let unitary = match &*span.node {
+            ast::Statement::QuantumOperation(ast::QuantumOperation::Unitary(unitary))
+            | ast::Statement::Conditional(_, _, ast::QuantumOperation::Unitary(unitary)) => unitary,
+            _ => continue,
+        };
+        for qubits in expand(&unitary.2, &sizes) {
+            let layer = 1 + qubits
+                .iter()
+                .map(|qubit| layers.get(qubit).copied().unwrap_or_default())
+                .max()
+                .unwrap_or_default();
+            for qubit in qubits {
+                layers.insert(qubit, layer);
+            }
+            stats.gate_count += 1;
+            stats.depth = stats.depth.max(layer);
+        }
+    }
+    stats
+}
+
+/// The parameters `theta`, `phi` and `lambda` of a `U` gate.
+type Angles = [f64; 3];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Gate {
+    U(Angles, Qubit),
+    CX(Qubit, Qubit),
+}
+
+/// A statement of the flat program, with its gate if the statement is an
+/// unconditional `U` or `CX`.
+#[derive(Debug)]
+struct Slot {
+    gate: Option<Gate>,
+    removed: bool,
+    changed: bool,
+}
+
+struct Optimizer {
+    slots: Vec<Slot>,
+    /// The statements touching each qubit, by index, not removed yet.
+    statements_by_qubit: HashMap<Qubit, Vec<usize>>,
+    /// The qubits each statement touches.
+    qubits: Vec<Vec<Qubit>>,
+}
+
+impl Optimizer {
+    fn new(program: &ast::OpenQasmProgram) -> Self {
+        let sizes = register_sizes(program);
+        let all_qubits: Vec<Qubit> = program
+            .program
+            .iter()
+            .filter_map(|span| match &*span.node {
+                ast::Statement::QRegDecl(name, size) => Some((name, *size)),
+                _ => None,
+            })
+            .flat_map(|(name, size)| (0..size).map(move |index| (name.clone(), index)))
+            .collect();
+        let mut slots = vec![];
+        let mut qubits = vec![];
+        for span in &program.program {
+            let (gate, touched) = match &*span.node {
+                ast::Statement::QuantumOperation(ast::QuantumOperation::Unitary(unitary)) => {
+                    let gate = gate(unitary);
+                    (gate, expand(&unitary.2, &sizes).concat())
+                }
+                ast::Statement::QuantumOperation(operation)
+                | ast::Statement::Conditional(_, _, operation) => {
+                    let args = match operation {
+                        ast::QuantumOperation::Unitary(unitary) => unitary.2.clone(),
+                        ast::QuantumOperation::Measure(source, _) => vec![source.clone()],
+                        ast::QuantumOperation::Reset(target) => vec![target.clone()],
+                    };
+                    (None, expand(&args, &sizes).concat())
+                }
+                ast::Statement::Barrier(ast::BarrierPragma(args)) => {
+                    (None, expand(args, &sizes).concat())
+                }
+                ast::Statement::Snapshot(_) => (None, all_qubits.clone()),
+                _ => (None, vec![]),
+            };
+            slots.push(Slot {
+                gate,
+                removed: false,
+                changed: false,
+            });
+            qubits.push(touched);
+        }
+        Optimizer {
+            slots,
+            statements_by_qubit: HashMap::new(),
+            qubits,
+        }
+    }
+
+    /// Add the statement at `index` after those before it, combining it
+    /// with them if possible.
+    fn add(&mut self, index: usize) {
+        let combined = match self.slots[index].gate.clone() {
+            Some(Gate::U(angles, qubit)) => self.add_u(angles, &qubit),
+            Some(Gate::CX(control, target)) => self.add_cx(&control, &target),
+            None => false,
+        };
+        if combined {
+            self.slots[index].removed = true;
+            return;
+        }
+        for qubit in &self.qubits[index] {
+            self.statements_by_qubit
+                .entry(qubit.clone())
+                .or_default()
+                .push(index);
+        }
+    }
+
+    /// Merge the gate into the last gate on `qubit`, moving the gate before
+    /// the `CX` gates controlled by `qubit` if the gate is diagonal. Return
+    /// whether the gate disappears.
+    fn add_u(&mut self, angles: Angles, qubit: &Qubit) -> bool {
+        if is_identity(angles) {
+            return true;
+        }
+        let previous = self.statements_before(qubit).find(|&index| {
+            !matches!(&self.slots[index].gate, Some(Gate::CX(control, _))
+                if control == qubit && is_diagonal(angles))
+        });
+        let index = match previous {
+            Some(index) => index,
+            None => return false,
+        };
+        let previous_angles = match &self.slots[index].gate {
+            Some(Gate::U(previous_angles, _)) => *previous_angles,
+            _ => return false,
+        };
+        let merged = merge(previous_angles, angles);
+        if is_identity(merged) {
+            self.remove(index);
+        } else {
+            let slot = &mut self.slots[index];
+            slot.gate = Some(Gate::U(merged, qubit.clone()));
+            slot.changed = true;
+        }
+        true
+    }
+
+    /// Cancel the gate with an equal `CX` before it, if the gates in between
+    /// commute with them. Return whether the gate disappears.
+    fn add_cx(&mut self, control: &Qubit, target: &Qubit) -> bool {
+        let on_control = self
+            .statements_before(control)
+            .find(|&index| !match &self.slots[index].gate {
+                Some(Gate::U(angles, _)) => is_diagonal(*angles),
+                Some(Gate::CX(other_control, other_target)) => {
+                    other_control == control && other_target != target
+                }
+                None => false,
+            });
+        let on_target = self.statements_before(target).find(|&index| {
+            !matches!(&self.slots[index].gate, Some(Gate::CX(other_control, other_target))
+                if other_target == target && other_control != control)
+        });
+        match (on_control, on_target) {
+            (Some(index), Some(other_index)) if index == other_index => {
+                let equal = Gate::CX(control.clone(), target.clone());
+                if self.slots[index].gate.as_ref() != Some(&equal) {
+                    return false;
+                }
+                self.remove(index);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Return the statements touching `qubit`, from the last one backwards.
+    fn statements_before<'a>(&'a self, qubit: &Qubit) -> impl Iterator<Item = usize> + 'a {
+        self.statements_by_qubit
+            .get(qubit)
+            .into_iter()
+            .flat_map(|indices| indices.iter().rev().copied())
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.slots[index].removed = true;
+        for qubit in &self.qubits[index] {
+            if let Some(indices) = self.statements_by_qubit.get_mut(qubit) {
+                indices.retain(|other| *other != index);
+            }
+        }
+    }
+
+    fn into_program(self, flat: &ast::OpenQasmProgram) -> ast::OpenQasmProgram {
+        let program = flat
+            .program
+            .iter()
+            .zip(self.slots)
+            .filter(|(_, slot)| !slot.removed)
+            .map(|(span, slot)| match slot.gate {
+                Some(Gate::U(angles, (name, index))) if slot.changed => ast::Span {
+                    boundaries: span.boundaries,
+                    node: Box::new(ast::Statement::QuantumOperation(
+                        ast::QuantumOperation::Unitary(ast::UnitaryOperation(
+                            "U".into(),
+                            angles.iter().copied().map(real).collect(),
+                            vec![ast::Argument::Item(name, index)],
+                        )),
+                    )),
+                },
+                _ => span.clone(),
+            })
+            .collect();
+        ast::OpenQasmProgram {
+            version: flat.version.clone(),
+            program,
+            trivia: vec![],
+        }
+    }
+}
+
+/// Return the gate of a `U` or `CX` call on single qubits.
+fn gate(unitary: &ast::UnitaryOperation) -> Option<Gate> {
+    let ast::UnitaryOperation(name, real_args, args) = unitary;
+    let qubits: Vec<Qubit> = args
+        .iter()
+        .map(|argument| match argument {
+            ast::Argument::Item(name, index) => Some((name.clone(), *index)),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    match (name.as_str(), qubits.as_slice()) {
+        ("U", [qubit]) => {
+            let bindings = HashMap::new();
+            let solver = ExpressionSolver::new(&bindings);
+            let values = real_args
+                .iter()
+                .map(|expression| solver.solve(expression).ok())
+                .collect::<Option<Vec<f64>>>()?;
+            match values.as_slice() {
+                [theta, phi, lambda] => Some(Gate::U([*theta, *phi, *lambda], qubit.clone())),
+                _ => None,
+            }
+        }
+        ("CX", [control, target]) => Some(Gate::CX(control.clone(), target.clone())),
+        _ => None,
+    }
+}
+
+fn is_diagonal(angles: Angles) -> bool {
+    normalize(angles[0]).abs() < TOLERANCE
+}
+
+fn is_identity(angles: Angles) -> bool {
+    is_diagonal(angles) && normalize(angles[1] + angles[2]).abs() < TOLERANCE
+}
+
+/// Return the angle equivalent to `angle` between -pi and pi, or 0 if the
+/// angle is close to 0.
+fn normalize(angle: f64) -> f64 {
+    let normalized = angle - 2.0 * PI * ((angle + PI) / (2.0 * PI)).floor();
+    if normalized.abs() < TOLERANCE || (2.0 * PI - normalized).abs() < TOLERANCE {
+        0.0
+    } else {
+        normalized
+    }
+}
+
+type Matrix = [[Complex; 2]; 2];
+
+fn matrix([theta, phi, lambda]: Angles) -> Matrix {
+    let (cos, sin) = ((theta / 2.0).cos(), (theta / 2.0).sin());
+    [
+        [Complex::new(cos, 0.0), -Complex::from_polar(&sin, &lambda)],
+        [
+            Complex::from_polar(&sin, &phi),
+            Complex::from_polar(&cos, &(phi + lambda)),
+        ],
+    ]
+}
+
+/// Return the angles of the gate applying `first` and then `second`, up to a
+/// global phase.
+fn merge(first: Angles, second: Angles) -> Angles {
+    let (a, b) = (matrix(second), matrix(first));
+    let product = |row: usize, column: usize| a[row][0] * b[0][column] + a[row][1] * b[1][column];
+    let (m00, m01, m10, m11) = (product(0, 0), product(0, 1), product(1, 0), product(1, 1));
+    let theta = 2.0 * m10.norm().atan2(m00.norm());
+    let (phi, lambda) = if m10.norm() < TOLERANCE {
+        (0.0, m11.arg() - m00.arg())
+    } else if m00.norm() < TOLERANCE {
+        (0.0, (-m01).arg() - m10.arg())
+    } else {
+        (m10.arg() - m00.arg(), (-m01).arg() - m00.arg())
+    };
+    [normalize(theta), normalize(phi), normalize(lambda)]
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use crate::{parse_and_link, simulate};
+
+    fn optimized(source: &str) -> Optimization {
+        optimize(&parse_and_link(source).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_adjacent_inverse_gates_cancel() {
+        let optimization = optimized(indoc!(
+            "
+            OPENQASM 2.0;
+            include \"qelib1.inc\";
+            qreg q[2];
+            h q;
+            h q;
+            cx q[0], q[1];
+            t q[0];
+            cx q[0], q[1];
+            tdg q[0];
+            s q[1];
+            sdg q[1];
+            "
+        ));
+        assert_eq!(
+            optimization.program.to_string(),
+            "OPENQASM 2.0;\nqreg q[2];\n"
+        );
+        assert_eq!(
+            optimization.before,
+            CircuitStats {
+                gate_count: 10,
+                depth: 7
+            }
+        );
+        assert_eq!(optimization.after, CircuitStats::default());
+    }
+
+    #[test]
+    fn test_rotations_merge_into_one_gate() {
+        let optimization = optimized(indoc!(
+            "
+            OPENQASM 2.0;
+            include \"qelib1.inc\";
+            qreg q[2];
+            u1(0.25) q[0];
+            rz(0.5) q[0];
+            cx q[0], q[1];
+            u1(0.25) q[0];
+            ry(0.5) q[1];
+            ry(0.25) q[1];
+            "
+        ));
+        let gates: Vec<Gate> = optimization
+            .program
+            .program
+            .iter()
+            .filter_map(|span| match &*span.node {
+                ast::Statement::QuantumOperation(ast::QuantumOperation::Unitary(unitary)) => {
+                    gate(unitary)
+                }
+                _ => None,
+            })
+            .collect();
+        let (q0, q1) = (("q".to_string(), 0), ("q".to_string(), 1));
+        match gates.as_slice() {
+            [Gate::U(first, first_qubit), Gate::CX(control, target), Gate::U(second, second_qubit)] =>
+            {
+                assert!(is_identity(merge(*first, [0.0, 0.0, -1.0])));
+                assert_eq!(first_qubit, &q0);
+                assert_eq!((control, target), (&q0, &q1));
+                assert!(is_identity(merge(*second, [-0.75, 0.0, 0.0])));
+                assert_eq!(second_qubit, &q1);
+            }
+            _ => panic!("unexpected gates {:?}", gates),
+        }
+        assert_eq!(
+            optimization.after,
+            CircuitStats {
+                gate_count: 3,
+                depth: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_barriers_and_measurements_are_fences() {
+        let source = indoc!(
+            "
+            OPENQASM 2.0;
+            include \"qelib1.inc\";
+            qreg q[2];
+            creg c[2];
+            h q[0];
+            barrier q;
+            h q[0];
+            x q[1];
+            measure q[1] -> c[1];
+            x q[1];
+            if (c == 1) x q[0];
+            x q[0];
+            "
+        );
+        let optimization = optimized(source);
+        assert_eq!(optimization.before.gate_count, 6);
+        assert_eq!(optimization.after.gate_count, 6);
+    }
+
+    #[test]
+    fn test_optimized_programs_are_equivalent() {
+        let source = indoc!(
+            "
+            OPENQASM 2.0;
+            include \"qelib1.inc\";
+            qreg q[3];
+            h q;
+            ccx q[0], q[1], q[2];
+            rz(0.3) q[0];
+            cx q[0], q[2];
+            t q[0];
+            cx q[0], q[2];
+            cu3(0.1, 0.2, 0.3) q[2], q[1];
+            u3(0.4, 0.5, 0.6) q[1];
+            u3(-0.4, -0.6, -0.5) q[1];
+            swap q[0], q[1];
+            swap q[0], q[1];
+            "
+        );
+        let program = parse_and_link(source).unwrap();
+        let optimization = optimize(&program).unwrap();
+        assert!(optimization.after.gate_count < optimization.before.gate_count);
+        let expected = simulate(&program).unwrap();
+        let computation = simulate(&optimization.program).unwrap();
+        let overlap: Complex = expected
+            .statevector()
+            .as_complex_bases()
+            .iter()
+            .zip(computation.statevector().as_complex_bases())
+            .map(|(expected, actual)| expected.conj() * actual)
+            .sum();
+        assert!((overlap.norm() - 1.0).abs() < 1e-9);
+    }
+}
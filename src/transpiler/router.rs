@@ -0,0 +1,678 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error;
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::RuntimeError;
+use crate::grammar::ast;
+use crate::grammar::lexer::Location;
+
+use super::{expand, register_sizes, transpile, Basis, Qubit};
+
+/// The connectivity of a device, as a list of edges between the physical
+/// qubits that two-qubit gates can act on. Edges work in both directions.
+///
+/// With the `serde` feature, the map is (de)serialized as the list of edges,
+/// such as the JSON `[[0, 1], [1, 2]]`.
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::transpiler::CouplingMap;
+///
+/// let line = CouplingMap::new(vec![(0, 1), (1, 2)]);
+/// assert_eq!(line.size(), 3);
+/// assert!(line.are_coupled(1, 0));
+/// assert_eq!(line.shortest_path(0, 2), Some(vec![0, 1, 2]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct CouplingMap {
+    edges: Vec<(usize, usize)>,
+}
+
+impl CouplingMap {
+    /// Create a coupling map with the given edges.
+    pub fn new(edges: Vec<(usize, usize)>) -> Self {
+        CouplingMap { edges }
+    }
+
+    /// Return the edges of the map.
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    /// Return the number of physical qubits, which is one more than the
+    /// greatest qubit in the edges.
+    pub fn size(&self) -> usize {
+        self.edges
+            .iter()
+            .map(|&(from, to)| from.max(to) + 1)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Return whether there is an edge between the qubits `a` and `b`.
+    pub fn are_coupled(&self, a: usize, b: usize) -> bool {
+        self.edges
+            .iter()
+            .any(|&edge| edge == (a, b) || edge == (b, a))
+    }
+
+    /// Return the qubits of one of the shortest paths from `from` to `to`,
+    /// both included, or `None` if `to` cannot be reached.
+    pub fn shortest_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let mut previous = HashMap::new();
+        let mut pending = VecDeque::from(vec![from]);
+        let mut visited: HashSet<usize> = vec![from].into_iter().collect();
+        while let Some(qubit) = pending.pop_front() {
+            if qubit == to {
+                let mut path = vec![to];
+                while let Some(&before) = previous.get(path.last().unwrap()) {
+                    path.push(before);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &(a, b) in &self.edges {
+                let neighbor = match (a == qubit, b == qubit) {
+                    (true, _) => b,
+                    (_, true) => a,
+                    _ => continue,
+                };
+                if visited.insert(neighbor) {
+                    previous.insert(neighbor, qubit);
+                    pending.push_back(neighbor);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// A program routed onto a device, with the positions of the qubits of the
+/// original program in the device before and after running the program.
+///
+/// Qubits of the original program are numbered in order of declaration, and
+/// by index within their registers. The layouts map each of these qubits to
+/// a physical qubit, which is its index in the single quantum register of the
+/// routed program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Routing {
+    /// The routed program.
+    pub program: ast::OpenQasmProgram,
+    /// The physical qubit of each qubit at the start of the program.
+    pub initial_layout: Vec<usize>,
+    /// The physical qubit of each qubit at the end of the program.
+    pub final_layout: Vec<usize>,
+}
+
+impl Routing {
+    /// Return the index, in the state-vector of the routed program, of the
+    /// amplitude with index `index` in the state-vector of the original
+    /// program at the end of the simulation. The physical qubits that hold
+    /// no qubit of the original program end in `|0⟩`.
+    ///
+    /// The routed program includes `qelib1.inc` for the `swap` gate, so it
+    /// needs linking before the simulation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qasmsim::{parse_and_link, simulate};
+    /// use qasmsim::transpiler::{route, CouplingMap};
+    ///
+    /// let program = parse_and_link(r#"
+    /// OPENQASM 2.0;
+    /// include "qelib1.inc";
+    /// qreg q[3];
+    /// x q[0];
+    /// h q[1];
+    /// cx q[0], q[2];
+    /// "#)?;
+    /// let routing = route(&program, &CouplingMap::new(vec![(0, 1), (1, 2)]), None)?;
+    /// assert_eq!(routing.final_layout, vec![1, 0, 2]);
+    ///
+    /// let original = simulate(&program)?;
+    /// let routed_source = routing.program.to_string();
+    /// let routed = simulate(&parse_and_link(&routed_source).expect("routed program"))?;
+    /// for (index, amplitude) in original.statevector().as_complex_bases().iter().enumerate() {
+    ///     let routed_amplitude = routed.statevector().as_complex_bases()[routing.physical_index(index)];
+    ///     assert!((amplitude - routed_amplitude).norm() < 1e-9);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn physical_index(&self, index: usize) -> usize {
+        self.final_layout
+            .iter()
+            .enumerate()
+            .filter(|(qubit, _)| index & (1 << qubit) != 0)
+            .map(|(_, physical)| 1 << physical)
+            .sum()
+    }
+}
+
+/// Represent the errors routing a program.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoutingError {
+    /// The program cannot be flattened.
+    RuntimeError(RuntimeError),
+    /// The device has fewer qubits than the program.
+    NotEnoughQubits {
+        /// Number of qubits declared in the program.
+        expected: usize,
+        /// Number of qubits of the device.
+        given: usize,
+    },
+    /// The initial layout does not assign a different physical qubit of the
+    /// device to each qubit of the program.
+    InvalidLayout(Vec<usize>),
+    /// There is no path between two physical qubits a gate acts on.
+    Disconnected(usize, usize),
+    /// A conditional measurement of several qubits writes the classical
+    /// register of its condition. The routed program measures each qubit in
+    /// a different statement, so the condition could change in between.
+    ConditionalMeasurement(String),
+}
+
+impl fmt::Display for RoutingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoutingError::RuntimeError(error) => write!(f, "{}", error),
+            RoutingError::NotEnoughQubits { expected, given } => write!(
+                f,
+                "the program has {} qubits but the device has {}",
+                expected, given
+            ),
+            RoutingError::InvalidLayout(layout) => {
+                write!(f, "invalid layout {:?} for the program", layout)
+            }
+            RoutingError::Disconnected(a, b) => write!(
+                f,
+                "physical qubits {} and {} are not connected in the coupling map",
+                a, b
+            ),
+            RoutingError::ConditionalMeasurement(register) => write!(
+                f,
+                "cannot route a measurement of several qubits into `{}` conditioned on `{}`",
+                register, register
+            ),
+        }
+    }
+}
+
+impl error::Error for RoutingError {}
+
+impl From<RuntimeError> for RoutingError {
+    fn from(error: RuntimeError) -> Self {
+        RoutingError::RuntimeError(error)
+    }
+}
+
+/// Flatten `program` into `U` and `CX` gates, as [`transpile()`] does, and map
+/// it onto the device described by `coupling_map`, inserting `swap` gates so
+/// every `CX` acts on coupled physical qubits.
+///
+/// The routed program declares one quantum register with a qubit per
+/// physical qubit, named `q` unless a classical register is. The qubits of
+/// `program` start at the physical qubits of `initial_layout` or, if it is
+/// `None`, at the physical qubits with the same number. The swaps move the
+/// control of each `CX` along the shortest path to its target, so the
+/// qubits are permuted at the end of the routed program, as the
+/// [`Routing::final_layout`] tells.
+///
+/// [`transpile()`]: ./fn.transpile.html
+/// [`Routing::final_layout`]: ./struct.Routing.html#structfield.final_layout
+///
+/// # Errors
+///
+/// The function fails with [`RoutingError::RuntimeError`] when
+/// [`transpile()`] does, with [`RoutingError::ConditionalMeasurement`] if a
+/// conditional measurement of a whole register writes the register of its
+/// condition, and with other [`RoutingError`] variants if the program does
+/// not fit the device.
+///
+/// [`RoutingError`]: ./enum.RoutingError.html
+/// [`RoutingError::RuntimeError`]: ./enum.RoutingError.html#variant.RuntimeError
+/// [`RoutingError::ConditionalMeasurement`]: ./enum.RoutingError.html#variant.ConditionalMeasurement
+///
+/// # Examples
+///
+/// ```
+/// use qasmsim::parse_and_link;
+/// use qasmsim::transpiler::{route, CouplingMap};
+///
+/// let program = parse_and_link(r#"
+/// OPENQASM 2.0;
+/// include "qelib1.inc";
+/// qreg q[3];
+/// cx q[0], q[2];
+/// "#)?;
+/// let coupling_map = CouplingMap::new(vec![(0, 1), (1, 2)]);
+/// let routing = route(&program, &coupling_map, None)?;
+///
+/// assert_eq!(routing.program.to_string(), "\
+/// OPENQASM 2.0;
+/// include \"qelib1.inc\";
+/// qreg q[3];
+/// swap q[0], q[1];
+/// CX q[1], q[2];
+/// ");
+/// assert_eq!(routing.final_layout, vec![1, 0, 2]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn route(
+    program: &ast::OpenQasmProgram,
+    coupling_map: &CouplingMap,
+    initial_layout: Option<&[usize]>,
+) -> Result<Routing, RoutingError> {
+    let flat = transpile(program, Basis::Primitive)?;
+    let qubits: Vec<Qubit> = flat
+        .program
+        .iter()
+        .filter_map(|span| match &*span.node {
+            ast::Statement::QRegDecl(name, size) => Some((name, *size)),
+            _ => None,
+        })
+        .flat_map(|(name, size)| (0..size).map(move |index| (name.clone(), index)))
+        .collect();
+    let device_size = coupling_map.size();
+    if qubits.len() > device_size {
+        return Err(RoutingError::NotEnoughQubits {
+            expected: qubits.len(),
+            given: device_size,
+        });
+    }
+    let layout = match initial_layout {
+        Some(layout) => layout.to_vec(),
+        None => (0..qubits.len()).collect(),
+    };
+    let distinct: HashSet<&usize> = layout.iter().collect();
+    if layout.len() != qubits.len()
+        || distinct.len() != layout.len()
+        || layout.iter().any(|&physical| physical >= device_size)
+    {
+        return Err(RoutingError::InvalidLayout(layout));
+    }
+
+    let mut name = "q".to_string();
+    while flat
+        .program
+        .iter()
+        .any(|span| matches!(&*span.node, ast::Statement::CRegDecl(creg, _) if *creg == name))
+    {
+        name.push('_');
+    }
+    let mut router = Router {
+        coupling_map,
+        name,
+        sizes: register_sizes(&flat),
+        indices: qubits
+            .into_iter()
+            .enumerate()
+            .map(|(index, qubit)| (qubit, index))
+            .collect(),
+        layout: layout.clone(),
+        boundaries: flat.program.first().map(|span| span.boundaries),
+        statements: vec![],
+    };
+    router.emit(ast::Statement::Include("qelib1.inc".into()));
+    let mut declared = false;
+    for span in &flat.program {
+        router.boundaries = Some(span.boundaries);
+        match &*span.node {
+            ast::Statement::QRegDecl(..) if declared => (),
+            ast::Statement::QRegDecl(..) => {
+                declared = true;
+                router.emit(ast::Statement::QRegDecl(router.name.clone(), device_size));
+            }
+            ast::Statement::QuantumOperation(operation) => {
+                for operation in router.operation(operation)? {
+                    router.emit(ast::Statement::QuantumOperation(operation));
+                }
+            }
+            ast::Statement::Conditional(register, value, operation) => {
+                let operations = router.operation(operation)?;
+                if let (
+                    ast::Argument::Id(condition),
+                    ast::QuantumOperation::Measure(_, ast::Argument::Id(target)),
+                ) = (register, operation)
+                {
+                    if operations.len() > 1 && condition == target {
+                        return Err(RoutingError::ConditionalMeasurement(target.clone()));
+                    }
+                }
+                for operation in operations {
+                    router.emit(ast::Statement::Conditional(
+                        register.clone(),
+                        *value,
+                        operation,
+                    ));
+                }
+            }
+            ast::Statement::Barrier(ast::BarrierPragma(args)) => {
+                let mut qubits: Vec<Qubit> = vec![];
+                for qubit in expand(args, &router.sizes).concat() {
+                    if !qubits.contains(&qubit) {
+                        qubits.push(qubit);
+                    }
+                }
+                let args = qubits.iter().map(|qubit| router.physical(qubit)).collect();
+                router.emit(ast::Statement::Barrier(ast::BarrierPragma(args)));
+            }
+            statement => router.emit(statement.clone()),
+        }
+    }
+    Ok(Routing {
+        program: ast::OpenQasmProgram {
+            version: flat.version.clone(),
+            program: router.statements,
+            trivia: vec![],
+        },
+        initial_layout: layout,
+        final_layout: router.layout,
+    })
+}
+
+struct Router<'a> {
+    coupling_map: &'a CouplingMap,
+    /// Name of the quantum register of the device.
+    name: String,
+    sizes: HashMap<String, usize>,
+    /// The number of each qubit of the program.
+    indices: HashMap<Qubit, usize>,
+    /// The physical qubit of each qubit of the program.
+    layout: Vec<usize>,
+    /// Boundaries of the statement being routed.
+    boundaries: Option<(Location, Location)>,
+    statements: Vec<ast::Span<ast::Statement>>,
+}
+
+impl Router<'_> {
+    fn emit(&mut self, statement: ast::Statement) {
+        let boundaries = self
+            .boundaries
+            .unwrap_or((Location::new_at(0), Location::new_at(0)));
+        self.statements.push(ast::Span {
+            boundaries,
+            node: Box::new(statement),
+        });
+    }
+
+    /// Return the operations on physical qubits for `operation`, applied to
+    /// each qubit of its register arguments. Emit the swaps the `CX` gates
+    /// need before them.
+    fn operation(
+        &mut self,
+        operation: &ast::QuantumOperation,
+    ) -> Result<Vec<ast::QuantumOperation>, RoutingError> {
+        let mut operations = vec![];
+        match operation {
+            ast::QuantumOperation::Unitary(ast::UnitaryOperation(name, real_args, args)) => {
+                for qubits in expand(args, &self.sizes) {
+                    if let [control, target] = qubits.as_slice() {
+                        self.bring_together(control, target)?;
+                    }
+                    let args = qubits.iter().map(|qubit| self.physical(qubit)).collect();
+                    operations.push(ast::QuantumOperation::Unitary(ast::UnitaryOperation(
+                        name.clone(),
+                        real_args.clone(),
+                        args,
+                    )));
+                }
+            }
+            ast::QuantumOperation::Measure(source, target) => {
+                let args = [source.clone(), target.clone()];
+                for qubits in expand(&args, &self.sizes) {
+                    let target = ast::Argument::Item(qubits[1].0.clone(), qubits[1].1);
+                    operations.push(ast::QuantumOperation::Measure(
+                        self.physical(&qubits[0]),
+                        target,
+                    ));
+                }
+            }
+            ast::QuantumOperation::Reset(target) => {
+                for qubits in expand(std::slice::from_ref(target), &self.sizes) {
+                    operations.push(ast::QuantumOperation::Reset(self.physical(&qubits[0])));
+                }
+            }
+        }
+        Ok(operations)
+    }
+
+    /// Swap the physical qubit of `control` along the shortest path to the
+    /// physical qubit of `target` until they are coupled.
+    fn bring_together(&mut self, control: &Qubit, target: &Qubit) -> Result<(), RoutingError> {
+        let from = self.layout[self.indices[control]];
+        let to = self.layout[self.indices[target]];
+        let path = self
+            .coupling_map
+            .shortest_path(from, to)
+            .ok_or(RoutingError::Disconnected(from, to))?;
+        for pair in path.windows(2).take(path.len().saturating_sub(2)) {
+            self.swap(pair[0], pair[1]);
+        }
+        Ok(())
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        for physical in self.layout.iter_mut() {
+            if *physical == a {
+                *physical = b;
+            } else if *physical == b {
+                *physical = a;
+            }
+        }
+        let args = vec![
+            ast::Argument::Item(self.name.clone(), a),
+            ast::Argument::Item(self.name.clone(), b),
+        ];
+        self.emit(ast::Statement::QuantumOperation(
+            ast::QuantumOperation::Unitary(ast::UnitaryOperation("swap".into(), vec![], args)),
+        ));
+    }
+
+    fn physical(&self, qubit: &Qubit) -> ast::Argument {
+        ast::Argument::Item(self.name.clone(), self.layout[self.indices[qubit]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use crate::{parse_and_link, simulate, simulate_with_shots};
+
+    fn assert_equivalent(source: &str, routing: &Routing) {
+        let expected = simulate(&parse_and_link(source).unwrap()).unwrap();
+        let routed_source = routing.program.to_string();
+        let routed = simulate(&parse_and_link(&routed_source).unwrap()).unwrap();
+        let routed_bases = routed.statevector().as_complex_bases();
+        for (index, amplitude) in expected.statevector().as_complex_bases().iter().enumerate() {
+            let routed_amplitude = routed_bases[routing.physical_index(index)];
+            assert!((amplitude - routed_amplitude).norm() < 1e-9);
+        }
+        assert_eq!(expected.memory(), routed.memory());
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let ring = CouplingMap::new(vec![(0, 1), (1, 2), (2, 3), (3, 4), (4, 0)]);
+        assert_eq!(ring.size(), 5);
+        assert_eq!(ring.shortest_path(0, 3), Some(vec![0, 4, 3]));
+        assert_eq!(ring.shortest_path(2, 2), Some(vec![2]));
+        assert_eq!(
+            CouplingMap::new(vec![(0, 1), (2, 3)]).shortest_path(0, 3),
+            None
+        );
+    }
+
+    #[test]
+    fn test_routed_programs_are_equivalent() {
+        let source = indoc!(
+            "
+            OPENQASM 2.0;
+            include \"qelib1.inc\";
+            qreg a[2];
+            qreg b[3];
+            creg c[2];
+            h a;
+            ccx a[0], a[1], b[2];
+            cu3(0.1, 0.2, 0.3) b[2], a[0];
+            x b[0];
+            cx b[0], a[1];
+            cx b[2], a;
+            measure b[0] -> c[1];
+            if (c == 2) cx a[0], b[2];
+            rz(0.4) b[1];
+            "
+        );
+        let program = parse_and_link(source).unwrap();
+        let line = CouplingMap::new(vec![(0, 1), (1, 2), (2, 3), (3, 4), (4, 5)]);
+        for layout in &[None, Some(&[5, 3, 0, 1, 4][..])] {
+            let routing = route(&program, &line, *layout).unwrap();
+            assert_equivalent(source, &routing);
+            for span in &routing.program.program {
+                if let ast::Statement::QuantumOperation(ast::QuantumOperation::Unitary(
+                    ast::UnitaryOperation(name, _, args),
+                ))
+                | ast::Statement::Conditional(
+                    _,
+                    _,
+                    ast::QuantumOperation::Unitary(ast::UnitaryOperation(name, _, args)),
+                ) = &*span.node
+                {
+                    if let [ast::Argument::Item(_, a), ast::Argument::Item(_, b)] = args.as_slice()
+                    {
+                        assert!(line.are_coupled(*a, *b), "{} on {} and {}", name, a, b);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_device_register_does_not_clash() {
+        let program = parse_and_link(indoc!(
+            "
+            OPENQASM 2.0;
+            qreg r[2];
+            creg q[2];
+            CX r[1], r[0];
+            measure r -> q;
+            "
+        ))
+        .unwrap();
+        let routing = route(&program, &CouplingMap::new(vec![(0, 1)]), None).unwrap();
+        assert_eq!(
+            routing.program.to_string(),
+            indoc!(
+                "
+                OPENQASM 2.0;
+                include \"qelib1.inc\";
+                qreg q_[2];
+                creg q[2];
+                CX q_[1], q_[0];
+                measure q_[0] -> q[0];
+                measure q_[1] -> q[1];
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_whole_registers_in_measurements_and_barriers() {
+        let source = indoc!(
+            "
+            OPENQASM 2.0;
+            qreg q[2];
+            creg c[2];
+            barrier q, q[0];
+            measure q[0] -> c;
+            "
+        );
+        let program = parse_and_link(source).unwrap();
+        let routing = route(&program, &CouplingMap::new(vec![(0, 1)]), None).unwrap();
+        assert_equivalent(source, &routing);
+        assert_eq!(
+            routing.program.to_string(),
+            indoc!(
+                "
+                OPENQASM 2.0;
+                include \"qelib1.inc\";
+                qreg q[2];
+                creg c[2];
+                barrier q[0], q[1];
+                measure q[0] -> c[0];
+                measure q[0] -> c[1];
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_conditional_measurements() {
+        let source = indoc!(
+            "
+            OPENQASM 2.0;
+            qreg q[2];
+            creg c[2];
+            creg d[1];
+            U(pi, 0, pi) q;
+            if (d == 0) measure q -> c;
+            "
+        );
+        let program = parse_and_link(source).unwrap();
+        let pair = CouplingMap::new(vec![(0, 1)]);
+        let routing = route(&program, &pair, Some(&[1, 0])).unwrap();
+        let routed = parse_and_link(&routing.program.to_string()).unwrap();
+        assert_eq!(
+            simulate_with_shots(&routed, 10).unwrap().histogram(),
+            simulate_with_shots(&program, 10).unwrap().histogram()
+        );
+
+        let program = parse_and_link(&source.replace("d == 0", "c == 0")).unwrap();
+        assert_eq!(
+            route(&program, &pair, None),
+            Err(RoutingError::ConditionalMeasurement("c".into()))
+        );
+    }
+
+    #[test]
+    fn test_routing_errors() {
+        let program = parse_and_link(indoc!(
+            "
+            OPENQASM 2.0;
+            qreg q[3];
+            CX q[0], q[2];
+            "
+        ))
+        .unwrap();
+        let pair = CouplingMap::new(vec![(0, 1)]);
+        assert_eq!(
+            route(&program, &pair, None),
+            Err(RoutingError::NotEnoughQubits {
+                expected: 3,
+                given: 2
+            })
+        );
+        let split = CouplingMap::new(vec![(0, 1), (2, 3)]);
+        assert_eq!(
+            route(&program, &split, Some(&[0, 1, 1])),
+            Err(RoutingError::InvalidLayout(vec![0, 1, 1]))
+        );
+        assert_eq!(
+            route(&program, &split, Some(&[0, 1])),
+            Err(RoutingError::InvalidLayout(vec![0, 1]))
+        );
+        assert_eq!(
+            route(&program, &split, None),
+            Err(RoutingError::Disconnected(0, 2))
+        );
+    }
+}
@@ -5,6 +5,7 @@ extern crate qasmsim;
 use std::f64::consts::FRAC_1_SQRT_2;
 
 use qasmsim::statevector::{assert_approx_eq, Complex, StateVector};
+use qasmsim::SimulationOptions;
 
 #[test]
 fn endianess() {
@@ -177,7 +178,7 @@ fn test_quantum_experience_header_is_included() {
 
 #[test]
 fn test_measurements() {
-    let subtests = vec![
+    let subtests = [
         (
             "
      OPENQASM 2.0;
@@ -266,3 +267,183 @@ fn test_conditional() {
     assert_eq!(*result.memory().get("c").unwrap(), 0b10);
     assert_eq!(*result.memory().get("d").unwrap(), 0b01);
 }
+
+#[test]
+fn test_register_density_matrices() {
+    let source = "
+  OPENQASM 2.0;
+  include \"qelib1.inc\";
+  qreg a[1];
+  qreg b[2];
+  h a[0];
+  cx a[0], b[1];
+  x b[0];
+  ";
+    let result = &qasmsim::run(source, None).unwrap();
+    assert_eq!(result.quantum_registers().get("a").unwrap(), &(0..1));
+    assert_eq!(result.quantum_registers().get("b").unwrap(), &(1..3));
+
+    let rho_a = result.register_density_matrix("a").unwrap();
+    assert!((rho_a.von_neumann_entropy() - 1.0).abs() < 1e-9);
+    assert!((rho_a.purity() - 0.5).abs() < 1e-9);
+
+    let rho_b = result.register_density_matrix("b").unwrap();
+    assert_eq!(rho_b.qubit_width(), 2);
+    assert!((rho_b.von_neumann_entropy() - 1.0).abs() < 1e-9);
+
+    let [_, _, z] = result.statevector().bloch_vector(1);
+    assert!((z + 1.0).abs() < 1e-9);
+    assert!(result.register_density_matrix("c").is_none());
+}
+
+#[test]
+fn test_simulation_from_initial_state() {
+    let source = "
+  OPENQASM 2.0;
+  include \"qelib1.inc\";
+  qreg q[2];
+  creg c[2];
+  cx q[0], q[1];
+  measure q -> c;
+  ";
+    let initial_state = StateVector::from_complex_bases(vec![
+        Complex::from(0.0),
+        Complex::from(1.0),
+        Complex::from(0.0),
+        Complex::from(0.0),
+    ]);
+    let result = &qasmsim::run_from(source, None, initial_state).unwrap();
+    assert_eq!(*result.memory().get("c").unwrap(), 0b11);
+}
+
+#[test]
+fn test_snapshots() {
+    let source = "
+  OPENQASM 2.0;
+  include \"qelib1.inc\";
+  qreg q[2];
+  snapshot \"start\";
+  h q[0];
+  snapshot \"superposition\";
+  cx q[0], q[1];
+  ";
+    let result = &qasmsim::run(source, None).unwrap();
+    assert_eq!(result.snapshots().len(), 2);
+    assert_approx_eq(
+        &result.snapshots()["start"],
+        &StateVector::from_complex_bases(vec![
+            Complex::from(1.0),
+            Complex::from(0.0),
+            Complex::from(0.0),
+            Complex::from(0.0),
+        ]),
+    );
+    assert_approx_eq(
+        &result.snapshots()["superposition"],
+        &StateVector::from_complex_bases(vec![
+            Complex::from(FRAC_1_SQRT_2),
+            Complex::from(FRAC_1_SQRT_2),
+            Complex::from(0.0),
+            Complex::from(0.0),
+        ]),
+    );
+}
+
+#[test]
+fn test_observer_notifications() {
+    use qasmsim::grammar::lexer::Location;
+    use qasmsim::Observer;
+
+    #[derive(Default)]
+    struct EventLogger(Vec<String>);
+
+    impl Observer for EventLogger {
+        fn on_statement(&mut self, _location: Location) {
+            self.0.push("statement".into());
+        }
+
+        fn on_gate(&mut self, name: &str, qubits: &[usize], _params: &[f64]) {
+            self.0.push(format!("gate {} {:?}", name, qubits));
+        }
+
+        fn on_measure(&mut self, qubit: usize, register: &str, bit: usize, outcome: u64) {
+            self.0.push(format!(
+                "measure {} {}[{}] {}",
+                qubit, register, bit, outcome
+            ));
+        }
+
+        fn on_macro_enter(&mut self, name: &str, qubits: &[usize], params: &[f64]) {
+            self.0
+                .push(format!("enter {} {:?} {:?}", name, qubits, params));
+        }
+
+        fn on_macro_exit(&mut self, name: &str) {
+            self.0.push(format!("exit {}", name));
+        }
+    }
+
+    let source = "
+  OPENQASM 2.0;
+  gate flip a {
+    U(pi, 0, pi) a;
+  }
+  qreg q[2];
+  creg c[1];
+  flip q[1];
+  CX q[1], q[0];
+  measure q[0] -> c[0];
+  ";
+    let program = qasmsim::parse_and_link(source).unwrap();
+    let mut logger = EventLogger::default();
+    qasmsim::simulate_with(
+        &program,
+        SimulationOptions::new().with_observer(&mut logger),
+    )
+    .unwrap();
+    assert_eq!(
+        logger.0,
+        vec![
+            "statement",
+            "statement",
+            "statement",
+            "statement",
+            "enter flip [1] []",
+            "gate U [1]",
+            "exit flip",
+            "statement",
+            "gate CX [1, 0]",
+            "statement",
+            "measure 0 c[0] 1",
+        ]
+    );
+
+    let source = "
+  OPENQASM 2.0;
+  opaque gate magic a;
+  gate wrap a {
+    magic a;
+  }
+  qreg q[1];
+  wrap q[0];
+  ";
+    let program = qasmsim::parse_and_link(source).unwrap();
+    let mut logger = EventLogger::default();
+    let error = qasmsim::simulate_with(
+        &program,
+        SimulationOptions::new().with_observer(&mut logger),
+    )
+    .expect_err("opaque gates cannot be simulated");
+    assert_eq!(error.code(), "E0104");
+    assert_eq!(
+        logger.0,
+        vec![
+            "statement",
+            "statement",
+            "statement",
+            "statement",
+            "enter wrap [0] []",
+            "exit wrap",
+        ]
+    );
+}
@@ -4,7 +4,8 @@ extern crate qasmsim;
 
 use indoc::indoc;
 
-use qasmsim::{QasmSimError, QasmType};
+use qasmsim::statevector::{Complex, StateVector};
+use qasmsim::{EmbeddedResolver, LinkerOptions, QasmSimError, QasmType};
 
 #[test]
 fn test_calling_a_non_existing_gate() {
@@ -19,7 +20,8 @@ fn test_calling_a_non_existing_gate() {
     assert_eq!(
         error,
         QasmSimError::UndefinedGate {
-            source: "xxx q;\n",
+            source: "xxx q;\n".into(),
+            file: None,
             symbol_name: "xxx".into(),
             lineno: 3
         }
@@ -40,7 +42,8 @@ fn test_using_a_quantum_register_while_expecting_classical() {
     assert_eq!(
         error,
         QasmSimError::TypeMismatch {
-            source: "measure q -> q;\n",
+            source: "measure q -> q;\n".into(),
+            file: None,
             lineno: 4,
             symbol_name: "q".into(),
             expected: QasmType::ClassicalRegister
@@ -62,7 +65,8 @@ fn test_using_a_classical_register_when_expecting_quantum() {
     assert_eq!(
         error,
         QasmSimError::TypeMismatch {
-            source: "measure c -> c;\n",
+            source: "measure c -> c;\n".into(),
+            file: None,
             lineno: 4,
             symbol_name: "c".into(),
             expected: QasmType::QuantumRegister
@@ -84,7 +88,8 @@ fn test_passing_a_classical_register_when_expecting_quantum() {
     assert_eq!(
         error,
         QasmSimError::TypeMismatch {
-            source: "h c;\n",
+            source: "h c;\n".into(),
+            file: None,
             lineno: 4,
             symbol_name: "c".into(),
             expected: QasmType::QuantumRegister
@@ -106,7 +111,8 @@ fn test_passing_an_unexistent_register() {
     assert_eq!(
         error,
         QasmSimError::SymbolNotFound {
-            source: "h t;\n",
+            source: "h t;\n".into(),
+            file: None,
             lineno: 4,
             symbol_name: "t".into(),
             expected: QasmType::QuantumRegister
@@ -129,7 +135,8 @@ fn test_passing_an_unexistent_real_parameter() {
         error,
         QasmSimError::SymbolNotFound {
             symbol_name: "xxx".into(),
-            source: "u1(xxx) q;\n",
+            source: "u1(xxx) q;\n".into(),
+            file: None,
             lineno: 4,
             expected: QasmType::RealValue
         }
@@ -151,7 +158,8 @@ fn test_passing_a_register_instead_of_real_parameter() {
         error,
         QasmSimError::SymbolNotFound {
             symbol_name: "q".into(),
-            source: "u1(q) q;\n",
+            source: "u1(q) q;\n".into(),
+            file: None,
             lineno: 4,
             expected: QasmType::RealValue
         }
@@ -173,7 +181,8 @@ fn test_pass_more_real_arguments_than_expected() {
         error,
         QasmSimError::WrongNumberOfParameters {
             are_registers: false,
-            source: "u1(pi, pi, pi) q;\n",
+            source: "u1(pi, pi, pi) q;\n".into(),
+            file: None,
             symbol_name: "u1".into(),
             lineno: 4,
             expected: 1,
@@ -197,7 +206,8 @@ fn test_pass_more_registers_than_expected() {
         error,
         QasmSimError::WrongNumberOfParameters {
             are_registers: true,
-            source: "u1(pi) q, q, q;\n",
+            source: "u1(pi) q, q, q;\n".into(),
+            file: None,
             symbol_name: "u1".into(),
             lineno: 4,
             expected: 1,
@@ -220,7 +230,8 @@ fn test_index_out_of_bounds() {
     assert_eq!(
         error,
         QasmSimError::IndexOutOfBounds {
-            source: "h q[3];\n",
+            source: "h q[3];\n".into(),
+            file: None,
             symbol_name: "q".into(),
             lineno: 4,
             size: 2,
@@ -244,7 +255,8 @@ fn test_argument_expansion_with_different_size_registers() {
     assert_eq!(
         error,
         QasmSimError::RegisterSizeMismatch {
-            source: "cx q, r;\n",
+            source: "cx q, r;\n".into(),
+            file: None,
             lineno: 5,
             symbol_name: "cx".into(),
             sizes: vec![1, 2]
@@ -267,7 +279,8 @@ fn test_argument_expansion_in_measurement_with_different_size_registers() {
     assert_eq!(
         error,
         QasmSimError::RegisterSizeMismatch {
-            source: "measure q -> c;\n",
+            source: "measure q -> c;\n".into(),
+            file: None,
             lineno: 5,
             symbol_name: "measure".into(),
             sizes: vec![1, 2]
@@ -289,11 +302,11 @@ fn test_quantum_register_in_conditional() {
     let error = qasmsim::run(source, None).expect_err("should fail");
     assert_eq!(
         error,
-        QasmSimError::TypeMismatch {
-            source: "if (q==3) h q;\n",
+        QasmSimError::QuantumCondition {
+            source: "if (q==3) h q;\n".into(),
+            file: None,
             lineno: 5,
-            symbol_name: "q".into(),
-            expected: QasmType::ClassicalRegister
+            symbol_name: "q".into()
         }
     );
 }
@@ -313,7 +326,8 @@ fn test_non_existent_register_in_conditional() {
     assert_eq!(
         error,
         QasmSimError::SymbolNotFound {
-            source: "if (d==3) h q;\n",
+            source: "if (d==3) h q;\n".into(),
+            file: None,
             lineno: 5,
             symbol_name: "d".into(),
             expected: QasmType::ClassicalRegister
@@ -333,9 +347,424 @@ fn test_include_non_existent_lib() {
     assert_eq!(
         error,
         QasmSimError::LibraryNotFound {
-            source: "include \"nonexist.inc\";\n",
+            source: "include \"nonexist.inc\";\n".into(),
+            file: None,
             lineno: 2,
             libpath: "nonexist.inc".into()
         }
     );
 }
+
+#[test]
+fn test_include_cycle() {
+    let source = indoc!(
+        r#"
+  OPENQASM 2.0;
+  include "a.inc";
+  "#
+    );
+    let resolver = EmbeddedResolver::default()
+        .with_library("a.inc", r#"include "b.inc";"#)
+        .with_library("b.inc", r#"include "a.inc";"#);
+    let error = qasmsim::parse_and_link_with_resolver(source, resolver).expect_err("should fail");
+    assert_eq!(
+        error,
+        QasmSimError::IncludeCycle {
+            source: "include \"a.inc\";".into(),
+            file: Some("b.inc".into()),
+            lineno: 1,
+            chain: vec!["a.inc".into(), "b.inc".into(), "a.inc".into()]
+        }
+    );
+}
+
+#[test]
+fn test_syntax_error_in_library() {
+    let source = indoc!(
+        r#"
+  OPENQASM 2.0;
+  include "bad.inc";
+  "#
+    );
+    let resolver = EmbeddedResolver::default().with_library(
+        "bad.inc",
+        indoc!(
+            "
+  gate good q {}
+  gate bad q { U(0, 0, 0) q }
+  "
+        ),
+    );
+    let error = qasmsim::parse_and_link_with_resolver(source, resolver).expect_err("should fail");
+    if let QasmSimError::LibrarySyntaxError {
+        source,
+        file,
+        lineno,
+        libpath,
+        error: library_error,
+    } = &error
+    {
+        assert_eq!(*source, "include \"bad.inc\";\n");
+        assert_eq!(*file, None);
+        assert_eq!(*lineno, 2);
+        assert_eq!(libpath, "bad.inc");
+        assert_eq!(library_error.source, "gate bad q { U(0, 0, 0) q }\n");
+        assert_eq!(library_error.lineno, 2);
+        assert_eq!(library_error.startpos, 26);
+    } else {
+        panic!("expected a library syntax error, found {:?}", error);
+    }
+    assert!(format!("{}", error).ends_with("note: included at line 2: include \"bad.inc\";\n"));
+    assert!(format!("{}", error).contains(" --> bad.inc:2:27\n"));
+}
+
+#[test]
+fn test_redefinition_of_a_library_gate() {
+    let source = indoc!(
+        r#"
+  OPENQASM 2.0;
+  include "qelib1.inc";
+  gate h q {}
+  "#
+    );
+    let (program, source_map) =
+        qasmsim::parse_and_link_with_source_map(source, &LinkerOptions::new()).unwrap();
+    let error = qasmsim::simulate(&program).expect_err("should fail");
+    let error = QasmSimError::from((&source_map, error));
+    if let QasmSimError::RedefinitionError {
+        symbol_name,
+        file,
+        lineno,
+        previous_file,
+        ..
+    } = &error
+    {
+        assert_eq!(symbol_name, "h");
+        assert_eq!(*file, None);
+        assert_eq!(*lineno, 3);
+        assert_eq!(previous_file.as_deref(), Some("qelib1.inc"));
+    } else {
+        panic!("expected a redefinition error, found {:?}", error);
+    }
+    assert!(format!("{}", error).contains("of `qelib1.inc`"));
+}
+
+#[test]
+fn test_initial_state_of_wrong_width() {
+    let source = indoc!(
+        "
+  OPENQASM 2.0;
+  qreg q[2];
+  "
+    );
+    let initial_state = StateVector::new(1);
+    let error = qasmsim::run_from(source, None, initial_state).expect_err("should fail");
+    assert_eq!(
+        error,
+        QasmSimError::InitialStateWidthMismatch {
+            expected: 2,
+            given: 1
+        }
+    );
+}
+
+#[test]
+fn test_initial_state_not_normalized() {
+    let source = indoc!(
+        "
+  OPENQASM 2.0;
+  qreg q[1];
+  "
+    );
+    let initial_state =
+        StateVector::from_complex_bases(vec![Complex::from(1.0), Complex::from(1.0)]);
+    let error = qasmsim::run_from(source, None, initial_state).expect_err("should fail");
+    assert_eq!(error, QasmSimError::InitialStateNotNormalized);
+}
+
+#[test]
+fn test_too_many_qubits_fail_at_runtime() {
+    let source = indoc!(
+        "
+  OPENQASM 2.0;
+  qreg q[20];
+  qreg r[20];
+  U(0, 0, 0) q;
+  "
+    );
+    let program = qasmsim::parse_and_link(source).unwrap();
+    assert_eq!(qasmsim::check(&program), Ok(()));
+    let error = qasmsim::run(source, None).expect_err("should fail");
+    assert_eq!(
+        error,
+        QasmSimError::TooManyQubits {
+            qubits: 40,
+            max_qubits: qasmsim::MAX_QUBITS
+        }
+    );
+    assert_eq!(error.code(), "E0109");
+}
+
+#[test]
+fn test_errors_under_conditionals_that_never_hold() {
+    let source = indoc!(
+        "
+  OPENQASM 2.0;
+  qreg q[2];
+  creg c[2];
+  if (c==3) U(0, 0, 0) q[2];
+  "
+    );
+    let error = qasmsim::run(source, None).expect_err("should fail");
+    assert_eq!(
+        error,
+        QasmSimError::IndexOutOfBounds {
+            source: "if (c==3) U(0, 0, 0) q[2];\n".into(),
+            file: None,
+            lineno: 4,
+            symbol_name: "q".into(),
+            index: 2,
+            size: 2
+        }
+    );
+}
+
+#[test]
+fn test_check_returns_all_errors() {
+    let source = indoc!(
+        "
+  OPENQASM 2.0;
+  qreg q[2];
+  undefined q;
+  U(0, 0, 0) r;
+  "
+    );
+    let program = qasmsim::parse_and_link(source).unwrap();
+    let errors: Vec<QasmSimError> = qasmsim::check(&program)
+        .expect_err("should fail")
+        .into_iter()
+        .map(|error| QasmSimError::from((source, error)))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            QasmSimError::UndefinedGate {
+                source: "undefined q;\n".into(),
+                file: None,
+                lineno: 3,
+                symbol_name: "undefined".into()
+            },
+            QasmSimError::SymbolNotFound {
+                source: "U(0, 0, 0) r;\n".into(),
+                file: None,
+                lineno: 4,
+                symbol_name: "r".into(),
+                expected: QasmType::QuantumRegister
+            }
+        ]
+    );
+}
+
+#[test]
+fn test_recursive_gate() {
+    let source = indoc!(
+        "
+  OPENQASM 2.0;
+  gate loop a { loop a; }
+  qreg q[1];
+  loop q;
+  "
+    );
+    let error = qasmsim::run(source, None).expect_err("should fail");
+    assert_eq!(
+        error,
+        QasmSimError::RecursiveGate {
+            source: "gate loop a { loop a; }\n".into(),
+            file: None,
+            lineno: 2,
+            symbol_name: "loop".into()
+        }
+    );
+}
+
+#[test]
+fn test_gate_used_before_its_definition() {
+    let source = indoc!(
+        "
+  OPENQASM 2.0;
+  qreg q[1];
+  later q;
+  gate later a { U(0, 0, 0) a; }
+  "
+    );
+    let error = qasmsim::run(source, None).expect_err("should fail");
+    assert_eq!(
+        error,
+        QasmSimError::UseBeforeDefinition {
+            source: "later q;\n".into(),
+            file: None,
+            lineno: 3,
+            symbol_name: "later".into(),
+            definition_lineno: 4,
+            definition_file: None
+        }
+    );
+    assert!(format!("{}", error).contains("gate `later` is defined in line 4"));
+}
+
+#[test]
+fn test_undeclared_argument_in_gate_body() {
+    let source = indoc!(
+        "
+  OPENQASM 2.0;
+  gate g a { U(theta, 0, 0) b; }
+  "
+    );
+    let program = qasmsim::parse_and_link(source).unwrap();
+    let errors: Vec<QasmSimError> = qasmsim::check(&program)
+        .expect_err("should fail")
+        .into_iter()
+        .map(|error| QasmSimError::from((source, error)))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            QasmSimError::UndeclaredGateParameter {
+                source: "gate g a { U(theta, 0, 0) b; }\n".into(),
+                file: None,
+                lineno: 2,
+                gate_name: "g".into(),
+                symbol_name: "theta".into()
+            },
+            QasmSimError::UndeclaredGateArgument {
+                source: "gate g a { U(theta, 0, 0) b; }\n".into(),
+                file: None,
+                lineno: 2,
+                gate_name: "g".into(),
+                symbol_name: "b".into()
+            }
+        ]
+    );
+}
+
+#[test]
+fn test_duplicate_qubit_operands() {
+    let source = indoc!(
+        r#"
+  OPENQASM 2.0;
+  include "qelib1.inc";
+  qreg q[2];
+  cx q[0], q[0];
+  cx q, q[1];
+  "#
+    );
+    let program = qasmsim::parse_and_link(source).unwrap();
+    let errors: Vec<QasmSimError> = qasmsim::check(&program)
+        .expect_err("should fail")
+        .into_iter()
+        .map(|error| QasmSimError::from((source, error)))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            QasmSimError::QubitAliasing {
+                source: "cx q[0], q[0];\n".into(),
+                file: None,
+                lineno: 4,
+                gate_name: "cx".into(),
+                symbol_name: "q".into()
+            },
+            QasmSimError::QubitAliasing {
+                source: "cx q, q[1];\n".into(),
+                file: None,
+                lineno: 5,
+                gate_name: "cx".into(),
+                symbol_name: "q".into()
+            }
+        ]
+    );
+}
+
+#[test]
+fn test_degenerate_registers() {
+    let source = indoc!(
+        "
+  OPENQASM 2.0;
+  qreg q[0];
+  qreg r[40];
+  creg c[100000];
+  U(0, 0, 0) q;
+  "
+    );
+    let program = qasmsim::parse_and_link(source).unwrap();
+    let errors: Vec<QasmSimError> = qasmsim::check(&program)
+        .expect_err("should fail")
+        .into_iter()
+        .map(|error| QasmSimError::from((source, error)))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            QasmSimError::EmptyRegister {
+                source: "qreg q[0];\n".into(),
+                file: None,
+                lineno: 2,
+                symbol_name: "q".into()
+            },
+            QasmSimError::RegisterTooLarge {
+                source: "creg c[100000];\n".into(),
+                file: None,
+                lineno: 4,
+                symbol_name: "c".into(),
+                size: 100000,
+                max_size: 64
+            }
+        ]
+    );
+}
+
+#[test]
+fn test_classical_registers_fit_in_memory() {
+    let source = indoc!(
+        "
+  OPENQASM 2.0;
+  qreg q[1];
+  creg c[100];
+  measure q[0] -> c[70];
+  "
+    );
+    let error = qasmsim::run(source, None).expect_err("should fail");
+    assert_eq!(
+        error,
+        QasmSimError::RegisterTooLarge {
+            source: "creg c[100];\n".into(),
+            file: None,
+            lineno: 3,
+            symbol_name: "c".into(),
+            size: 100,
+            max_size: 64
+        }
+    );
+}
+
+#[test]
+fn test_errors_keep_their_codes() {
+    let source = indoc!(
+        "
+  OPENQASM 2.0;
+  qreg q[2];
+  qreg q[2];
+  gate loop a { loop a; }
+  undefined q;
+  U(0, 0, 0) q[2];
+  "
+    );
+    let program = qasmsim::parse_and_link(source).unwrap();
+    let errors = qasmsim::check(&program).expect_err("should fail");
+    let codes: Vec<&str> = errors.iter().map(|error| error.code()).collect();
+    assert_eq!(codes, vec!["E0201", "E0203", "E0104", "E0101"]);
+    for error in errors {
+        let code = error.code();
+        assert_eq!(QasmSimError::from((source, error)).code(), code);
+    }
+}
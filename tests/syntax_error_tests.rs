@@ -14,11 +14,12 @@ fn test_missing_semicolon_at_eof() {
     OPENQASM 2.0;
     qreg q[10]"
     );
-    let err = qasmsim::run(&source, None).unwrap_err();
+    let err = qasmsim::run(source, None).unwrap_err();
     assert_eq!(
         err,
         QasmSimError::UnexpectedEOF {
-            source: "qreg q[10]",
+            source: "qreg q[10]".into(),
+            file: None,
             lineno: 2,
             startpos: 10,
             endpos: None,
@@ -36,11 +37,12 @@ fn test_missing_semicolon_almost_at_eof() {
     qreg q[10]
   "
     );
-    let err = qasmsim::run(&source, None).unwrap_err();
+    let err = qasmsim::run(source, None).unwrap_err();
     assert_eq!(
         err,
         QasmSimError::UnexpectedEOF {
-            source: "qreg q[10]\n",
+            source: "qreg q[10]\n".into(),
+            file: None,
             lineno: 2,
             startpos: 10,
             endpos: None,
@@ -59,11 +61,12 @@ fn test_missing_semicolon_between_two_instructions() {
     qreg r[10];
   "
     );
-    let err = qasmsim::run(&source, None).unwrap_err();
+    let err = qasmsim::run(source, None).unwrap_err();
     assert_eq!(
         err,
         QasmSimError::UnexpectedToken {
-            source: "qreg r[10];\n",
+            source: "qreg r[10];\n".into(),
+            file: None,
             lineno: 3,
             startpos: 0,
             endpos: Some(4),
@@ -81,11 +84,12 @@ fn test_missing_bracket() {
     qreg q[10;
   "
     );
-    let err = qasmsim::run(&source, None).unwrap_err();
+    let err = qasmsim::run(source, None).unwrap_err();
     assert_eq!(
         err,
         QasmSimError::UnexpectedToken {
-            source: "qreg q[10;\n",
+            source: "qreg q[10;\n".into(),
+            file: None,
             lineno: 2,
             startpos: 9,
             endpos: Some(10),
@@ -102,11 +106,12 @@ fn test_missing_openqasm_header() {
     qreg q[10];
   "
     );
-    let err = qasmsim::run(&source, None).unwrap_err();
+    let err = qasmsim::run(source, None).unwrap_err();
     assert_eq!(
         err,
         QasmSimError::UnexpectedToken {
-            source: "qreg q[10];\n",
+            source: "qreg q[10];\n".into(),
+            file: None,
             lineno: 1,
             startpos: 0,
             endpos: Some(4),
@@ -124,11 +129,12 @@ fn test_misspelling_openqasm_header() {
     qreg q[10];
   "
     );
-    let err = qasmsim::run(&source, None).unwrap_err();
+    let err = qasmsim::run(source, None).unwrap_err();
     assert_eq!(
         err,
         QasmSimError::InvalidToken {
-            source: "OEPNQASM 2.0;\n",
+            source: "OEPNQASM 2.0;\n".into(),
+            file: None,
             lineno: 1,
             startpos: 0,
             endpos: None,
@@ -146,11 +152,12 @@ fn test_missing_openqasm_version() {
     qreg q[10];
   "
     );
-    let err = qasmsim::run(&source, None).unwrap_err();
+    let err = qasmsim::run(source, None).unwrap_err();
     assert_eq!(
         err,
         QasmSimError::UnexpectedToken {
-            source: "OPENQASM;\n",
+            source: "OPENQASM;\n".into(),
+            file: None,
             lineno: 1,
             startpos: 8,
             endpos: Some(9),
@@ -173,11 +180,12 @@ fn test_missing_arrow() {
     );
     // XXX: I have no idea why lalrpop is expecting something different than an
     // arrow here.
-    let err = qasmsim::run(&source, None).unwrap_err();
+    let err = qasmsim::run(source, None).unwrap_err();
     assert_eq!(
         err,
         QasmSimError::UnexpectedToken {
-            source: "measure q c;\n",
+            source: "measure q c;\n".into(),
+            file: None,
             lineno: 4,
             startpos: 10,
             endpos: Some(11),
@@ -186,3 +194,86 @@ fn test_missing_arrow() {
         }
     );
 }
+
+#[test]
+fn test_recovery_reports_all_syntax_errors() {
+    let source = indoc!(
+        "
+    OPENQASM 2.0;
+    qreg q[1];
+    creg c[1];
+    measure q c;
+    gate g a {
+        U(0, 0) a;
+        CX a a;
+    }
+    reset q;
+    "
+    );
+    let (program, errors) = qasmsim::grammar::parse_program_with_recovery(source);
+    assert_eq!(
+        errors,
+        vec![
+            QasmSimError::UnexpectedToken {
+                source: "measure q c;\n".into(),
+                file: None,
+                lineno: 4,
+                startpos: 10,
+                endpos: Some(11),
+                token: Some(Tok::Id { repr: "c".into() }),
+                expected: vec![
+                    "\",\"".into(),
+                    "\"->\"".into(),
+                    "\";\"".into(),
+                    "\"[\"".into()
+                ]
+            },
+            QasmSimError::UnexpectedToken {
+                source: "    U(0, 0) a;\n".into(),
+                file: None,
+                lineno: 6,
+                startpos: 10,
+                endpos: Some(11),
+                token: Some(Tok::RParent),
+                expected: vec!["\"+\"".into(), "\",\"".into(), "\"-\"".into()]
+            },
+            QasmSimError::UnexpectedToken {
+                source: "    CX a a;\n".into(),
+                file: None,
+                lineno: 7,
+                startpos: 9,
+                endpos: Some(10),
+                token: Some(Tok::Id { repr: "a".into() }),
+                expected: vec![
+                    "\",\"".into(),
+                    "\"->\"".into(),
+                    "\";\"".into(),
+                    "\"[\"".into()
+                ]
+            },
+        ]
+    );
+    assert_eq!(program.expect("a partial AST").program.len(), 4);
+}
+
+#[test]
+fn test_recovery_from_unterminated_programs() {
+    let source = indoc!(
+        "
+    OPENQASM 2.0;
+    qreg q[1]]
+    qreg r[1];
+    creg c[1]"
+    );
+    let (program, errors) = qasmsim::grammar::parse_program_with_recovery(source);
+    assert_eq!(program, None);
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(
+        errors[0],
+        QasmSimError::UnexpectedToken { lineno: 2, .. }
+    ));
+    assert!(matches!(
+        errors[1],
+        QasmSimError::UnexpectedEOF { lineno: 4, .. }
+    ));
+}